@@ -4,23 +4,131 @@ use axum::{
     Router,
 };
 
-use crate::handlers::{invoices_handler, payments_handler, quotes_handler};
+use crate::handlers::{analytics_handler, checkout_handler, events_handler, export_handler, invoices_handler, merchants_handler, payment_links_handler, payments_handler, prices_handler, quotes_handler, subscriptions_handler};
 use crate::AppState;
 
 pub fn create_routes() -> Router<AppState> {
     Router::new()
+        // Merchant routes
+        .route("/merchants", post(merchants_handler::create_merchant))
+        .route(
+            "/merchants/{wallet_address}",
+            get(merchants_handler::get_merchant)
+                .patch(merchants_handler::update_merchant)
+                .delete(merchants_handler::delete_merchant),
+        )
+        .route(
+            "/merchants/{wallet_address}/analytics",
+            get(analytics_handler::get_merchant_analytics),
+        )
+        .route(
+            "/merchants/{wallet_address}/export",
+            get(export_handler::export_merchant_data),
+        )
         // Invoice routes
         .route(
             "/merchants/{wallet_address}/invoices",
             post(invoices_handler::create_invoice)
                 .get(invoices_handler::list_invoices),
         )
-        .route("/invoices/{invoice_id}", get(invoices_handler::get_invoice))
+        .route(
+            "/invoices/{invoice_id}",
+            get(invoices_handler::get_invoice).patch(invoices_handler::update_invoice),
+        )
+        .route(
+            "/invoices/{invoice_id}/cancel",
+            post(invoices_handler::cancel_invoice),
+        )
+        .route(
+            "/invoices/{invoice_id}/quote",
+            get(invoices_handler::get_invoice_quote),
+        )
+        .route(
+            "/invoices/{invoice_id}/checkout",
+            get(checkout_handler::get_invoice_checkout),
+        )
+        .route(
+            "/invoices/{invoice_id}/payment-uri",
+            get(checkout_handler::get_invoice_payment_uri),
+        )
+        .route(
+            "/invoices/{invoice_id}/qr",
+            get(checkout_handler::get_invoice_qr_code),
+        )
+        .route(
+            "/invoices/{invoice_id}/timeline",
+            get(invoices_handler::get_invoice_timeline),
+        )
+        // Live invoice updates
+        .route(
+            "/invoices/{invoice_id}/events",
+            get(events_handler::stream_invoice_events),
+        )
+        .route(
+            "/invoices/{invoice_id}/events/ws",
+            get(events_handler::invoice_events_socket),
+        )
+        // Payment link routes
+        .route(
+            "/merchants/{wallet_address}/payment-links",
+            post(payment_links_handler::create_payment_link)
+                .get(payment_links_handler::list_payment_links),
+        )
+        .route(
+            "/payment-links/{link_id}",
+            get(payment_links_handler::get_payment_link)
+                .patch(payment_links_handler::update_payment_link),
+        )
+        .route(
+            "/payment-links/{link_id}/open",
+            post(payment_links_handler::open_payment_link),
+        )
+        // Subscription routes
+        .route(
+            "/merchants/{wallet_address}/subscription-plans",
+            post(subscriptions_handler::create_plan).get(subscriptions_handler::list_plans),
+        )
+        .route(
+            "/subscription-plans/{plan_id}",
+            get(subscriptions_handler::get_plan).patch(subscriptions_handler::update_plan),
+        )
+        .route(
+            "/subscription-plans/{plan_id}/subscriptions",
+            post(subscriptions_handler::create_subscription),
+        )
+        .route(
+            "/merchants/{wallet_address}/subscriptions",
+            get(subscriptions_handler::list_subscriptions),
+        )
+        .route(
+            "/subscriptions/{subscription_id}",
+            get(subscriptions_handler::get_subscription),
+        )
+        .route(
+            "/subscriptions/{subscription_id}/cancel",
+            post(subscriptions_handler::cancel_subscription),
+        )
         // Payment routes
+        .route(
+            "/invoices/{invoice_id}/payments",
+            get(payments_handler::list_invoice_payments),
+        )
         .route(
             "/invoices/{invoice_id}/payments/submit",
             post(payments_handler::submit_payment),
         )
+        .route("/payments", get(payments_handler::list_payments))
+        .route("/payments/{payment_id}", get(payments_handler::get_payment))
+        .route(
+            "/payments/{payment_id}/price",
+            get(payments_handler::get_payment_price),
+        )
+        .route(
+            "/merchants/{wallet_address}/payments",
+            get(payments_handler::list_merchant_payments),
+        )
         // Quote routes
         .route("/quotes", get(quotes_handler::get_quote))
+        // Price history
+        .route("/prices/history", get(prices_handler::get_price_history))
 }
\ No newline at end of file
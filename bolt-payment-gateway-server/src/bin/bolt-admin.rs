@@ -0,0 +1,268 @@
+//! Operator commands against the gateway's database, replacing edits in the Mongo shell.
+//!
+//! Connects with the same `MONGODB_URI` and `DATABASE_NAME` as the server. Every command,
+//! read-only ones included, is recorded in the `admin_actions` collection under the operator
+//! given by `--operator`, `BOLT_ADMIN_OPERATOR` or `USER`.
+use std::env;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Result};
+use bson::oid::ObjectId;
+use serde::{de::DeserializeOwned, Serialize};
+
+use bolt_payment_gateway_server::database::{MigrationRunner, MongoDBClient};
+use bolt_payment_gateway_server::models::{format_money_amount, InvoiceStatus, PaymentStatus};
+use bolt_payment_gateway_server::services::admin_service::AdminService;
+use bolt_payment_gateway_server::services::event_bus::EventBus;
+use bolt_payment_gateway_server::AppState;
+
+const USAGE: &str = "\
+Usage: bolt-admin [--operator NAME] <command>
+
+Commands:
+  invoice list <wallet_address> [--status STATUS]
+  invoice show <invoice_id>
+  invoice expire <invoice_id> [--reason TEXT]
+  invoice cancel <invoice_id> [--reason TEXT]
+  payment list [--status STATUS]            accepted payments by default
+  payment show <payment_id>
+  payment reject <payment_id> [--reason TEXT] [--force]
+  payment confirm <payment_id> <tx_id> <sender_address> [--reason TEXT]
+                                            record a broadcast payment the gateway could not confirm
+  merchant verify-payout <wallet_address> <payout_address> [--reason TEXT]
+                                            verify a payout address whose ownership was checked
+  indexes                                   create missing indexes
+  price                                     fetch the current BTC price
+  price status                              whether payments are halted by the price guard
+  price resume [--reason TEXT]              resume payments halted by the price guard
+  export <wallet_address> [--output FILE]   everything stored for a merchant, as JSON
+  migrate [--dry-run]                       apply pending schema migrations, or list what they would change";
+
+/// Options taking a value, everything else starting with `--` is a flag
+const VALUE_OPTIONS: [&str; 4] = ["--operator", "--reason", "--status", "--output"];
+
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| anyhow!("{} needs a value", arg))?;
+                parsed.options.push((arg, value));
+            } else if arg.starts_with("--") || arg == "-h" {
+                parsed.flags.push(arg);
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    /// Command line as recorded in the audit log, without the operator
+    fn command_line(&self) -> String {
+        let mut parts = self.positional.clone();
+        for (option, value) in &self.options {
+            if option != "--operator" {
+                parts.push(format!("{} {}", option, value));
+            }
+        }
+        parts.extend(self.flags.iter().cloned());
+        parts.join(" ")
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(tracing::Level::WARN)
+        .init();
+
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.positional.is_empty() || args.flag("--help") || args.flag("-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(operator) = args
+        .option("--operator")
+        .map(str::to_string)
+        .or_else(|| env::var("BOLT_ADMIN_OPERATOR").ok())
+        .or_else(|| env::var("USER").ok())
+        .filter(|operator| !operator.trim().is_empty())
+    else {
+        eprintln!("Set the operator with --operator or BOLT_ADMIN_OPERATOR");
+        return ExitCode::FAILURE;
+    };
+
+    let mongodb_uri = env::var("MONGODB_URI").unwrap_or_else(|_| "mongodb://mongo:27017".to_string());
+    let database_name = env::var("DATABASE_NAME").unwrap_or_else(|_| "bolt_payment_gateway-dev".to_string());
+    let mongodb_client = match MongoDBClient::new(&mongodb_uri, &database_name).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Failed to connect to MongoDB at {}: {}", mongodb_uri, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let app_state = AppState::from_env(mongodb_client.get_database(), EventBus::new());
+    let migrations = MigrationRunner::new(mongodb_client.get_database(), format!("bolt-admin:{}", operator));
+    let admin = AdminService::new(app_state, operator);
+
+    let result = run(&admin, &migrations, &args).await;
+
+    let target = args.positional.iter().skip(1).find(|arg| !is_subcommand(arg)).map(String::as_str);
+    let error = result.as_ref().err().map(|e| format!("{:#}", e));
+    if let Some(error) = &error {
+        eprintln!("{}", error);
+    }
+    if let Err(e) = admin.record(&args.command_line(), target, args.option("--reason"), error.clone()).await {
+        eprintln!("Failed to record the command in the admin audit log: {:#}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if error.is_some() { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+fn is_subcommand(arg: &str) -> bool {
+    matches!(arg, "list" | "show" | "expire" | "cancel" | "reject" | "status" | "resume" | "confirm" | "verify-payout")
+}
+
+async fn run(admin: &AdminService, migrations: &MigrationRunner, args: &Args) -> Result<()> {
+    let positional: Vec<&str> = args.positional.iter().map(String::as_str).collect();
+    let reason = args.option("--reason");
+
+    match positional.as_slice() {
+        ["invoice", "list", wallet_address] => {
+            let status = args.option("--status").map(parse_status::<InvoiceStatus>).transpose()?;
+            print_json(&admin.list_invoices(wallet_address, status).await?)
+        }
+        ["invoice", "show", invoice_id] => print_json(&admin.invoice(&parse_id(invoice_id)?).await?),
+        ["invoice", "expire", invoice_id] => {
+            print_json(&admin.expire_invoice(&parse_id(invoice_id)?, reason).await?)
+        }
+        ["invoice", "cancel", invoice_id] => {
+            print_json(&admin.cancel_invoice(&parse_id(invoice_id)?, reason).await?)
+        }
+        ["payment", "list"] => {
+            let status = args
+                .option("--status")
+                .map(parse_status::<PaymentStatus>)
+                .transpose()?
+                .unwrap_or(PaymentStatus::Accepted);
+            print_json(&admin.list_payments(status).await?)
+        }
+        ["payment", "show", payment_id] => print_json(&admin.payment(&parse_id(payment_id)?).await?),
+        ["payment", "reject", payment_id] => print_json(
+            &admin
+                .reject_payment(&parse_id(payment_id)?, reason, args.flag("--force"))
+                .await?,
+        ),
+        ["payment", "confirm", payment_id, tx_id, sender_address] => print_json(
+            &admin
+                .confirm_payment(&parse_id(payment_id)?, tx_id, sender_address, reason)
+                .await?,
+        ),
+        ["merchant", "verify-payout", wallet_address, payout_address] => {
+            print_json(&admin.verify_payout_address(wallet_address, payout_address).await?)
+        }
+        ["indexes"] => {
+            admin.create_indexes().await?;
+            println!("Indexes created");
+            Ok(())
+        }
+        ["price"] => {
+            let price = admin.btc_price().await?;
+            println!("BTC/USD {}", format_money_amount(price));
+            Ok(())
+        }
+        ["price", "status"] => match admin.price_guard().await? {
+            Some(state) => print_json(&state),
+            None => {
+                println!("Payments were never halted by the price guard");
+                Ok(())
+            }
+        },
+        ["price", "resume"] => {
+            let halt = admin.resume_payments(reason).await?;
+            println!("Payments resumed, halted since {}: {}", halt.tripped_at, halt);
+            Ok(())
+        }
+        ["export", wallet_address] => {
+            let export = admin.export_merchant(wallet_address).await?;
+            match args.option("--output") {
+                Some(path) => {
+                    std::fs::write(path, serde_json::to_vec_pretty(&export)?)?;
+                    eprintln!(
+                        "Exported {} invoices and {} payments to {}",
+                        export.invoices.len(),
+                        export.payments.len(),
+                        path
+                    );
+                    Ok(())
+                }
+                None => print_json(&export),
+            }
+        }
+        ["migrate"] if args.flag("--dry-run") => {
+            let reports = migrations.dry_run().await?;
+            if reports.is_empty() {
+                println!("No pending migrations");
+            }
+            for report in reports {
+                println!("{:>4} {}: {} documents to change", report.version, report.name, report.documents);
+            }
+            Ok(())
+        }
+        ["migrate"] => {
+            let reports = migrations.run().await?;
+            if reports.is_empty() {
+                println!("No pending migrations");
+            }
+            for report in reports {
+                println!("{:>4} {}: {} documents changed", report.version, report.name, report.documents);
+            }
+            Ok(())
+        }
+        _ => bail!("Unknown command: {}\n\n{}", args.positional.join(" "), USAGE),
+    }
+}
+
+fn parse_id(id: &str) -> Result<ObjectId> {
+    ObjectId::parse_str(id).map_err(|_| anyhow!("{} is not a valid ID", id))
+}
+
+/// Parse a status by its API name, e.g. `expired`
+fn parse_status<S: DeserializeOwned>(status: &str) -> Result<S> {
+    serde_json::from_value(serde_json::Value::String(status.to_string()))
+        .map_err(|_| anyhow!("Unknown status: {}", status))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
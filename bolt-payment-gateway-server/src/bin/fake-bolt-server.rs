@@ -0,0 +1,38 @@
+// src/bin/fake-bolt-server.rs
+//! Fake Bolt protocol API for end-to-end testing.
+//!
+//! Point the gateway at it with `BOLT_PROTOCOL_URL=http://127.0.0.1:3999` and script its
+//! behaviors over HTTP, for instance:
+//! `curl -X POST localhost:3999/__fake/script -H 'content-type: application/json' -d '[{"behavior":"fail","status":400,"body":"bad tx"}]'`
+use std::env;
+use std::net::SocketAddr;
+
+use bolt_payment_gateway_server::testing::{FakeBoltBehavior, FakeBoltServer};
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let addr: SocketAddr = env::var("FAKE_BOLT_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:3999".to_string())
+        .parse()
+        .expect("FAKE_BOLT_ADDR must be a socket address");
+
+    // Behavior once the script is exhausted, as JSON, accepting everything by default
+    let default = env::var("FAKE_BOLT_DEFAULT")
+        .ok()
+        .map(|json| {
+            serde_json::from_str::<FakeBoltBehavior>(&json)
+                .expect("FAKE_BOLT_DEFAULT must be a behavior in JSON")
+        });
+
+    let server = FakeBoltServer::bind(addr)
+        .await
+        .expect("Failed to bind the fake Bolt server");
+    if let Some(default) = default {
+        server.set_default(default);
+    }
+
+    println!("🧪 Fake Bolt server running on {}", server.base_url());
+    server.wait().await;
+}
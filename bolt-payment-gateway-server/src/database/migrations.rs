@@ -0,0 +1,301 @@
+// src/database/migrations.rs
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, Collection, Database};
+use serde::{Deserialize, Serialize};
+
+/// Collection recording applied migrations, plus the lock document while migrations run
+const COLLECTION: &str = "schema_migrations";
+
+/// `_id` of the lock document, migration records are keyed by their numeric version
+const LOCK_ID: &str = "lock";
+
+/// A lock older than this is considered abandoned by a crashed process and can be taken over
+const LOCK_LEASE: Duration = Duration::from_secs(15 * 60);
+
+/// How long to wait for another process to finish migrating before giving up
+const LOCK_WAIT: Duration = Duration::from_secs(5 * 60);
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A step bringing stored documents to the shape the current models expect.
+///
+/// Steps are applied in `version` order, each exactly once. They must tolerate running
+/// against documents already in the new shape, as a step interrupted midway is run again.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    /// Number of documents the step would change, shown by dry runs
+    pending: for<'a> fn(&'a Database) -> BoxFuture<'a, Result<u64>>,
+    /// Apply the step, returns the number of documents changed
+    apply: for<'a> fn(&'a Database) -> BoxFuture<'a, Result<u64>>,
+}
+
+/// Every migration, in the order they are applied. Append only: released steps are never
+/// edited or renumbered, a correction is a new step.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "backfill_document_versions",
+        pending: |database| Box::pin(count_unversioned(database)),
+        apply: |database| Box::pin(backfill_versions(database)),
+    },
+    Migration {
+        version: 2,
+        name: "backfill_payment_wallet_addresses",
+        pending: |database| Box::pin(count_payments_without_wallet_address(database)),
+        apply: |database| Box::pin(backfill_payment_wallet_addresses(database)),
+    },
+];
+
+/// Record of an applied migration in `schema_migrations`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    #[serde(rename = "_id")]
+    pub version: i32,
+    pub name: String,
+    pub modified: i64,
+    pub duration_ms: i64,
+    /// Process that applied the migration
+    pub applied_by: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+/// Outcome of a migration in a run, or what it would change in a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub version: i32,
+    pub name: &'static str,
+    /// Documents changed, or still to change in a dry run
+    pub documents: u64,
+}
+
+/// Applies pending migrations, holding the lock in `schema_migrations` so that replicas
+/// starting together do not migrate concurrently.
+#[derive(Clone)]
+pub struct MigrationRunner {
+    database: Database,
+    collection: Collection<AppliedMigration>,
+    /// Identifies the lock holder, e.g. the instance ID or `bolt-admin:<operator>`
+    owner: String,
+}
+
+impl MigrationRunner {
+    pub fn new(database: &Database, owner: impl Into<String>) -> Self {
+        Self {
+            database: database.clone(),
+            collection: database.collection::<AppliedMigration>(COLLECTION),
+            owner: owner.into(),
+        }
+    }
+
+    pub async fn applied(&self) -> Result<Vec<AppliedMigration>> {
+        let filter = doc! { "_id": { "$type": "number" } };
+        let cursor = self.collection.find(filter).sort(doc! { "_id": 1 }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Migrations not applied yet, in the order they will run.
+    ///
+    /// A database migrated by a newer release is served with a warning: documents only gain fields
+    /// this release ignores, see the README. Fails on a gap, a migration this release knows that is
+    /// missing while a later one was applied, as the documents are then in neither shape.
+    pub async fn pending(&self) -> Result<Vec<&'static Migration>> {
+        let applied = self.applied().await?;
+        let known = MIGRATIONS.last().map_or(0, |migration| migration.version);
+        if let Some(newest) = applied.iter().filter(|applied| applied.version > known).max_by_key(|applied| applied.version) {
+            tracing::warn!(
+                "Database schema is at version {} ({}), newer than the latest this release knows ({})",
+                newest.version,
+                newest.name,
+                known
+            );
+        }
+
+        let pending: Vec<&'static Migration> = MIGRATIONS
+            .iter()
+            .filter(|migration| !applied.iter().any(|applied| applied.version == migration.version))
+            .collect();
+        if let (Some(missing), Some(later)) = (pending.first(), applied.iter().map(|applied| applied.version).max())
+            && missing.version < later
+        {
+            bail!(
+                "Migration {} ({}) was never applied but version {} was, refusing to run against the database",
+                missing.version,
+                missing.name,
+                later
+            );
+        }
+        Ok(pending)
+    }
+
+    /// What the pending migrations would change, without changing anything or taking the lock
+    pub async fn dry_run(&self) -> Result<Vec<MigrationReport>> {
+        let mut reports = Vec::new();
+        for migration in self.pending().await? {
+            let documents = (migration.pending)(&self.database)
+                .await
+                .with_context(|| format!("Failed to inspect migration {} ({})", migration.version, migration.name))?;
+            reports.push(MigrationReport { version: migration.version, name: migration.name, documents });
+        }
+        Ok(reports)
+    }
+
+    /// Apply the pending migrations in order under the lock, stopping at the first failure
+    pub async fn run(&self) -> Result<Vec<MigrationReport>> {
+        // Skip the lock when there is nothing to do, the common case on every start
+        if self.pending().await?.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.lock().await?;
+        let result = self.run_locked().await;
+        if let Err(e) = self.unlock().await {
+            tracing::warn!("Failed to release the migration lock, it expires on its own: {}", e);
+        }
+        result
+    }
+
+    async fn run_locked(&self) -> Result<Vec<MigrationReport>> {
+        let mut reports = Vec::new();
+        // Read again under the lock, another process may have migrated while we waited
+        for migration in self.pending().await? {
+            let started = Instant::now();
+            let documents = (migration.apply)(&self.database)
+                .await
+                .with_context(|| format!("Migration {} ({}) failed", migration.version, migration.name))?;
+
+            let applied = AppliedMigration {
+                version: migration.version,
+                name: migration.name.to_string(),
+                modified: documents as i64,
+                duration_ms: started.elapsed().as_millis() as i64,
+                applied_by: self.owner.clone(),
+                applied_at: Utc::now(),
+            };
+            self.collection.insert_one(&applied).await.with_context(|| {
+                format!("Migration {} ({}) applied but could not be recorded", migration.version, migration.name)
+            })?;
+
+            tracing::info!(
+                "Applied migration {} ({}), {} documents changed",
+                migration.version,
+                migration.name,
+                documents
+            );
+            reports.push(MigrationReport { version: migration.version, name: migration.name, documents });
+        }
+        Ok(reports)
+    }
+
+    async fn lock(&self) -> Result<()> {
+        let locks = self.collection.clone_with_type::<bson::Document>();
+        let deadline = Instant::now() + LOCK_WAIT;
+
+        loop {
+            let now = Utc::now();
+            let expires_at = bson::DateTime::from_chrono(now + LOCK_LEASE);
+            let lock = doc! { "_id": LOCK_ID, "owner": &self.owner, "expires_at": expires_at };
+
+            match locks.insert_one(&lock).await {
+                Ok(_) => return Ok(()),
+                Err(e) if e.to_string().contains("E11000") => {}
+                Err(e) => return Err(e).context("Failed to take the migration lock"),
+            }
+
+            // Take over a lock left behind by a process that died while migrating
+            let abandoned = doc! { "_id": LOCK_ID, "expires_at": { "$lt": bson::DateTime::from_chrono(now) } };
+            let update = doc! { "$set": { "owner": &self.owner, "expires_at": expires_at } };
+            if locks.update_one(abandoned, update).await?.modified_count == 1 {
+                tracing::warn!("Took over an expired migration lock");
+                return Ok(());
+            }
+
+            if Instant::now() >= deadline {
+                let holder = locks.find_one(doc! { "_id": LOCK_ID }).await?;
+                let holder = holder.as_ref().and_then(|lock| lock.get_str("owner").ok()).unwrap_or("unknown");
+                bail!("Timed out waiting for the migration lock held by {}", holder);
+            }
+            tracing::info!("Waiting for another process to finish migrating");
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn unlock(&self) -> Result<()> {
+        let locks = self.collection.clone_with_type::<bson::Document>();
+        locks.delete_one(doc! { "_id": LOCK_ID, "owner": &self.owner }).await?;
+        Ok(())
+    }
+}
+
+/// Collections whose documents carry an optimistic concurrency `version`
+const VERSIONED_COLLECTIONS: [&str; 2] = ["invoices", "payments"];
+
+async fn count_unversioned(database: &Database) -> Result<u64> {
+    let mut count = 0;
+    for name in VERSIONED_COLLECTIONS {
+        count += database
+            .collection::<bson::Document>(name)
+            .count_documents(doc! { "version": { "$exists": false } })
+            .await?;
+    }
+    Ok(count)
+}
+
+/// Documents stored before versions existed read as version 0, store it explicitly
+async fn backfill_versions(database: &Database) -> Result<u64> {
+    let mut modified = 0;
+    for name in VERSIONED_COLLECTIONS {
+        modified += database
+            .collection::<bson::Document>(name)
+            .update_many(doc! { "version": { "$exists": false } }, doc! { "$set": { "version": 0_i64 } })
+            .await?
+            .modified_count;
+    }
+    Ok(modified)
+}
+
+async fn count_payments_without_wallet_address(database: &Database) -> Result<u64> {
+    Ok(database
+        .collection::<bson::Document>("payments")
+        .count_documents(doc! { "wallet_address": { "$exists": false } })
+        .await?)
+}
+
+/// Copy the merchant of each payment's invoice onto the payment, payments of missing invoices are left as is
+async fn backfill_payment_wallet_addresses(database: &Database) -> Result<u64> {
+    let payments = database.collection::<bson::Document>("payments");
+    let before = count_payments_without_wallet_address(database).await?;
+    let pipeline = vec![
+        doc! { "$match": { "wallet_address": { "$exists": false } } },
+        doc! { "$lookup": {
+            "from": "invoices",
+            "localField": "invoice_id",
+            "foreignField": "_id",
+            "as": "invoice",
+        } },
+        doc! { "$project": { "wallet_address": { "$first": "$invoice.wallet_address" } } },
+        doc! { "$match": { "wallet_address": { "$type": "string" } } },
+        doc! { "$merge": { "into": "payments", "on": "_id", "whenMatched": "merge", "whenNotMatched": "discard" } },
+    ];
+    payments.aggregate(pipeline).await?;
+    Ok(before - count_payments_without_wallet_address(database).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered_and_unique() {
+        assert!(!MIGRATIONS.is_empty());
+        assert!(MIGRATIONS[0].version >= 1);
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version, "{} before {}", pair[0].name, pair[1].name);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 // src/database/mod.rs
+pub mod migrations;
 pub mod mongodb;
 pub mod repositories;
 
+pub use migrations::*;
 pub use mongodb::*;
 pub use repositories::*;
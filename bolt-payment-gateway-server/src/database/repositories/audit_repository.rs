@@ -0,0 +1,90 @@
+// src/database/repositories/audit_repository.rs
+use anyhow::Result;
+use bson;
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database, IndexModel, bson::doc, options::IndexOptions};
+
+use crate::models::{AdminAction, AuditEvent};
+
+#[derive(Clone)]
+pub struct AuditRepository {
+    collection: Collection<AuditEvent>,
+    admin_actions: Collection<AdminAction>,
+}
+
+impl AuditRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<AuditEvent>("audit_events");
+        let admin_actions = database.collection::<AdminAction>("admin_actions");
+        Self { collection, admin_actions }
+    }
+
+    /// Creates the indexes used to read an invoice timeline and an operator's actions in order
+    pub async fn create_indexes(&self) -> Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "invoice_id": 1, "timestamp": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("invoice_timeline_index".to_string())
+                    .build(),
+            )
+            .build();
+
+        self.collection.create_index(index).await?;
+
+        let index = IndexModel::builder()
+            .keys(doc! { "operator": 1, "timestamp": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("operator_actions_index".to_string())
+                    .build(),
+            )
+            .build();
+        self.admin_actions.create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn record(&self, event: &AuditEvent) -> Result<()> {
+        self.collection.insert_one(event).await?;
+        Ok(())
+    }
+
+    /// Record an event without failing the caller; the state change it describes has already happened.
+    pub async fn record_or_log(&self, event: &AuditEvent) {
+        if let Err(e) = self.record(event).await {
+            tracing::error!(
+                "Failed to record audit event for {:?} {} ({:?} -> {}): {}",
+                event.entity_type,
+                event.entity_id,
+                event.old_state,
+                event.new_state,
+                e
+            );
+        }
+    }
+
+    /// All events of an invoice and its payments, oldest first
+    pub async fn find_by_invoice_id(
+        &self,
+        invoice_id: &bson::oid::ObjectId,
+    ) -> Result<Vec<AuditEvent>> {
+        let filter = doc! { "invoice_id": invoice_id };
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "timestamp": 1 })
+            .await?;
+        let mut events = Vec::new();
+
+        while let Some(event) = cursor.try_next().await? {
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    pub async fn record_admin_action(&self, action: &AdminAction) -> Result<()> {
+        self.admin_actions.insert_one(action).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+// src/database/repositories/error.rs
+use std::fmt;
+
+use crate::models::IllegalTransition;
+
+/// Error returned by conditional status updates.
+#[derive(Debug)]
+pub enum StatusUpdateError<S> {
+    /// The requested change is not a legal edge of the state machine.
+    IllegalTransition(IllegalTransition<S>),
+    /// The document was not in the `expected` status when the write was applied,
+    /// either because it does not exist or because another writer changed it first.
+    Conflict { expected: S, to: S },
+    /// The document is still in the expected status but was updated since it was read at `version`,
+    /// the caller can read it again and retry.
+    VersionConflict { version: i64 },
+    /// The underlying database operation failed.
+    Database(anyhow::Error),
+}
+
+impl<S: fmt::Debug> fmt::Display for StatusUpdateError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusUpdateError::IllegalTransition(e) => write!(f, "{}", e),
+            StatusUpdateError::Conflict { expected, to } => write!(
+                f,
+                "status conflict: expected {:?} when moving to {:?}",
+                expected, to
+            ),
+            StatusUpdateError::VersionConflict { version } => write!(
+                f,
+                "version conflict: updated by another writer since version {}",
+                version
+            ),
+            StatusUpdateError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl<S: fmt::Debug> std::error::Error for StatusUpdateError<S> {}
+
+impl<S> From<IllegalTransition<S>> for StatusUpdateError<S> {
+    fn from(e: IllegalTransition<S>) -> Self {
+        StatusUpdateError::IllegalTransition(e)
+    }
+}
+
+impl<S> From<mongodb::error::Error> for StatusUpdateError<S> {
+    fn from(e: mongodb::error::Error) -> Self {
+        StatusUpdateError::Database(e.into())
+    }
+}
+
+impl<S> From<bson::ser::Error> for StatusUpdateError<S> {
+    fn from(e: bson::ser::Error) -> Self {
+        StatusUpdateError::Database(e.into())
+    }
+}
+
+/// Filter matching a document still at `version`.
+/// Documents stored before versions existed have no `version` field and match version 0.
+pub(crate) fn version_filter(version: i64) -> bson::Document {
+    if version == 0 {
+        bson::doc! { "version": { "$in": [0_i64, bson::Bson::Null] } }
+    } else {
+        bson::doc! { "version": version }
+    }
+}
@@ -1,23 +1,44 @@
 // src/database/repositories/invoice_repository.rs
-use mongodb::{Collection, Database, bson::doc};
+use mongodb::{Collection, Cursor, Database, bson::doc};
 use futures::stream::TryStreamExt;
 use anyhow::Result;
 use bson;
-use crate::models::Invoice;
+use crate::database::{version_filter, AuditRepository, StatusUpdateError};
+use crate::models::{AnalyticsGranularity, AuditContext, AuditEntityType, AuditEvent, DomainEvent, ExportRow, Invoice, InvoiceAmendment, InvoiceQuote, InvoiceStatus, MerchantAnalytics, StateMachine};
+use crate::services::event_bus::EventBus;
 
 #[derive(Clone)]
 pub struct InvoiceRepository {
     collection: Collection<Invoice>,
+    audit_repository: AuditRepository,
+    event_bus: EventBus,
 }
 
 impl InvoiceRepository {
-    pub fn new(database: &Database) -> Self {
+    pub fn new(database: &Database, event_bus: EventBus) -> Self {
         let collection = database.collection::<Invoice>("invoices");
-        Self { collection }
+        let audit_repository = AuditRepository::new(database);
+        Self { collection, audit_repository, event_bus }
     }
 
-    pub async fn create(&self, invoice: &Invoice) -> Result<()> {
+    pub async fn create(&self, invoice: &Invoice, context: &AuditContext) -> Result<()> {
         self.collection.insert_one(invoice).await?;
+
+        let event = AuditEvent::new(
+            AuditEntityType::Invoice,
+            invoice.id,
+            invoice.id,
+            None,
+            invoice.status,
+            context,
+        );
+        self.audit_repository.record_or_log(&event).await;
+        self.event_bus.publish_local(DomainEvent::InvoiceStatusChanged {
+            invoice_id: invoice.id,
+            from: None,
+            to: invoice.status,
+            timestamp: event.timestamp,
+        });
         Ok(())
     }
 
@@ -39,11 +60,312 @@ impl InvoiceRepository {
         Ok(invoices)
     }
 
-    pub async fn update_status(&self, invoice_id: &bson::oid::ObjectId, status: crate::models::InvoiceStatus) -> Result<bool> {
-        let filter = doc! { "_id": invoice_id };
-        let update = doc! { "$set": { "status": bson::to_bson(&status)? } };
-        
-        let result = self.collection.update_one(filter, update).await?;
-        Ok(result.modified_count > 0)
+    /// Aggregate the invoices a merchant created in `[from, to)` with their confirmed payments.
+    /// Periods of the volume series start at midnight in `timezone` (an Olson name or UTC offset).
+    pub async fn merchant_analytics(
+        &self,
+        wallet_address: &str,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        granularity: AnalyticsGranularity,
+        timezone: &str,
+    ) -> Result<MerchantAnalytics> {
+        let is_paid = doc! { "$in": ["$status", ["paid", "settled"]] };
+        let paid_in_sbtc = doc! { "$and": [is_paid.clone(), "$paid_in_sbtc"] };
+        let count_status = |status: &str| doc! { "$sum": { "$cond": [{ "$eq": ["$status", status] }, 1, 0] } };
+
+        let mut period = doc! { "date": "$paid_at", "unit": granularity.unit(), "timezone": timezone };
+        if granularity == AnalyticsGranularity::Week {
+            period.insert("startOfWeek", "monday");
+        }
+
+        // Timestamps are stored as RFC 3339 strings and converted to dates here
+        let pipeline = vec![
+            doc! { "$match": { "wallet_address": wallet_address } },
+            doc! { "$addFields": { "created": { "$dateFromString": { "dateString": "$created_at" } } } },
+            doc! { "$match": { "created": {
+                "$gte": bson::DateTime::from_chrono(from),
+                "$lt": bson::DateTime::from_chrono(to),
+            } } },
+            doc! { "$lookup": {
+                "from": "payments",
+                "localField": "_id",
+                "foreignField": "invoice_id",
+                "as": "payments",
+            } },
+            doc! { "$addFields": { "payment": { "$first": { "$filter": {
+                "input": "$payments",
+                "cond": { "$eq": ["$$this.status", "confirmed"] },
+            } } } } },
+            doc! { "$addFields": {
+                "paid_at": { "$dateFromString": { "dateString": "$payment.received_at", "onNull": null } },
+                // Only sBTC payments are in satoshis, other tokens are left out of the amounts received
+                "paid_in_sbtc": { "$eq": ["$payment.asset", "sBTC"] },
+            } },
+            doc! { "$facet": {
+                "totals": [
+                    { "$group": {
+                        "_id": "$settlement_asset",
+                        "created": { "$sum": 1 },
+                        "paid": { "$sum": { "$cond": [is_paid.clone(), 1, 0] } },
+                        "expired": count_status("expired"),
+                        "cancelled": count_status("cancelled"),
+                        "gross_volume": { "$sum": { "$cond": [is_paid.clone(), "$amount", 0] } },
+                        "sats_received": { "$sum": { "$cond": [paid_in_sbtc.clone(), "$payment.amount", 0] } },
+                        "sbtc_volume": { "$sum": { "$cond": [paid_in_sbtc.clone(), "$amount", 0] } },
+                        "avg_time_to_pay_ms": { "$avg": { "$cond": [
+                            { "$and": [is_paid.clone(), "$paid_at"] },
+                            { "$subtract": ["$paid_at", "$created"] },
+                            null,
+                        ] } },
+                    } },
+                    { "$sort": { "_id": 1 } },
+                ],
+                "series": [
+                    { "$match": { "status": { "$in": ["paid", "settled"] }, "paid_at": { "$ne": null } } },
+                    { "$group": {
+                        "_id": {
+                            "settlement_asset": "$settlement_asset",
+                            "period": { "$dateTrunc": period },
+                        },
+                        "paid": { "$sum": 1 },
+                        "gross_volume": { "$sum": "$amount" },
+                        "sats_received": { "$sum": { "$cond": ["$paid_in_sbtc", "$payment.amount", 0] } },
+                        "sbtc_volume": { "$sum": { "$cond": ["$paid_in_sbtc", "$amount", 0] } },
+                    } },
+                    { "$sort": { "_id.period": 1, "_id.settlement_asset": 1 } },
+                ],
+            } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let result = cursor
+            .try_next()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Analytics aggregation returned no result"))?;
+        Ok(bson::from_document(result)?)
+    }
+
+    /// Cursor over the invoices a merchant created in `[from, to)` joined with their payments,
+    /// one row per payment (or per invoice without payments), oldest first
+    pub async fn export_rows(
+        &self,
+        wallet_address: &str,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Cursor<ExportRow>> {
+        let pipeline = vec![
+            doc! { "$match": { "wallet_address": wallet_address } },
+            doc! { "$addFields": { "created": { "$dateFromString": { "dateString": "$created_at" } } } },
+            doc! { "$match": { "created": {
+                "$gte": bson::DateTime::from_chrono(from),
+                "$lt": bson::DateTime::from_chrono(to),
+            } } },
+            doc! { "$sort": { "created": 1, "_id": 1 } },
+            doc! { "$lookup": {
+                "from": "payments",
+                "localField": "_id",
+                "foreignField": "invoice_id",
+                "as": "payment",
+                "pipeline": [{ "$sort": { "received_at": 1 } }],
+            } },
+            doc! { "$unwind": { "path": "$payment", "preserveNullAndEmptyArrays": true } },
+            doc! { "$project": {
+                "_id": 0,
+                "invoice_id": "$_id",
+                "merchant_order_id": 1,
+                "invoice_status": "$status",
+                "invoice_amount": "$amount",
+                "settlement_asset": 1,
+                "created_at": 1,
+                "expires_at": 1,
+                "description": 1,
+                "buyer_email": 1,
+                "buyer_reference": 1,
+                "payment_id": "$payment._id",
+                "payment_status": "$payment.status",
+                "payment_asset": "$payment.asset",
+                "payment_amount": "$payment.amount",
+                "btc_price": "$payment.btc_price",
+                "tx_id": "$payment.tx_id",
+                "sender_address": "$payment.sender_address",
+                "recipient_address": { "$ifNull": ["$payment.recipient_address", "$recipient_address"] },
+                "received_at": "$payment.received_at",
+            } },
+        ];
+
+        let cursor = self
+            .collection
+            .aggregate(pipeline)
+            .allow_disk_use(true)
+            .with_type::<ExportRow>()
+            .await?;
+        Ok(cursor)
+    }
+
+    /// Store a newly locked quote, only while the invoice is still awaiting payment
+    /// and unchanged since `invoice` was read
+    pub async fn set_quote(
+        &self,
+        invoice: &Invoice,
+        quote: &InvoiceQuote,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        let status = InvoiceStatus::Created;
+        if invoice.status != status {
+            return Err(StatusUpdateError::Conflict { expected: status, to: status });
+        }
+
+        let update = doc! { "$set": { "quote": bson::to_bson(quote)? } };
+        let updated = self.write(invoice, update).await?;
+
+        self.event_bus.publish_local(DomainEvent::QuoteRefreshed {
+            invoice_id: invoice.id,
+            quote: quote.clone(),
+        });
+        Ok(updated)
+    }
+
+    /// Move an invoice from the status it was read in to `to`, returns the updated invoice.
+    /// The write only applies if the transition is legal and the invoice is unchanged since it was read.
+    pub async fn update_status(
+        &self,
+        invoice: &Invoice,
+        to: InvoiceStatus,
+        context: &AuditContext,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        self.transition(invoice, to, context).await
+    }
+
+    /// Claim a created invoice for a payment by moving it to `Pending`.
+    /// The claim fails if the invoice was amended or requoted since `invoice` was read,
+    /// so a payment is never broadcast against an amount it was not validated for.
+    pub async fn claim_for_payment(
+        &self,
+        invoice: &Invoice,
+        context: &AuditContext,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        if invoice.status != InvoiceStatus::Created {
+            return Err(StatusUpdateError::Conflict {
+                expected: InvoiceStatus::Created,
+                to: InvoiceStatus::Pending,
+            });
+        }
+        self.transition(invoice, InvoiceStatus::Pending, context).await
+    }
+
+    /// Apply a merchant's amendment to an invoice that is still awaiting payment.
+    /// A changed amount drops the locked quote, which was calculated for the old amount,
+    /// and an earlier deadline cuts the quote's window short so it is never honored past it.
+    pub async fn amend(
+        &self,
+        invoice: &Invoice,
+        amendment: &InvoiceAmendment,
+        context: &AuditContext,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        let status = InvoiceStatus::Created;
+        if invoice.status != status {
+            return Err(StatusUpdateError::Conflict { expected: status, to: status });
+        }
+
+        let mut set = doc! {};
+        let mut update = doc! {};
+        if let Some(amount) = amendment.amount {
+            set.insert("amount", amount as i64);
+            update.insert("$unset", doc! { "quote": "" });
+        }
+        if let Some(merchant_order_id) = &amendment.merchant_order_id {
+            set.insert("merchant_order_id", merchant_order_id);
+        }
+        if let Some(expires_at) = amendment.expires_at {
+            set.insert("expires_at", bson::to_bson(&expires_at)?);
+            if amendment.amount.is_none()
+                && invoice.quote.as_ref().is_some_and(|quote| quote.expires_at > expires_at)
+            {
+                set.insert("quote.expires_at", bson::to_bson(&expires_at)?);
+            }
+        }
+        update.insert("$set", set);
+
+        let invoice = self.write(invoice, update).await?;
+
+        let event = AuditEvent::new(
+            AuditEntityType::Invoice,
+            invoice.id,
+            invoice.id,
+            Some(status),
+            status,
+            context,
+        );
+        self.audit_repository.record_or_log(&event).await;
+        self.event_bus.publish_local(DomainEvent::InvoiceAmended {
+            invoice_id: invoice.id,
+            timestamp: event.timestamp,
+        });
+        Ok(invoice)
+    }
+
+    async fn transition(
+        &self,
+        invoice: &Invoice,
+        to: InvoiceStatus,
+        context: &AuditContext,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        let from = invoice.status;
+        from.transition_to(to)?;
+
+        let update = doc! { "$set": { "status": bson::to_bson(&to)? } };
+        let updated = self.write(invoice, update).await.map_err(|e| match e {
+            StatusUpdateError::Conflict { expected, .. } => StatusUpdateError::Conflict { expected, to },
+            e => e,
+        })?;
+
+        let event = AuditEvent::new(
+            AuditEntityType::Invoice,
+            invoice.id,
+            invoice.id,
+            Some(from),
+            to,
+            context,
+        );
+        self.audit_repository.record_or_log(&event).await;
+        self.event_bus.publish_local(DomainEvent::InvoiceStatusChanged {
+            invoice_id: invoice.id,
+            from: Some(from),
+            to,
+            timestamp: event.timestamp,
+        });
+        Ok(updated)
+    }
+
+    /// Compare-and-swap: apply `update` only if the invoice is still in the status and at the
+    /// version it was read with, bumping the version. Returns the updated invoice.
+    async fn write(
+        &self,
+        invoice: &Invoice,
+        mut update: bson::Document,
+    ) -> Result<Invoice, StatusUpdateError<InvoiceStatus>> {
+        let mut filter = doc! { "_id": invoice.id, "status": bson::to_bson(&invoice.status)? };
+        filter.extend(version_filter(invoice.version));
+        update.insert("$inc", doc! { "version": 1_i64 });
+
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        if let Some(updated) = self
+            .collection
+            .find_one_and_update(filter, update)
+            .with_options(options)
+            .await?
+        {
+            return Ok(updated);
+        }
+
+        // Tell a status change, which callers usually cannot retry, from a concurrent update
+        match self.find_by_id(&invoice.id).await.map_err(StatusUpdateError::Database)? {
+            Some(current) if current.status == invoice.status => {
+                Err(StatusUpdateError::VersionConflict { version: invoice.version })
+            }
+            _ => Err(StatusUpdateError::Conflict { expected: invoice.status, to: invoice.status }),
+        }
     }
 }
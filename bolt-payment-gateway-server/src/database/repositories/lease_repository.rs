@@ -0,0 +1,66 @@
+// src/database/repositories/lease_repository.rs
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use mongodb::{Collection, Database, bson::doc};
+use serde::{Deserialize, Serialize};
+
+/// Time-limited claim of a named job by one process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lease {
+    /// Job name, e.g. `subscription_scheduler`
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub holder: String,
+    /// Stored as a BSON date so expiry can be compared by the server
+    pub expires_at: bson::DateTime,
+}
+
+/// Leases in the `leases` collection, so that a job runs on a single replica at a time
+#[derive(Clone)]
+pub struct LeaseRepository {
+    collection: Collection<Lease>,
+}
+
+impl LeaseRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<Lease>("leases");
+        Self { collection }
+    }
+
+    /// Take the lease `name` for `ttl`, or extend it if `holder` has it already.
+    /// Returns `false` while another holder's lease has not expired.
+    pub async fn try_acquire(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = Utc::now();
+        let expires_at = bson::DateTime::from_chrono(now + ttl);
+        let filter = doc! {
+            "_id": name,
+            "$or": [
+                { "holder": holder },
+                { "expires_at": { "$lt": bson::DateTime::from_chrono(now) } },
+            ],
+        };
+        let update = doc! { "$set": { "holder": holder, "expires_at": expires_at } };
+
+        // A lease held by someone else does not match, so the upsert collides with its `_id`
+        match self.collection.update_one(filter, update).upsert(true).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000") => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Give the lease up so another replica can take it without waiting for it to expire
+    pub async fn release(&self, name: &str, holder: &str) -> Result<bool> {
+        let result = self
+            .collection
+            .delete_one(doc! { "_id": name, "holder": holder })
+            .await?;
+        Ok(result.deleted_count == 1)
+    }
+
+    pub async fn find(&self, name: &str) -> Result<Option<Lease>> {
+        Ok(self.collection.find_one(doc! { "_id": name }).await?)
+    }
+}
@@ -0,0 +1,78 @@
+// src/database/repositories/merchant_repository.rs
+use anyhow::Result;
+use chrono::Utc;
+use mongodb::{Collection, Database, IndexModel, bson::doc, options::IndexOptions};
+
+use crate::models::Merchant;
+
+#[derive(Clone)]
+pub struct MerchantRepository {
+    collection: Collection<Merchant>,
+}
+
+impl MerchantRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<Merchant>("merchants");
+        Self { collection }
+    }
+
+    /// Creates the unique index on wallet_address so each merchant has a single profile
+    pub async fn create_indexes(&self) -> Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name("unique_wallet_address".to_string())
+                    .build(),
+            )
+            .build();
+
+        self.collection.create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, merchant: &Merchant) -> Result<()> {
+        self.collection.insert_one(merchant).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_wallet_address(&self, wallet_address: &str) -> Result<Option<Merchant>> {
+        let filter = doc! { "wallet_address": wallet_address };
+        let result = self.collection.find_one(filter).await?;
+        Ok(result)
+    }
+
+    /// The merchant's profile, or the gateway defaults if the merchant never created one
+    pub async fn find_or_default(&self, wallet_address: &str) -> Result<Merchant> {
+        Ok(self
+            .find_by_wallet_address(wallet_address)
+            .await?
+            .unwrap_or_else(|| Merchant::with_defaults(wallet_address)))
+    }
+
+    /// Replace a stored profile, returns false if the merchant does not exist
+    pub async fn update(&self, merchant: &Merchant) -> Result<bool> {
+        let filter = doc! { "_id": merchant.id };
+        let result = self.collection.replace_one(filter, merchant).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Mark the merchant's payout address verified, as long as it is still `payout_address`.
+    /// Returns false if the merchant does not exist or changed the address in the meantime.
+    pub async fn verify_payout_address(&self, wallet_address: &str, payout_address: &str) -> Result<bool> {
+        let filter = doc! { "wallet_address": wallet_address, "payout_address": payout_address };
+        let update = doc! { "$set": {
+            "payout_address_verified": true,
+            "updated_at": bson::to_bson(&Utc::now())?,
+        } };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn delete(&self, wallet_address: &str) -> Result<bool> {
+        let filter = doc! { "wallet_address": wallet_address };
+        let result = self.collection.delete_one(filter).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
@@ -1,6 +1,28 @@
 // src/database/repositories/mod.rs
+pub mod audit_repository;
+pub mod error;
 pub mod invoice_repository;
+pub mod lease_repository;
+pub mod merchant_repository;
+pub mod payment_link_repository;
 pub mod payment_repository;
+pub mod price_guard_repository;
+pub mod price_history_repository;
+pub mod price_snapshot_repository;
+pub mod resume_token_repository;
+pub mod subscription_plan_repository;
+pub mod subscription_repository;
 
+pub use audit_repository::*;
+pub use error::*;
 pub use invoice_repository::*;
+pub use lease_repository::*;
+pub use merchant_repository::*;
+pub use payment_link_repository::*;
 pub use payment_repository::*;
+pub use price_guard_repository::*;
+pub use price_history_repository::*;
+pub use price_snapshot_repository::*;
+pub use resume_token_repository::*;
+pub use subscription_plan_repository::*;
+pub use subscription_repository::*;
@@ -0,0 +1,116 @@
+// src/database/repositories/payment_link_repository.rs
+use anyhow::Result;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
+    Collection, Database, IndexModel,
+};
+
+use crate::models::PaymentLink;
+
+#[derive(Clone)]
+pub struct PaymentLinkRepository {
+    collection: Collection<PaymentLink>,
+}
+
+impl PaymentLinkRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<PaymentLink>("payment_links");
+        Self { collection }
+    }
+
+    /// Creates the index used to list a merchant's links
+    pub async fn create_indexes(&self) -> Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name("wallet_address_created_at".to_string())
+                    .build(),
+            )
+            .build();
+
+        self.collection.create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, link: &PaymentLink) -> Result<()> {
+        self.collection.insert_one(link).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<PaymentLink>> {
+        let result = self.collection.find_one(doc! { "_id": id }).await?;
+        Ok(result)
+    }
+
+    pub async fn find_by_merchant(&self, wallet_address: &str) -> Result<Vec<PaymentLink>> {
+        let filter = doc! { "wallet_address": wallet_address };
+        let mut cursor = self.collection.find(filter).sort(doc! { "created_at": -1 }).await?;
+        let mut links = Vec::new();
+
+        while let Some(link) = cursor.try_next().await? {
+            links.push(link);
+        }
+
+        Ok(links)
+    }
+
+    /// Store the fields a merchant edits, `usage_count` is only changed by `claim_use` and `release_use`
+    /// so concurrent openings are kept. Returns the updated link, `None` if the link does not exist
+    pub async fn update(&self, link: &PaymentLink) -> Result<Option<PaymentLink>> {
+        let filter = doc! { "_id": link.id };
+        let update = doc! { "$set": {
+            "title": &link.title,
+            "description": &link.description,
+            "amount": bson::to_bson(&link.amount)?,
+            "settlement_asset": bson::to_bson(&link.settlement_asset)?,
+            "usage_limit": bson::to_bson(&link.usage_limit)?,
+            "active": link.active,
+            "updated_at": bson::to_bson(&link.updated_at)?,
+        } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let result = self
+            .collection
+            .find_one_and_update(filter, update)
+            .with_options(options)
+            .await?;
+        Ok(result)
+    }
+
+    /// Count one more use of an active link that has uses left, atomically so concurrent
+    /// openings cannot exceed the usage limit. Returns the updated link, `None` if unavailable.
+    pub async fn claim_use(&self, id: &ObjectId) -> Result<Option<PaymentLink>> {
+        let filter = doc! {
+            "_id": id,
+            "active": true,
+            "$or": [
+                { "usage_limit": null },
+                { "$expr": { "$lt": ["$usage_count", "$usage_limit"] } },
+            ],
+        };
+        let update = doc! { "$inc": { "usage_count": 1 } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let result = self
+            .collection
+            .find_one_and_update(filter, update)
+            .with_options(options)
+            .await?;
+        Ok(result)
+    }
+
+    /// Give back a use claimed by `claim_use` when the invoice could not be created
+    pub async fn release_use(&self, id: &ObjectId) -> Result<()> {
+        let filter = doc! { "_id": id, "usage_count": { "$gt": 0 } };
+        let update = doc! { "$inc": { "usage_count": -1 } };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+}
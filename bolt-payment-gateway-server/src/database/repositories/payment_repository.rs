@@ -1,19 +1,43 @@
 // src/database/repositories/payment_repository.rs
-use crate::models::{Payment, PaymentStatus};
+use crate::database::{version_filter, AuditRepository, StatusUpdateError};
+use crate::models::{AuditContext, AuditEntityType, AuditEvent, DomainEvent, Payment, PaymentStatus, StateMachine};
+use crate::services::event_bus::EventBus;
 use anyhow::Result;
 use bson;
+use chrono::{DateTime, Utc};
 use futures::stream::TryStreamExt;
 use mongodb::{Collection, Database, IndexModel, bson::doc, options::IndexOptions};
 
+/// Payments a listing is limited to
+#[derive(Debug, Clone, Copy)]
+pub enum PaymentScope<'a> {
+    Invoice(&'a bson::oid::ObjectId),
+    Merchant(&'a str),
+    /// Every merchant's, only listed by transaction
+    All,
+}
+
+/// Filters of a payment listing, `None` matches everything
+#[derive(Debug, Clone, Default)]
+pub struct PaymentFilter {
+    pub tx_id: Option<String>,
+    pub status: Option<PaymentStatus>,
+    pub received_from: Option<DateTime<Utc>>,
+    pub received_to: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone)]
 pub struct PaymentRepository {
     collection: Collection<Payment>,
+    audit_repository: AuditRepository,
+    event_bus: EventBus,
 }
 
 impl PaymentRepository {
-    pub fn new(database: &Database) -> Self {
+    pub fn new(database: &Database, event_bus: EventBus) -> Self {
         let collection = database.collection::<Payment>("payments");
-        Self { collection }
+        let audit_repository = AuditRepository::new(database);
+        Self { collection, audit_repository, event_bus }
     }
 
     /// Creates the unique partial index for payments
@@ -51,11 +75,91 @@ impl PaymentRepository {
 
         self.collection.create_index(general_index).await?;
 
+        // Listings are per merchant or invoice and newest first, lookups by transaction span every merchant
+        let listing_indexes = [
+            IndexModel::builder()
+                .keys(doc! { "wallet_address": 1, "received_at": -1 })
+                .options(IndexOptions::builder().name("wallet_address_received_at".to_string()).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "invoice_id": 1, "received_at": -1 })
+                .options(IndexOptions::builder().name("invoice_id_received_at".to_string()).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "tx_id": 1 })
+                .options(IndexOptions::builder().name("tx_id_index".to_string()).sparse(true).build())
+                .build(),
+        ];
+        self.collection.create_indexes(listing_indexes).await?;
+
         Ok(())
     }
 
-    pub async fn create(&self, payment: &Payment) -> Result<()> {
+    /// One page of the payments in `scope` matching `filter`, newest first, with the number of matching payments
+    pub async fn find_page(
+        &self,
+        scope: PaymentScope<'_>,
+        filter: &PaymentFilter,
+        offset: u64,
+        limit: i64,
+    ) -> Result<(Vec<Payment>, u64)> {
+        let mut query = match scope {
+            PaymentScope::Invoice(invoice_id) => doc! { "invoice_id": invoice_id },
+            PaymentScope::Merchant(wallet_address) => doc! { "wallet_address": wallet_address },
+            PaymentScope::All => doc! {},
+        };
+        if let Some(tx_id) = &filter.tx_id {
+            query.insert("tx_id", tx_id);
+        }
+        if let Some(status) = filter.status {
+            query.insert("status", bson::to_bson(&status)?);
+        }
+        // Timestamps are stored as RFC 3339 strings, which order like the dates to within a second
+        let mut received_at = doc! {};
+        if let Some(from) = filter.received_from {
+            received_at.insert("$gte", bson::to_bson(&from)?);
+        }
+        if let Some(to) = filter.received_to {
+            received_at.insert("$lte", bson::to_bson(&to)?);
+        }
+        if !received_at.is_empty() {
+            query.insert("received_at", received_at);
+        }
+
+        let total = self.collection.count_documents(query.clone()).await?;
+        // A zero limit means none to the database
+        if limit == 0 {
+            return Ok((Vec::new(), total));
+        }
+        let cursor = self
+            .collection
+            .find(query)
+            .sort(doc! { "received_at": -1, "_id": -1 })
+            .skip(offset)
+            .limit(limit)
+            .await?;
+        Ok((cursor.try_collect().await?, total))
+    }
+
+    pub async fn create(&self, payment: &Payment, context: &AuditContext) -> Result<()> {
         self.collection.insert_one(payment).await?;
+
+        let event = AuditEvent::new(
+            AuditEntityType::Payment,
+            payment.id,
+            payment.invoice_id,
+            None,
+            payment.status,
+            context,
+        );
+        self.audit_repository.record_or_log(&event).await;
+        self.event_bus.publish_local(DomainEvent::PaymentStatusChanged {
+            invoice_id: payment.invoice_id,
+            payment_id: payment.id,
+            from: None,
+            to: payment.status,
+            timestamp: event.timestamp,
+        });
         Ok(())
     }
 
@@ -80,56 +184,138 @@ impl PaymentRepository {
         Ok(payments)
     }
 
+    /// Payments of any of the given invoices, e.g. all invoices of a merchant
+    pub async fn find_by_invoice_ids(
+        &self,
+        invoice_ids: &[bson::oid::ObjectId],
+    ) -> Result<Vec<Payment>> {
+        let filter = doc! { "invoice_id": { "$in": invoice_ids } };
+        let mut cursor = self.collection.find(filter).await?;
+        let mut payments = Vec::new();
+
+        while let Some(payment) = cursor.try_next().await? {
+            payments.push(payment);
+        }
+
+        Ok(payments)
+    }
+
     pub async fn find_by_tx_id(&self, tx_id: &str) -> Result<Option<Payment>> {
         let filter = doc! { "tx_id": tx_id };
         let result = self.collection.find_one(filter).await?;
         Ok(result)
     }
 
+    /// Mark an accepted payment as confirmed with its on-chain transaction ID and sender.
     pub async fn confirm(
         &self,
-        payment_id: &bson::oid::ObjectId,
+        payment: &Payment,
         tx_id: &str,
-    ) -> Result<Option<Payment>> {
-        let filter = doc! { "_id": payment_id };
-        let update = doc! { "$set": {
-            "tx_id": tx_id,
-            "status": bson::to_bson(&PaymentStatus::Confirmed)?
-        } };
+        sender_address: &str,
+        context: &AuditContext,
+    ) -> Result<Payment, StatusUpdateError<PaymentStatus>> {
+        let set = doc! { "tx_id": tx_id, "sender_address": sender_address };
+        self.transition(payment, PaymentStatus::Confirmed, set, context).await
+    }
+
+    /// Move a payment from the status it was read in to `to`, returns the updated payment.
+    /// The write only applies if the transition is legal and the payment is unchanged since it was read.
+    pub async fn update_status(
+        &self,
+        payment: &Payment,
+        to: PaymentStatus,
+        context: &AuditContext,
+    ) -> Result<Payment, StatusUpdateError<PaymentStatus>> {
+        self.transition(payment, to, doc! {}, context).await
+    }
+
+    /// Compare-and-swap on the status and version `payment` was read with, bumping the version
+    async fn transition(
+        &self,
+        payment: &Payment,
+        to: PaymentStatus,
+        mut set: bson::Document,
+        context: &AuditContext,
+    ) -> Result<Payment, StatusUpdateError<PaymentStatus>> {
+        let from = payment.status;
+        from.transition_to(to)?;
+
+        let mut filter = doc! { "_id": payment.id, "status": bson::to_bson(&from)? };
+        filter.extend(version_filter(payment.version));
+        set.insert("status", bson::to_bson(&to)?);
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
 
-        // Use find_one_and_update to return the updated document
         let options = mongodb::options::FindOneAndUpdateOptions::builder()
             .return_document(mongodb::options::ReturnDocument::After)
             .build();
-
-        let result = self
+        let Some(updated) = self
             .collection
             .find_one_and_update(filter, update)
             .with_options(options)
-            .await?;
+            .await?
+        else {
+            // Tell a status change from a concurrent update of the same status
+            return match self.find_by_id(&payment.id).await.map_err(StatusUpdateError::Database)? {
+                Some(current) if current.status == from => {
+                    Err(StatusUpdateError::VersionConflict { version: payment.version })
+                }
+                _ => Err(StatusUpdateError::Conflict { expected: from, to }),
+            };
+        };
 
-        Ok(result)
+        let event = AuditEvent::new(
+            AuditEntityType::Payment,
+            updated.id,
+            updated.invoice_id,
+            Some(from),
+            to,
+            context,
+        );
+        self.audit_repository.record_or_log(&event).await;
+        self.event_bus.publish_local(DomainEvent::PaymentStatusChanged {
+            invoice_id: updated.invoice_id,
+            payment_id: updated.id,
+            from: Some(from),
+            to,
+            timestamp: event.timestamp,
+        });
+        Ok(updated)
     }
 
-    pub async fn update_status(
-        &self,
-        payment_id: &bson::oid::ObjectId,
-        status: PaymentStatus,
-    ) -> Result<bool> {
-        let filter = doc! { "_id": payment_id };
-        let update = doc! { "$set": { "status": bson::to_bson(&status)? } };
-
-        let result = self.collection.update_one(filter, update).await?;
-        Ok(result.modified_count > 0)
+    /// Record the transaction an accepted payment was broadcast as, so it can be reconciled when it
+    /// could not be confirmed. Returns the updated payment, `None` once it left `Accepted` or has one.
+    pub async fn record_tx_id(&self, payment_id: &bson::oid::ObjectId, tx_id: &str, sender_address: &str) -> Result<Option<Payment>> {
+        let filter = doc! {
+            "_id": payment_id,
+            "status": bson::to_bson(&PaymentStatus::Accepted)?,
+            "tx_id": null,
+        };
+        let update = doc! {
+            "$set": { "tx_id": tx_id, "sender_address": sender_address },
+            "$inc": { "version": 1_i64 },
+        };
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        Ok(self.collection.find_one_and_update(filter, update).with_options(options).await?)
     }
 
-    // pub async fn update_tx_id(&self, payment_id: &bson::oid::ObjectId, tx_id: &str) -> Result<bool> {
-    //     let filter = doc! { "_id": payment_id };
-    //     let update = doc! { "$set": { "tx_id": tx_id } };
+    /// Payments still `Accepted` that were received before `received_before`, oldest first
+    pub async fn find_accepted_before(&self, received_before: DateTime<Utc>) -> Result<Vec<Payment>> {
+        // Timestamps are stored as RFC 3339 strings, which order like the dates to within a second
+        let filter = doc! {
+            "status": bson::to_bson(&PaymentStatus::Accepted)?,
+            "received_at": { "$lt": bson::to_bson(&received_before)? },
+        };
+        let mut cursor = self.collection.find(filter).sort(doc! { "received_at": 1 }).await?;
+        let mut payments = Vec::new();
+
+        while let Some(payment) = cursor.try_next().await? {
+            payments.push(payment);
+        }
 
-    //     let result = self.collection.update_one(filter, update).await?;
-    //     Ok(result.modified_count > 0)
-    // }
+        Ok(payments)
+    }
 
     pub async fn find_by_status(&self, status: PaymentStatus) -> Result<Vec<Payment>> {
         let filter = doc! { "status": bson::to_bson(&status)? };
@@ -186,7 +372,7 @@ impl PaymentRepository {
 mod tests {
     use super::*;
     use crate::database::MongoDBClient;
-    use crate::models::{PaymentStatus, PaymentToken};
+    use crate::models::{Actor, PaymentStatus, PaymentToken};
     use bson::oid::ObjectId;
 
     async fn setup_test_db() -> PaymentRepository {
@@ -198,7 +384,7 @@ mod tests {
             .await
             .expect("Failed to connect to test MongoDB");
 
-        let repo = PaymentRepository::new(client.get_database());
+        let repo = PaymentRepository::new(client.get_database(), EventBus::new());
         repo.create_indexes()
             .await
             .expect("Failed to create indexes");
@@ -209,10 +395,11 @@ mod tests {
     async fn test_unique_payment_constraint() {
         let repo = setup_test_db().await;
         let invoice_id = ObjectId::new();
+        let context = AuditContext::new(Actor::Customer { address: None }, None);
 
         // Create first accepted payment - should succeed
         let payment1 = Payment::new(invoice_id, PaymentToken::SBTC, 1000);
-        let result1 = repo.create(&payment1).await;
+        let result1 = repo.create(&payment1, &context).await;
         assert!(
             result1.is_ok(),
             "First payment should be created successfully"
@@ -220,7 +407,7 @@ mod tests {
 
         // Try to create second accepted payment for same invoice - should fail
         let payment2 = Payment::new(invoice_id, PaymentToken::SBTC, 2000);
-        let result2 = repo.create(&payment2).await;
+        let result2 = repo.create(&payment2, &context).await;
         assert!(
             result2.is_err(),
             "Second accepted payment should fail due to unique constraint"
@@ -229,13 +416,13 @@ mod tests {
         // Create a rejected payment for the same invoice - should succeed
         let mut payment3 = Payment::new(invoice_id, PaymentToken::SBTC, 3000);
         payment3.status = PaymentStatus::Rejected;
-        let result3 = repo.create(&payment3).await;
+        let result3 = repo.create(&payment3, &context).await;
         assert!(result3.is_ok(), "Rejected payment should be allowed");
 
         // Try to create confirmed payment for same invoice - should fail
         let mut payment4 = Payment::new(invoice_id, PaymentToken::SBTC, 4000);
         payment4.status = PaymentStatus::Confirmed;
-        let result4 = repo.create(&payment4).await;
+        let result4 = repo.create(&payment4, &context).await;
         assert!(
             result4.is_err(),
             "Confirmed payment should fail due to unique constraint with existing accepted payment"
@@ -244,7 +431,7 @@ mod tests {
         // Create accepted payment for different invoice - should succeed
         let different_invoice_id = ObjectId::new();
         let payment5 = Payment::new(different_invoice_id, PaymentToken::SBTC, 5000);
-        let result5 = repo.create(&payment5).await;
+        let result5 = repo.create(&payment5, &context).await;
         assert!(
             result5.is_ok(),
             "Payment for different invoice should succeed"
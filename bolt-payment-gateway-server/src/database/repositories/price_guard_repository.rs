@@ -0,0 +1,58 @@
+// src/database/repositories/price_guard_repository.rs
+use anyhow::Result;
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, Bson},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::models::{PriceGuardState, PriceHalt};
+
+/// Price guard state per pair in `price_guard`, so a halt applies to every replica
+#[derive(Debug, Clone)]
+pub struct PriceGuardRepository {
+    collection: Collection<PriceGuardState>,
+}
+
+impl PriceGuardRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<PriceGuardState>("price_guard");
+        Self { collection }
+    }
+
+    pub async fn find(&self, pair: &str) -> Result<Option<PriceGuardState>> {
+        Ok(self.collection.find_one(doc! { "_id": pair }).await?)
+    }
+
+    /// Halt payments on `pair`, returns `false` when they were halted already
+    pub async fn halt(&self, pair: &str, halt: &PriceHalt) -> Result<bool> {
+        let filter = doc! { "_id": pair, "halt": Bson::Null };
+        let update = doc! { "$set": { "halt": bson::to_bson(halt)? } };
+
+        // An existing halt does not match, so the upsert collides with its `_id`
+        match self.collection.update_one(filter, update).upsert(true).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("E11000") => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Clear the halt on `pair`, returns it or `None` when payments were not halted
+    pub async fn resume(&self, pair: &str, resumed_by: &str, reason: Option<&str>) -> Result<Option<PriceHalt>> {
+        let filter = doc! { "_id": pair, "halt": { "$ne": Bson::Null } };
+        let update = doc! { "$set": {
+            "halt": Bson::Null,
+            "resumed_by": resumed_by,
+            "resume_reason": reason,
+            "resumed_at": bson::to_bson(&Utc::now())?,
+        } };
+
+        let previous = self
+            .collection
+            .find_one_and_update(filter, update)
+            .return_document(ReturnDocument::Before)
+            .await?;
+        Ok(previous.and_then(|state| state.halt))
+    }
+}
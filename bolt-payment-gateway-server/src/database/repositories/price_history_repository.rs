@@ -0,0 +1,113 @@
+// src/database/repositories/price_history_repository.rs
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::doc,
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+
+use crate::models::{PriceCandle, PriceInterval, PriceTick};
+
+/// How long fetched prices are kept
+pub const PRICE_HISTORY_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Every price fetched, in the `price_ticks` collection
+#[derive(Debug, Clone)]
+pub struct PriceHistoryRepository {
+    collection: Collection<PriceTick>,
+}
+
+impl PriceHistoryRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<PriceTick>("price_ticks");
+        Self { collection }
+    }
+
+    pub async fn create_indexes(&self) -> Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "pair": 1, "fetched_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("price_history_index".to_string())
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(index).await?;
+
+        // Ticks older than the retention are removed by MongoDB
+        let index = IndexModel::builder()
+            .keys(doc! { "fetched_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("price_tick_expiry_index".to_string())
+                    .expire_after(PRICE_HISTORY_RETENTION)
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn record(&self, tick: &PriceTick) -> Result<()> {
+        self.collection.insert_one(tick).await?;
+        Ok(())
+    }
+
+    /// The latest tick of `pair` at `price` fetched at or before `at`, i.e. the tick a price used at `at` came from
+    pub async fn find_tick(&self, pair: &str, price: u128, at: DateTime<Utc>) -> Result<Option<PriceTick>> {
+        let filter = doc! {
+            "pair": pair,
+            "price": price as i64,
+            "fetched_at": { "$lte": bson::DateTime::from_chrono(at) },
+        };
+        let tick = self
+            .collection
+            .find_one(filter)
+            .sort(doc! { "fetched_at": -1 })
+            .await?;
+        Ok(tick)
+    }
+
+    /// OHLC candles of the ticks of `pair` fetched in `[from, to)`, oldest first.
+    /// Intervals without ticks have no candle.
+    pub async fn candles(
+        &self,
+        pair: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        interval: PriceInterval,
+    ) -> Result<Vec<PriceCandle>> {
+        let (unit, bin_size) = interval.date_trunc();
+        let pipeline = vec![
+            doc! { "$match": {
+                "pair": pair,
+                "fetched_at": {
+                    "$gte": bson::DateTime::from_chrono(from),
+                    "$lt": bson::DateTime::from_chrono(to),
+                },
+            } },
+            // Ticks of the same millisecond keep their insertion order
+            doc! { "$sort": { "fetched_at": 1, "_id": 1 } },
+            doc! { "$group": {
+                "_id": { "$dateTrunc": { "date": "$fetched_at", "unit": unit, "binSize": bin_size } },
+                "open": { "$first": "$price" },
+                "high": { "$max": "$price" },
+                "low": { "$min": "$price" },
+                "close": { "$last": "$price" },
+                "ticks": { "$sum": 1_i64 },
+            } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+
+        let cursor = self.collection.aggregate(pipeline).await?;
+        let documents: Vec<bson::Document> = cursor.try_collect().await?;
+        documents
+            .into_iter()
+            .map(|document| Ok(bson::from_document(document)?))
+            .collect()
+    }
+}
@@ -0,0 +1,47 @@
+// src/database/repositories/price_snapshot_repository.rs
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use mongodb::{Collection, Database, bson::doc};
+
+use crate::models::PriceSnapshot;
+
+/// Latest price per pair, one document per pair in `price_snapshots`
+#[derive(Debug, Clone)]
+pub struct PriceSnapshotRepository {
+    collection: Collection<PriceSnapshot>,
+}
+
+impl PriceSnapshotRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<PriceSnapshot>("price_snapshots");
+        Self { collection }
+    }
+
+    pub async fn find(&self, pair: &str) -> Result<Option<PriceSnapshot>> {
+        Ok(self.collection.find_one(doc! { "_id": pair }).await?)
+    }
+
+    /// Store `snapshot` unless another replica stored one younger than `max_age` in the meantime.
+    /// Returns the snapshot in effect, so replicas fetching at the same time agree on one price.
+    pub async fn replace_if_older(&self, snapshot: &PriceSnapshot, max_age: Duration) -> Result<PriceSnapshot> {
+        let stale_before = bson::DateTime::from_chrono(Utc::now() - max_age);
+        let filter = doc! { "_id": &snapshot.pair, "fetched_at": { "$lt": stale_before } };
+        let update = doc! { "$set": {
+            "price": snapshot.price as i64,
+            "source": &snapshot.source,
+            "fetched_at": snapshot.fetched_at,
+        } };
+
+        // A fresh snapshot does not match, so the upsert collides with its `_id`
+        match self.collection.update_one(filter, update).upsert(true).await {
+            Ok(_) => Ok(snapshot.clone()),
+            Err(e) if e.to_string().contains("E11000") => self
+                .find(&snapshot.pair)
+                .await?
+                .ok_or_else(|| anyhow!("Price snapshot {} disappeared", snapshot.pair)),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// src/database/repositories/resume_token_repository.rs
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use mongodb::{Collection, Database, bson::doc, change_stream::event::ResumeToken};
+use serde::{Deserialize, Serialize};
+
+/// Last change stream position processed by a listener
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredResumeToken {
+    /// Watched collection, e.g. `invoices`, shared by the replicas
+    #[serde(rename = "_id")]
+    id: String,
+    token: ResumeToken,
+    updated_at: DateTime<Utc>,
+}
+
+/// Persists change stream resume tokens so a restarted listener continues where it stopped
+#[derive(Clone)]
+pub struct ResumeTokenRepository {
+    collection: Collection<StoredResumeToken>,
+}
+
+impl ResumeTokenRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<StoredResumeToken>("change_stream_resume_tokens");
+        Self { collection }
+    }
+
+    pub async fn find(&self, stream_id: &str) -> Result<Option<ResumeToken>> {
+        let filter = doc! { "_id": stream_id };
+        let result = self.collection.find_one(filter).await?;
+        Ok(result.map(|stored| stored.token))
+    }
+
+    pub async fn save(&self, stream_id: &str, token: ResumeToken) -> Result<()> {
+        let stored = StoredResumeToken {
+            id: stream_id.to_string(),
+            token,
+            updated_at: Utc::now(),
+        };
+        self.collection
+            .replace_one(doc! { "_id": stream_id }, &stored)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+}
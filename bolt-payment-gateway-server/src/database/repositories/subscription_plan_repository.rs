@@ -0,0 +1,62 @@
+// src/database/repositories/subscription_plan_repository.rs
+use anyhow::Result;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+
+use crate::models::SubscriptionPlan;
+
+#[derive(Clone)]
+pub struct SubscriptionPlanRepository {
+    collection: Collection<SubscriptionPlan>,
+}
+
+impl SubscriptionPlanRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<SubscriptionPlan>("subscription_plans");
+        Self { collection }
+    }
+
+    /// Creates the index used to list a merchant's plans
+    pub async fn create_indexes(&self) -> Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "wallet_address": 1 })
+            .options(IndexOptions::builder().name("wallet_address".to_string()).build())
+            .build();
+
+        self.collection.create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, plan: &SubscriptionPlan) -> Result<()> {
+        self.collection.insert_one(plan).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<SubscriptionPlan>> {
+        let result = self.collection.find_one(doc! { "_id": id }).await?;
+        Ok(result)
+    }
+
+    pub async fn find_by_merchant(&self, wallet_address: &str) -> Result<Vec<SubscriptionPlan>> {
+        let filter = doc! { "wallet_address": wallet_address };
+        let mut cursor = self.collection.find(filter).await?;
+        let mut plans = Vec::new();
+
+        while let Some(plan) = cursor.try_next().await? {
+            plans.push(plan);
+        }
+
+        Ok(plans)
+    }
+
+    /// Replace a stored plan, returns false if the plan does not exist
+    pub async fn update(&self, plan: &SubscriptionPlan) -> Result<bool> {
+        let filter = doc! { "_id": plan.id };
+        let result = self.collection.replace_one(filter, plan).await?;
+        Ok(result.matched_count > 0)
+    }
+}
@@ -0,0 +1,130 @@
+// src/database/repositories/subscription_repository.rs
+use anyhow::Result;
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+
+use crate::models::{BillingCycle, CycleStatus, Subscription, SubscriptionStatus};
+
+#[derive(Clone)]
+pub struct SubscriptionRepository {
+    collection: Collection<Subscription>,
+}
+
+impl SubscriptionRepository {
+    pub fn new(database: &Database) -> Self {
+        let collection = database.collection::<Subscription>("subscriptions");
+        Self { collection }
+    }
+
+    /// Creates the indexes used to list a merchant's subscriptions and the ones still billed
+    pub async fn create_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "wallet_address": 1 })
+                .options(IndexOptions::builder().name("wallet_address".to_string()).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "status": 1 })
+                .options(IndexOptions::builder().name("status".to_string()).build())
+                .build(),
+        ];
+
+        self.collection.create_indexes(indexes).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, subscription: &Subscription) -> Result<()> {
+        self.collection.insert_one(subscription).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> Result<Option<Subscription>> {
+        let result = self.collection.find_one(doc! { "_id": id }).await?;
+        Ok(result)
+    }
+
+    pub async fn find_by_merchant(&self, wallet_address: &str) -> Result<Vec<Subscription>> {
+        self.find_many(doc! { "wallet_address": wallet_address }).await
+    }
+
+    /// Subscriptions that are not cancelled, the ones the scheduler works on
+    pub async fn find_billable(&self) -> Result<Vec<Subscription>> {
+        let cancelled = bson::to_bson(&SubscriptionStatus::Cancelled)?;
+        self.find_many(doc! { "status": { "$ne": cancelled } }).await
+    }
+
+    async fn find_many(&self, filter: bson::Document) -> Result<Vec<Subscription>> {
+        let mut cursor = self.collection.find(filter).await?;
+        let mut subscriptions = Vec::new();
+
+        while let Some(subscription) = cursor.try_next().await? {
+            subscriptions.push(subscription);
+        }
+
+        Ok(subscriptions)
+    }
+
+    /// Record `cycle` and move the next billing date to its end, only if it is still the start of
+    /// the cycle, so a period is billed once even when several schedulers run. The cycle holds the id
+    /// its invoice is created with, a cycle whose invoice is missing is billed again.
+    pub async fn claim_billing(&self, id: &ObjectId, cycle: &BillingCycle) -> Result<bool> {
+        let filter = doc! {
+            "_id": id,
+            "status": { "$ne": bson::to_bson(&SubscriptionStatus::Cancelled)? },
+            "next_billing_at": bson::to_bson(&cycle.period_start)?,
+        };
+        let update = doc! {
+            "$set": {
+                "next_billing_at": bson::to_bson(&cycle.period_end)?,
+                "updated_at": bson::to_bson(&Utc::now())?,
+            },
+            "$push": { "cycles": bson::to_bson(cycle)? },
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Store the outcome of a cycle closed with `Subscription::close_cycle`.
+    /// Returns false if the cycle was closed in the meantime.
+    pub async fn close_cycle(&self, subscription: &Subscription, number: u32) -> Result<bool> {
+        let Some(cycle) = subscription.cycles.iter().find(|cycle| cycle.number == number) else {
+            return Ok(false);
+        };
+        let filter = doc! {
+            "_id": subscription.id,
+            "cycles": {
+                "$elemMatch": { "number": number, "status": bson::to_bson(&CycleStatus::Open)? }
+            },
+        };
+        let update = doc! {
+            "$set": {
+                "cycles.$.status": bson::to_bson(&cycle.status)?,
+                "unpaid_cycles": subscription.unpaid_cycles,
+                "status": bson::to_bson(&subscription.status)?,
+                "updated_at": bson::to_bson(&Utc::now())?,
+            }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Cancel a subscription, returns false if it does not exist or is already cancelled
+    pub async fn cancel(&self, id: &ObjectId, reason: &str) -> Result<bool> {
+        let cancelled = bson::to_bson(&SubscriptionStatus::Cancelled)?;
+        let filter = doc! { "_id": id, "status": { "$ne": cancelled.clone() } };
+        let update = doc! {
+            "$set": {
+                "status": cancelled,
+                "cancel_reason": reason,
+                "updated_at": bson::to_bson(&Utc::now())?,
+            }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+}
@@ -0,0 +1,82 @@
+// src/handlers/analytics_handler.rs
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+
+use crate::models::{
+    AnalyticsQuery, AssetAnalyticsResponse, ErrorResponse, MerchantAnalyticsResponse,
+    VolumeBucketResponse,
+};
+use crate::AppState;
+
+/// Range used when the request does not set `from`
+const DEFAULT_ANALYTICS_RANGE_DAYS: i64 = 30;
+
+/// Longest range that can be requested (about 5 years)
+const MAX_ANALYTICS_RANGE_DAYS: i64 = 5 * 366;
+
+/// Sales metrics of a merchant's invoices created in a time range
+pub async fn get_merchant_analytics(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<MerchantAnalyticsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        )
+    };
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query
+        .from
+        .unwrap_or_else(|| to - chrono::Duration::days(DEFAULT_ANALYTICS_RANGE_DAYS));
+    if from >= to {
+        return Err(invalid("invalid_range", "from must be before to"));
+    }
+    if to - from > chrono::Duration::days(MAX_ANALYTICS_RANGE_DAYS) {
+        return Err(invalid("invalid_range", "The range cannot exceed 5 years"));
+    }
+
+    let timezone = query.timezone.unwrap_or_else(|| "UTC".to_string());
+    if timezone.trim().is_empty() {
+        return Err(invalid("invalid_timezone", "Timezone cannot be empty"));
+    }
+
+    let analytics = app_state
+        .invoice_repository
+        .merchant_analytics(&wallet_address, from, to, query.granularity, &timezone)
+        .await
+        .map_err(|e| {
+            // MongoDB validates the timezone while aggregating
+            let message = e.to_string();
+            if message.contains("time zone") || message.contains("timezone") {
+                return invalid("invalid_timezone", "Timezone must be an Olson name or a UTC offset");
+            }
+            tracing::error!("Failed to aggregate analytics for merchant {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to compute analytics".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(MerchantAnalyticsResponse {
+        wallet_address,
+        from,
+        to,
+        granularity: query.granularity,
+        timezone,
+        totals: analytics.totals.into_iter().map(AssetAnalyticsResponse::from).collect(),
+        series: analytics.series.into_iter().map(VolumeBucketResponse::from).collect(),
+    }))
+}
@@ -0,0 +1,287 @@
+// src/handlers/checkout_handler.rs
+use std::io::Cursor;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Json, Response},
+};
+use chrono::Utc;
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, QrCode};
+use reqwest::Url;
+
+use crate::handlers::invoices_handler::{current_quote, expire_if_due, find_invoice};
+use crate::models::{
+    convert_string_to_object_id, format_money_amount, payout_error_response, CheckoutResponse,
+    ErrorResponse, InvoiceQuoteResponse, PaymentOptionResponse, PaymentToken, PaymentUriQuery,
+    PaymentUriResponse, QrCodeFormat, QrCodeQuery,
+};
+use crate::shared::RequestId;
+use crate::AppState;
+
+/// Scheme of the payment request URIs
+const PAYMENT_URI_SCHEME: &str = "stacks";
+
+/// Bounds of the QR code size in pixels
+const DEFAULT_QR_SIZE: u32 = 300;
+const MAX_QR_SIZE: u32 = 1024;
+
+/// Checkout session of an invoice awaiting payment: amounts due per token, the locked quote,
+/// recipient, token contracts, memo and deadline, so any wallet can build the transfer
+pub async fn get_invoice_checkout(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+) -> Result<Json<CheckoutResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let checkout = checkout_session(&app_state, &invoice_id, request_id).await?;
+    Ok(Json(checkout))
+}
+
+/// Payment request URI of an invoice for one token, built from the current locked quote
+pub async fn get_invoice_payment_uri(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    Query(query): Query<PaymentUriQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let checkout = checkout_session(&app_state, &invoice_id, request_id).await?;
+    let option = select_payment_option(&checkout, query.token)?;
+
+    let response = PaymentUriResponse {
+        uri: payment_uri(&checkout, option),
+        token: option.token,
+        amount: option.amount_due.clone(),
+        recipient_address: checkout.recipient_address.clone(),
+        memo: checkout.memo.clone(),
+        quote_expires_at: checkout.quote.expires_at,
+    };
+    let body = serde_json::to_vec(&response).expect("payment URI response serializes");
+
+    Ok(quote_bound_response(&checkout, "application/json", body))
+}
+
+/// Payment request URI of an invoice rendered as a PNG or SVG QR code.
+/// Clients may cache it until the quote expires, the next request renders the refreshed amount.
+pub async fn get_invoice_qr_code(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    Query(query): Query<QrCodeQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let size = query.size.unwrap_or(DEFAULT_QR_SIZE);
+    if size == 0 || size > MAX_QR_SIZE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_size".to_string(),
+                message: format!("Size must be between 1 and {} pixels", MAX_QR_SIZE),
+            }),
+        ));
+    }
+
+    let checkout = checkout_session(&app_state, &invoice_id, request_id).await?;
+    let option = select_payment_option(&checkout, query.token)?;
+    let uri = payment_uri(&checkout, option);
+
+    let body = render_qr_code(&uri, query.format, size).map_err(|e| {
+        tracing::error!("Failed to render QR code for invoice {}: {}", invoice_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "qr_code_error".to_string(),
+                message: "Failed to render QR code".to_string(),
+            }),
+        )
+    })?;
+
+    Ok(quote_bound_response(&checkout, query.format.content_type(), body))
+}
+
+async fn checkout_session(
+    app_state: &AppState,
+    invoice_id: &str,
+    request_id: Option<String>,
+) -> Result<CheckoutResponse, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(invoice_id)?;
+
+    let invoice = find_invoice(app_state, &object_id).await?;
+    let invoice = expire_if_due(app_state, invoice, request_id).await?;
+    let quote = current_quote(app_state, &invoice).await?;
+
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&invoice.wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", invoice.wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    // Invoices created before recipients were stored are resolved from the merchant's current settings
+    let recipient_address = match &invoice.recipient_address {
+        Some(recipient_address) => recipient_address.clone(),
+        None => app_state
+            .payout_service
+            .resolve_recipient(&merchant)
+            .map_err(|e| {
+                tracing::warn!("Cannot resolve recipient for invoice {}: {}", invoice.id, e);
+                payout_error_response(e)
+            })?,
+    };
+
+    let payment_options = merchant
+        .allowed_payment_tokens
+        .iter()
+        .filter_map(|&token| {
+            let contract = app_state.token_registry.contract(token)?;
+            // Payments are only priced in satoshis for now
+            let amount_due = match token {
+                PaymentToken::SBTC => quote.amount,
+                PaymentToken::USDT => return None,
+            };
+            Some(PaymentOptionResponse {
+                token,
+                amount_due: amount_due.to_string(),
+                decimals: contract.decimals,
+                token_contract_id: contract.contract_id.clone(),
+                transfer_contract_id: contract.transfer_contract_id.clone(),
+                transfer_function: contract.transfer_function.clone(),
+                fee: contract.fee.to_string(),
+            })
+        })
+        .collect();
+
+    Ok(CheckoutResponse {
+        invoice_id: invoice.id.to_string(),
+        status: invoice.status,
+        merchant_name: merchant.display_name,
+        branding: merchant.branding,
+        amount: format_money_amount(invoice.amount),
+        settlement_asset: invoice.settlement_asset,
+        memo: invoice.payment_memo(),
+        merchant_order_id: invoice.merchant_order_id,
+        description: invoice.description,
+        expires_at: invoice.expires_at,
+        recipient_address,
+        quote: InvoiceQuoteResponse::from(quote),
+        payment_options,
+    })
+}
+
+fn select_payment_option(
+    checkout: &CheckoutResponse,
+    token: Option<PaymentToken>,
+) -> Result<&PaymentOptionResponse, (StatusCode, Json<ErrorResponse>)> {
+    checkout
+        .payment_options
+        .iter()
+        .find(|option| token.is_none_or(|token| option.token == token))
+        .ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ErrorResponse {
+                    error: "unsupported_payment_token".to_string(),
+                    message: "Invoice cannot be paid with this token".to_string(),
+                }),
+            )
+        })
+}
+
+/// `stacks:<recipient>?token=<contract>&amount=<base units>&memo=<memo>&...`
+fn payment_uri(checkout: &CheckoutResponse, option: &PaymentOptionResponse) -> String {
+    let mut uri = Url::parse(&format!("{}:{}", PAYMENT_URI_SCHEME, checkout.recipient_address))
+        .expect("payment URI scheme is valid");
+    uri.query_pairs_mut()
+        .append_pair("token", &option.token_contract_id)
+        .append_pair("amount", &option.amount_due)
+        .append_pair("memo", &checkout.memo)
+        .append_pair("contract", &option.transfer_contract_id)
+        .append_pair("function", &option.transfer_function)
+        .append_pair("fee", &option.fee)
+        .append_pair("invoice", &checkout.invoice_id)
+        .append_pair("expires", &checkout.quote.expires_at.timestamp().to_string());
+    uri.to_string()
+}
+
+fn render_qr_code(uri: &str, format: QrCodeFormat, size: u32) -> anyhow::Result<Vec<u8>> {
+    let code = QrCode::new(uri.as_bytes())?;
+    match format {
+        QrCodeFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+            let mut png = Vec::new();
+            image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(png)
+        }
+        QrCodeFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .min_dimensions(size, size)
+            .build()
+            .into_bytes()),
+    }
+}
+
+/// Response only valid as long as the checkout's quote, so caches drop it when the quote refreshes
+fn quote_bound_response(checkout: &CheckoutResponse, content_type: &str, body: Vec<u8>) -> Response {
+    let max_age = (checkout.quote.expires_at - Utc::now()).num_seconds().max(0);
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, format!("private, max-age={}", max_age))
+        .body(Body::from(body))
+        .expect("quote bound response headers are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Branding, InvoiceStatus, SettlementAsset};
+
+    #[test]
+    fn test_payment_uri_encodes_memo() {
+        let now = Utc::now();
+        let option = PaymentOptionResponse {
+            token: PaymentToken::SBTC,
+            amount_due: "48213".to_string(),
+            decimals: 8,
+            token_contract_id: "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF.sbtc-token".to_string(),
+            transfer_contract_id: "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF.boltproto".to_string(),
+            transfer_function: "transfer-bolt-to-bolt".to_string(),
+            fee: "2000".to_string(),
+        };
+        let checkout = CheckoutResponse {
+            invoice_id: "66e123456789abcdef012345".to_string(),
+            status: InvoiceStatus::Created,
+            merchant_name: "Coffee Shop".to_string(),
+            branding: Branding::default(),
+            amount: "49.90".to_string(),
+            settlement_asset: SettlementAsset::USD,
+            merchant_order_id: "ORD 1&2".to_string(),
+            description: None,
+            expires_at: None,
+            recipient_address: "ST1RECIPIENT".to_string(),
+            memo: "BG-MID: ORD 1&2".to_string(),
+            quote: InvoiceQuoteResponse {
+                asset: PaymentToken::SBTC,
+                amount: "48213".to_string(),
+                unit_price: "103500.00".to_string(),
+                spread: "1.00%".to_string(),
+                locked_at: now,
+                expires_at: now,
+            },
+            payment_options: Vec::new(),
+        };
+
+        let uri = payment_uri(&checkout, &option);
+        assert!(uri.starts_with("stacks:ST1RECIPIENT?token=ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF.sbtc-token&amount=48213&"));
+        assert!(uri.contains("&memo=BG-MID%3A+ORD+1%262&"));
+        assert!(render_qr_code(&uri, QrCodeFormat::Png, 200).is_ok());
+        assert!(render_qr_code(&uri, QrCodeFormat::Svg, 200).is_ok());
+    }
+}
@@ -0,0 +1,236 @@
+// src/handlers/events_handler.rs
+use std::{collections::VecDeque, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json, Response,
+    },
+};
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::handlers::invoices_handler::{expire_if_due, find_invoice};
+use crate::models::{
+    convert_string_to_object_id, DomainEvent, ErrorResponse, Invoice, InvoiceCountdownResponse,
+    InvoiceQuoteResponse, InvoiceResponse, InvoiceStatus, InvoiceUpdateResponse, PaymentResponse,
+};
+use crate::shared::RequestId;
+use crate::AppState;
+
+/// How often subscribers of an invoice awaiting payment get a countdown
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stream an invoice's status changes, payments, quote refreshes and expiry countdown as Server-Sent Events
+pub async fn stream_invoice_events(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, Json<ErrorResponse>)> {
+    let subscription = InvoiceSubscription::start(app_state, &invoice_id, request_id).await?;
+
+    let events = subscription
+        .into_stream()
+        .map(|update| Event::default().event(update.name()).json_data(&update));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// WebSocket variant of `stream_invoice_events`, each update is sent as a JSON text message
+pub async fn invoice_events_socket(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    // Subscribe before upgrading so an unknown invoice is still answered with a 404
+    let subscription = InvoiceSubscription::start(app_state, &invoice_id, request_id).await?;
+
+    Ok(upgrade.on_upgrade(move |socket| forward_updates(socket, subscription)))
+}
+
+async fn forward_updates(mut socket: WebSocket, subscription: InvoiceSubscription) {
+    let updates = subscription.into_stream();
+    tokio::pin!(updates);
+
+    loop {
+        tokio::select! {
+            update = updates.next() => {
+                let Some(update) = update else {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                };
+                let text = match serde_json::to_string(&update) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        tracing::error!("Failed to serialize invoice update: {}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum, anything else from the client is ignored
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Updates for a single invoice, built from the event bus and a countdown timer
+struct InvoiceSubscription {
+    app_state: AppState,
+    invoice: Invoice,
+    request_id: Option<String>,
+    events: broadcast::Receiver<DomainEvent>,
+    countdown: tokio::time::Interval,
+    pending: VecDeque<InvoiceUpdateResponse>,
+    finished: bool,
+}
+
+impl InvoiceSubscription {
+    async fn start(
+        app_state: AppState,
+        invoice_id: &str,
+        request_id: Option<String>,
+    ) -> Result<Self, (StatusCode, Json<ErrorResponse>)> {
+        let object_id = convert_string_to_object_id(invoice_id)?;
+
+        // Subscribe before reading the invoice so no change in between is missed
+        let events = app_state.event_bus.subscribe();
+        let invoice = find_invoice(&app_state, &object_id).await?;
+        let invoice = expire_if_due(&app_state, invoice, request_id.clone()).await?;
+
+        let mut subscription = Self {
+            app_state,
+            invoice,
+            request_id,
+            events,
+            countdown: tokio::time::interval(COUNTDOWN_INTERVAL),
+            pending: VecDeque::new(),
+            finished: false,
+        };
+        subscription.push_invoice();
+        Ok(subscription)
+    }
+
+    fn into_stream(self) -> impl Stream<Item = InvoiceUpdateResponse> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            let update = subscription.next_update().await?;
+            Some((update, subscription))
+        })
+    }
+
+    async fn next_update(&mut self) -> Option<InvoiceUpdateResponse> {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Some(update);
+            }
+            if self.finished {
+                return None;
+            }
+
+            tokio::select! {
+                received = self.events.recv() => match received {
+                    Ok(event) if event.invoice_id() == self.invoice.id => self.apply(event).await,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "Event stream of invoice {} skipped {} events, resending its state",
+                            self.invoice.id,
+                            skipped
+                        );
+                        self.reload_invoice().await;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = self.countdown.tick() => self.count_down().await,
+            }
+        }
+    }
+
+    async fn apply(&mut self, event: DomainEvent) {
+        match event {
+            DomainEvent::InvoiceStatusChanged { .. } | DomainEvent::InvoiceAmended { .. } => {
+                self.reload_invoice().await;
+            }
+            DomainEvent::QuoteRefreshed { quote, .. } => {
+                self.invoice.quote = Some(quote.clone());
+                self.pending
+                    .push_back(InvoiceUpdateResponse::Quote(InvoiceQuoteResponse::from(quote)));
+            }
+            DomainEvent::PaymentStatusChanged { payment_id, .. } => {
+                match self.app_state.payment_repository.find_by_id(&payment_id).await {
+                    Ok(Some(payment)) => self
+                        .pending
+                        .push_back(InvoiceUpdateResponse::Payment(PaymentResponse::from(payment))),
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::error!("Database error when retrieving payment {}: {}", payment_id, e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn count_down(&mut self) {
+        if self.invoice.status != InvoiceStatus::Created {
+            return;
+        }
+
+        let now = Utc::now();
+        if self.invoice.is_past_due(now) {
+            // The expiry is published on the bus and picked up like any other change
+            if let Err((_, Json(e))) =
+                expire_if_due(&self.app_state, self.invoice.clone(), self.request_id.clone()).await
+            {
+                tracing::error!("Failed to expire invoice {}: {}", self.invoice.id, e.message);
+            }
+            return;
+        }
+
+        self.pending
+            .push_back(InvoiceUpdateResponse::Countdown(InvoiceCountdownResponse {
+                invoice_expires_in_secs: self
+                    .invoice
+                    .expires_at
+                    .map(|expires_at| (expires_at - now).num_seconds()),
+                quote_expires_in_secs: self
+                    .invoice
+                    .live_quote(now)
+                    .map(|quote| (quote.expires_at - now).num_seconds()),
+            }));
+    }
+
+    async fn reload_invoice(&mut self) {
+        match self.app_state.invoice_repository.find_by_id(&self.invoice.id).await {
+            Ok(Some(invoice)) => {
+                self.invoice = invoice;
+                self.push_invoice();
+            }
+            Ok(None) => self.finished = true,
+            Err(e) => {
+                tracing::error!("Database error when retrieving invoice {}: {}", self.invoice.id, e);
+            }
+        }
+    }
+
+    /// Queue the current invoice, ending the stream once nothing more can happen to it
+    fn push_invoice(&mut self) {
+        self.finished = matches!(
+            self.invoice.status,
+            InvoiceStatus::Expired | InvoiceStatus::Cancelled | InvoiceStatus::Settled
+        );
+        self.pending
+            .push_back(InvoiceUpdateResponse::Invoice(Box::new(InvoiceResponse::from(self.invoice.clone()))));
+    }
+}
@@ -0,0 +1,98 @@
+// src/handlers/export_handler.rs
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Json, Response},
+};
+use futures::{stream, StreamExt};
+
+use crate::models::{ErrorResponse, ExportFormat, ExportQuery, ExportRecord};
+use crate::AppState;
+
+/// Longest range that can be exported at once (about 1 year)
+const MAX_EXPORT_RANGE_DAYS: i64 = 366;
+
+/// Export a merchant's invoices created in a date range, joined with their payments, as CSV or JSON Lines.
+/// Rows are streamed from the database cursor as they are read.
+pub async fn export_merchant_data(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    if query.from >= query.to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_range".to_string(),
+                message: "from must be before to".to_string(),
+            }),
+        ));
+    }
+    if query.to - query.from > chrono::Duration::days(MAX_EXPORT_RANGE_DAYS) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_range".to_string(),
+                message: "The range cannot exceed 366 days".to_string(),
+            }),
+        ));
+    }
+
+    let cursor = app_state
+        .invoice_repository
+        .export_rows(&wallet_address, query.from, query.to)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to start export for merchant {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to export invoices".to_string(),
+                }),
+            )
+        })?;
+
+    let format = query.format;
+    let header_line = match format {
+        ExportFormat::Csv => Some(Ok(ExportRecord::CSV_HEADER.to_string())),
+        ExportFormat::Jsonl => None,
+    };
+    let merchant = wallet_address.clone();
+    let lines = cursor.map(move |row| {
+        let record = ExportRecord::from(row.map_err(|e| {
+            // The response is already under way, the client sees a truncated body
+            tracing::error!("Export for merchant {} failed mid-stream: {}", merchant, e);
+            e
+        })?);
+        Ok::<_, mongodb::error::Error>(match format {
+            ExportFormat::Csv => record.to_csv_line(),
+            ExportFormat::Jsonl => {
+                let mut line = serde_json::to_string(&record).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+        })
+    });
+    let body = Body::from_stream(stream::iter(header_line).chain(lines));
+
+    let filename = format!(
+        "invoices-{}-{}-{}.{}",
+        wallet_address,
+        query.from.format("%Y%m%d"),
+        query.to.format("%Y%m%d"),
+        format.extension()
+    );
+
+    tracing::info!("Exporting invoices of merchant {} as {}", wallet_address, format.extension());
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(body)
+        .expect("export response headers are valid"))
+}
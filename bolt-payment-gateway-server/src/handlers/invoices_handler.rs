@@ -1,23 +1,55 @@
 // src/handlers/invoices.rs
+use std::collections::HashMap;
+
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
 };
 use bson::oid::ObjectId;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
+use crate::database::StatusUpdateError;
 use crate::models::{
-    convert_money_from_string, convert_string_to_object_id, CreateInvoiceRequest, ErrorResponse, Invoice, InvoiceResponse, InvoiceStatus, ListInvoicesQuery, ListInvoicesResponse
+    convert_money_from_string, convert_string_to_object_id, payout_error_response, price_error_response, status_update_error_response, Actor, AuditContext, AuditEventResponse, CancelInvoiceRequest, CreateInvoiceRequest, ErrorResponse, IncludeQuery, Invoice, InvoiceAmendment, InvoiceQuote, InvoiceQuoteResponse, InvoiceResponse, InvoiceStatus, InvoiceTimelineResponse, LineItem, LineItemRequest, ListInvoicesQuery, ListInvoicesResponse, Merchant, Payment, UpdateInvoiceRequest, format_money_amount, includes
 };
+use crate::shared::RequestId;
 use crate::AppState;
 
+/// Size limits of the descriptive invoice fields
+const MAX_DESCRIPTION_LEN: usize = 1000;
+const MAX_LINE_ITEMS: usize = 100;
+const MAX_LINE_ITEM_NAME_LEN: usize = 200;
+const MAX_METADATA_ENTRIES: usize = 20;
+const MAX_METADATA_KEY_LEN: usize = 40;
+const MAX_METADATA_VALUE_LEN: usize = 500;
+const MAX_BUYER_FIELD_LEN: usize = 254;
+
 /// Create a new invoice for a merchant
 pub async fn create_invoice(
     State(app_state): State<AppState>,
     Path(wallet_address): Path<String>,
+    RequestId(request_id): RequestId,
     Json(request): Json<CreateInvoiceRequest>,
 ) -> Result<Json<InvoiceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let invoice = new_invoice(&app_state, &wallet_address, request).await?;
+
+    let context = AuditContext::new(
+        Actor::Merchant { wallet_address: wallet_address.clone() },
+        request_id,
+    );
+    save_invoice(&app_state, &invoice, &context).await?;
+
+    Ok(Json(InvoiceResponse::from(invoice)))
+}
+
+/// Build and validate a new invoice for a merchant from a creation request, without storing it.
+/// Shared by every way of creating invoices (API, payment links) so they follow the same rules.
+pub(crate) async fn new_invoice(
+    app_state: &AppState,
+    wallet_address: &str,
+    request: CreateInvoiceRequest,
+) -> Result<Invoice, (StatusCode, Json<ErrorResponse>)> {
     // Validate amount
     if request.amount.parse::<f64>().is_err() || request.amount.parse::<f64>().unwrap() <= 0.0 {
         return Err((
@@ -29,10 +61,36 @@ pub async fn create_invoice(
         ));
     }
 
-    // Create invoice
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    let recipient_address = app_state
+        .payout_service
+        .resolve_recipient(&merchant)
+        .map_err(|e| {
+            tracing::warn!("Cannot resolve recipient for merchant {}: {}", wallet_address, e);
+            payout_error_response(e)
+        })?;
+
+    let now = Utc::now();
+    let expires_at = resolve_invoice_deadline(request.expires_in_secs, request.due_date, &merchant, now)?
+        .unwrap_or_else(|| now + chrono::Duration::seconds(merchant.default_invoice_expiry_secs));
+
     let invoice = Invoice {
         id: ObjectId::new(),
-        wallet_address: wallet_address.clone(),
+        wallet_address: wallet_address.to_string(),
         status: InvoiceStatus::Created,
         amount: convert_money_from_string(request.amount).map_err(|_| {
             (
@@ -43,14 +101,36 @@ pub async fn create_invoice(
                 }),
             )
         })?,
-        settlement_asset: request.settlement_asset,
+        settlement_asset: request
+            .settlement_asset
+            .unwrap_or(merchant.default_settlement_asset),
         merchant_order_id: request.merchant_order_id,
-        created_at: Utc::now(),
-        expires_at: Some(Utc::now() + chrono::Duration::minutes(2)), // 2 minute expiry
+        created_at: now,
+        expires_at: Some(expires_at),
+        recipient_address: Some(recipient_address),
+        quote: None,
+        description: request.description,
+        line_items: parse_line_items(request.line_items.unwrap_or_default())?,
+        metadata: request.metadata.unwrap_or_default(),
+        buyer_email: request.buyer_email,
+        buyer_reference: request.buyer_reference,
+        payment_link_id: None,
+        subscription_id: None,
+        version: 0,
     };
 
-    // Save to database
-    if let Err(e) = app_state.invoice_repository.create(&invoice).await {
+    validate_invoice_details(&invoice)?;
+
+    Ok(invoice)
+}
+
+/// Store an invoice built by `new_invoice`
+pub(crate) async fn save_invoice(
+    app_state: &AppState,
+    invoice: &Invoice,
+    context: &AuditContext,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if let Err(e) = app_state.invoice_repository.create(invoice, context).await {
         tracing::error!("Failed to create invoice in database: {}", e);
         return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -62,20 +142,22 @@ pub async fn create_invoice(
     }
 
     tracing::info!(
-        "Created invoice {} for merchant {} with amount {} {}",
-        invoice.id.to_string(),
-        wallet_address,
+        "Created invoice {} for merchant {} with amount {} {:?}",
+        invoice.id,
+        invoice.wallet_address,
         invoice.amount,
-        format!("{:?}", invoice.settlement_asset)
+        invoice.settlement_asset
     );
 
-    Ok(Json(InvoiceResponse::from(invoice)))
+    Ok(())
 }
 
-/// Get a specific invoice by ID
+/// Get a specific invoice by ID, with its payments when `include=payments`
 pub async fn get_invoice(
     State(app_state): State<AppState>,
     Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    Query(include): Query<IncludeQuery>,
 ) -> Result<Json<InvoiceResponse>, (StatusCode, Json<ErrorResponse>)> {
 
     // convert the string ID to ObjectId
@@ -85,7 +167,26 @@ pub async fn get_invoice(
     match app_state.invoice_repository.find_by_id(&object_id).await {
         Ok(Some(invoice)) => {
             tracing::info!("Retrieved invoice {} from database", invoice_id);
-            Ok(Json(InvoiceResponse::from(invoice)))
+            let invoice = expire_if_due(&app_state, invoice, request_id).await?;
+            if !includes(include.include.as_deref(), "payments") {
+                return Ok(Json(InvoiceResponse::from(invoice)));
+            }
+
+            let payments = app_state
+                .payment_repository
+                .find_by_invoice_id(&object_id)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Database error when retrieving payments for invoice {}: {}", invoice_id, e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse {
+                            error: "database_error".to_string(),
+                            message: "Failed to retrieve payments".to_string(),
+                        }),
+                    )
+                })?;
+            Ok(Json(InvoiceResponse::from(invoice).with_payments(payments)))
         }
         Ok(None) => {
             tracing::warn!("Invoice {} not found", invoice_id);
@@ -110,6 +211,176 @@ pub async fn get_invoice(
     }
 }
 
+/// Get the BTC quote locked for an invoice, locking a new one when the previous window has passed
+pub async fn get_invoice_quote(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+) -> Result<Json<InvoiceQuoteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&invoice_id)?;
+
+    let invoice = find_invoice(&app_state, &object_id).await?;
+
+    let invoice = expire_if_due(&app_state, invoice, request_id).await?;
+    let quote = current_quote(&app_state, &invoice).await?;
+
+    Ok(Json(InvoiceQuoteResponse::from(quote)))
+}
+
+/// Cancel an invoice that is still awaiting payment
+pub async fn cancel_invoice(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    request: Option<Json<CancelInvoiceRequest>>,
+) -> Result<Json<InvoiceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&invoice_id)?;
+    let invoice = find_invoice(&app_state, &object_id).await?;
+    let invoice = expire_if_due(&app_state, invoice, request_id.clone()).await?;
+
+    let mut context = AuditContext::new(
+        Actor::Merchant { wallet_address: invoice.wallet_address.clone() },
+        request_id,
+    );
+    if let Some(Json(CancelInvoiceRequest { reason: Some(reason) })) = request {
+        context = context.with_reason(reason);
+    }
+
+    // Conditional on the invoice being unchanged since it was read, so a payment that claimed it first wins
+    let cancelled = app_state
+        .invoice_repository
+        .update_status(&invoice, InvoiceStatus::Cancelled, &context)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to cancel invoice {}: {}", invoice_id, e);
+            invoice_locked_error_response(&e)
+        })?;
+
+    tracing::info!("Cancelled invoice {}", invoice_id);
+
+    Ok(Json(InvoiceResponse::from(cancelled)))
+}
+
+/// Amend the amount, merchant order ID or deadline of an invoice that is still awaiting payment
+pub async fn update_invoice(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
+    Json(request): Json<UpdateInvoiceRequest>,
+) -> Result<Json<InvoiceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&invoice_id)?;
+    let invoice = find_invoice(&app_state, &object_id).await?;
+    let invoice = expire_if_due(&app_state, invoice, request_id.clone()).await?;
+
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&invoice.wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", invoice.wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    let mut amendment = InvoiceAmendment {
+        merchant_order_id: request.merchant_order_id,
+        expires_at: resolve_invoice_deadline(request.expires_in_secs, request.due_date, &merchant, Utc::now())?,
+        ..Default::default()
+    };
+
+    if let Some(amount) = request.amount {
+        if amount.parse::<f64>().is_err() || amount.parse::<f64>().unwrap() <= 0.0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "invalid_amount".to_string(),
+                    message: "Amount must be a positive number".to_string(),
+                }),
+            ));
+        }
+        amendment.amount = Some(convert_money_from_string(amount).map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "invalid_amount_format".to_string(),
+                    message: "Amount format is invalid".to_string(),
+                }),
+            )
+        })?);
+    }
+
+    if let (Some(amount), Some(total)) = (amendment.amount, invoice.line_items_total())
+        && amount != total
+    {
+        return Err(line_items_mismatch_error(total));
+    }
+
+    if amendment == InvoiceAmendment::default() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "empty_amendment".to_string(),
+                message: "At least one of amount, merchant_order_id, expires_in_secs or due_date must be set".to_string(),
+            }),
+        ));
+    }
+
+    let context = AuditContext::new(
+        Actor::Merchant { wallet_address: invoice.wallet_address.clone() },
+        request_id,
+    )
+    .with_reason(describe_amendment(&invoice, &amendment));
+
+    // Conditional on the invoice still being `Created`, so a payment that claimed it first wins
+    let amended = app_state
+        .invoice_repository
+        .amend(&invoice, &amendment, &context)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to amend invoice {}: {}", invoice_id, e);
+            invoice_locked_error_response(&e)
+        })?;
+
+    tracing::info!("Amended invoice {}: {}", invoice_id, describe_amendment(&invoice, &amendment));
+
+    Ok(Json(InvoiceResponse::from(amended)))
+}
+
+/// Get the audit timeline of an invoice and its payments
+pub async fn get_invoice_timeline(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+) -> Result<Json<InvoiceTimelineResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&invoice_id)?;
+
+    find_invoice(&app_state, &object_id).await?;
+
+    let events = app_state
+        .audit_repository
+        .find_by_invoice_id(&object_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving timeline for invoice {}: {}", invoice_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve invoice timeline".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(InvoiceTimelineResponse {
+        invoice_id,
+        events: events.into_iter().map(AuditEventResponse::from).collect(),
+    }))
+}
+
 /// List invoices for a merchant with optional filtering
 pub async fn list_invoices(
     State(app_state): State<AppState>,
@@ -159,6 +430,52 @@ pub async fn list_invoices(
         invoices.retain(|inv| inv.created_at <= to_date);
     }
 
+    if let Some(description) = &query.description {
+        let description = description.to_lowercase();
+        invoices.retain(|inv| {
+            inv.description
+                .as_ref()
+                .is_some_and(|d| d.to_lowercase().contains(&description))
+        });
+    }
+
+    if let Some(buyer_email) = &query.buyer_email {
+        invoices.retain(|inv| {
+            inv.buyer_email
+                .as_ref()
+                .is_some_and(|e| e.eq_ignore_ascii_case(buyer_email))
+        });
+    }
+
+    if let Some(buyer_reference) = &query.buyer_reference {
+        invoices.retain(|inv| inv.buyer_reference.as_ref() == Some(buyer_reference));
+    }
+
+    if let Some(subscription_id) = &query.subscription_id {
+        let subscription_id = convert_string_to_object_id(subscription_id)?;
+        invoices.retain(|inv| inv.subscription_id == Some(subscription_id));
+    }
+
+    if let Some(payment_link_id) = &query.payment_link_id {
+        let payment_link_id = convert_string_to_object_id(payment_link_id)?;
+        invoices.retain(|inv| inv.payment_link_id == Some(payment_link_id));
+    }
+
+    match (&query.metadata_key, &query.metadata_value) {
+        (Some(key), Some(value)) => invoices.retain(|inv| inv.metadata.get(key) == Some(value)),
+        (Some(key), None) => invoices.retain(|inv| inv.metadata.contains_key(key)),
+        (None, Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "invalid_metadata_filter".to_string(),
+                    message: "metadata_value requires metadata_key".to_string(),
+                }),
+            ));
+        }
+        (None, None) => {}
+    }
+
     let total = invoices.len();
     
     // Apply pagination
@@ -179,8 +496,40 @@ pub async fn list_invoices(
         query.limit
     );
 
+    let items = if includes(query.include.as_deref(), "payments") {
+        let invoice_ids: Vec<ObjectId> = paginated_invoices.iter().map(|inv| inv.id).collect();
+        let mut payments_by_invoice: HashMap<ObjectId, Vec<Payment>> = HashMap::new();
+        for payment in app_state
+            .payment_repository
+            .find_by_invoice_ids(&invoice_ids)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error when retrieving payments for merchant {}: {}", wallet_address, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "database_error".to_string(),
+                        message: "Failed to retrieve payments".to_string(),
+                    }),
+                )
+            })?
+        {
+            payments_by_invoice.entry(payment.invoice_id).or_default().push(payment);
+        }
+
+        paginated_invoices
+            .into_iter()
+            .map(|inv| {
+                let payments = payments_by_invoice.remove(&inv.id).unwrap_or_default();
+                InvoiceResponse::from(inv).with_payments(payments)
+            })
+            .collect()
+    } else {
+        paginated_invoices.into_iter().map(InvoiceResponse::from).collect()
+    };
+
     let response = ListInvoicesResponse {
-        items: paginated_invoices.into_iter().map(InvoiceResponse::from).collect(),
+        items,
         total,
         limit: query.limit,
         offset: query.offset,
@@ -188,3 +537,388 @@ pub async fn list_invoices(
 
     Ok(Json(response))
 }
+
+fn parse_line_items(
+    items: Vec<LineItemRequest>,
+) -> Result<Vec<LineItem>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_line_item".to_string(),
+                message,
+            }),
+        )
+    };
+
+    items
+        .into_iter()
+        .map(|item| {
+            let unit_price = convert_money_from_string(item.unit_price).map_err(|_| {
+                invalid(format!("Unit price of line item '{}' is invalid", item.name))
+            })?;
+            let tax = match item.tax {
+                Some(tax) => convert_money_from_string(tax).map_err(|_| {
+                    invalid(format!("Tax of line item '{}' is invalid", item.name))
+                })?,
+                None => 0,
+            };
+            Ok(LineItem {
+                name: item.name,
+                quantity: item.quantity,
+                unit_price,
+                tax,
+            })
+        })
+        .collect()
+}
+
+/// Check the descriptive fields of a new invoice against the size limits,
+/// and that its line items add up to the invoice amount
+fn validate_invoice_details(invoice: &Invoice) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: String| {
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message,
+            }),
+        ))
+    };
+
+    if let Some(description) = &invoice.description
+        && description.chars().count() > MAX_DESCRIPTION_LEN
+    {
+        return invalid(
+            "invalid_description",
+            format!("Description cannot exceed {} characters", MAX_DESCRIPTION_LEN),
+        );
+    }
+
+    if invoice.line_items.len() > MAX_LINE_ITEMS {
+        return invalid(
+            "invalid_line_item",
+            format!("An invoice cannot have more than {} line items", MAX_LINE_ITEMS),
+        );
+    }
+    for item in &invoice.line_items {
+        if item.name.trim().is_empty() || item.name.chars().count() > MAX_LINE_ITEM_NAME_LEN {
+            return invalid(
+                "invalid_line_item",
+                format!("Line item names must have 1 to {} characters", MAX_LINE_ITEM_NAME_LEN),
+            );
+        }
+        if item.quantity == 0 {
+            return invalid(
+                "invalid_line_item",
+                format!("Quantity of line item '{}' must be positive", item.name),
+            );
+        }
+    }
+    if !invoice.line_items.is_empty() && invoice.line_items_total().is_none() {
+        return invalid("invalid_line_items", "Line item totals are too large".to_string());
+    }
+    if let Some(total) = invoice.line_items_total()
+        && total != invoice.amount
+    {
+        return Err(line_items_mismatch_error(total));
+    }
+
+    if invoice.metadata.len() > MAX_METADATA_ENTRIES {
+        return invalid(
+            "invalid_metadata",
+            format!("Metadata cannot have more than {} keys", MAX_METADATA_ENTRIES),
+        );
+    }
+    for (key, value) in &invoice.metadata {
+        if key.is_empty() || key.chars().count() > MAX_METADATA_KEY_LEN {
+            return invalid(
+                "invalid_metadata",
+                format!("Metadata keys must have 1 to {} characters", MAX_METADATA_KEY_LEN),
+            );
+        }
+        if value.chars().count() > MAX_METADATA_VALUE_LEN {
+            return invalid(
+                "invalid_metadata",
+                format!("Metadata value of '{}' cannot exceed {} characters", key, MAX_METADATA_VALUE_LEN),
+            );
+        }
+    }
+
+    if let Some(email) = &invoice.buyer_email
+        && !is_plausible_email(email)
+    {
+        return invalid("invalid_buyer_email", "Buyer email is not a valid email address".to_string());
+    }
+    if let Some(reference) = &invoice.buyer_reference
+        && (reference.trim().is_empty() || reference.chars().count() > MAX_BUYER_FIELD_LEN)
+    {
+        return invalid(
+            "invalid_buyer_reference",
+            format!("Buyer reference must have 1 to {} characters", MAX_BUYER_FIELD_LEN),
+        );
+    }
+
+    Ok(())
+}
+
+/// Shape check only, the address is never contacted
+pub(crate) fn is_plausible_email(email: &str) -> bool {
+    if email.len() > MAX_BUYER_FIELD_LEN || email.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+fn line_items_mismatch_error(total: u128) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: "line_items_mismatch".to_string(),
+            message: format!(
+                "Line items add up to {}, which does not match the invoice amount",
+                format_money_amount(total)
+            ),
+        }),
+    )
+}
+
+/// Requested invoice deadline, bounded by the merchant's min and max lifetime.
+/// Returns `None` when neither a lifetime nor a due date was requested.
+fn resolve_invoice_deadline(
+    expires_in_secs: Option<i64>,
+    due_date: Option<DateTime<Utc>>,
+    merchant: &Merchant,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_expiry".to_string(),
+                message,
+            }),
+        )
+    };
+
+    let lifetime_secs = match (expires_in_secs, due_date) {
+        (Some(_), Some(_)) => {
+            return Err(invalid(
+                "Only one of expires_in_secs and due_date can be set".to_string(),
+            ));
+        }
+        (Some(expires_in_secs), None) => expires_in_secs,
+        (None, Some(due_date)) => (due_date - now).num_seconds(),
+        (None, None) => return Ok(None),
+    };
+
+    if !merchant.accepts_invoice_lifetime(lifetime_secs) {
+        return Err(invalid(format!(
+            "Invoice lifetime must be between {} and {} seconds",
+            merchant.min_invoice_expiry_secs, merchant.max_invoice_expiry_secs
+        )));
+    }
+
+    Ok(Some(
+        due_date.unwrap_or_else(|| now + chrono::Duration::seconds(lifetime_secs)),
+    ))
+}
+
+/// Move an unpaid invoice whose deadline has passed to `Expired`, returning the current invoice
+pub(crate) async fn expire_if_due(
+    app_state: &AppState,
+    mut invoice: Invoice,
+    request_id: Option<String>,
+) -> Result<Invoice, (StatusCode, Json<ErrorResponse>)> {
+    let context = AuditContext::new(
+        Actor::System { worker: "invoice_expiry".to_string() },
+        request_id,
+    )
+    .with_reason("Invoice deadline passed");
+
+    loop {
+        if invoice.status != InvoiceStatus::Created || !invoice.is_past_due(Utc::now()) {
+            return Ok(invoice);
+        }
+
+        match app_state
+            .invoice_repository
+            .update_status(&invoice, InvoiceStatus::Expired, &context)
+            .await
+        {
+            Ok(expired) => {
+                tracing::info!("Invoice {} expired", invoice.id);
+                return Ok(expired);
+            }
+            // Someone else changed the invoice first, check again what is stored now
+            Err(StatusUpdateError::Conflict { .. } | StatusUpdateError::VersionConflict { .. }) => {
+                invoice = find_invoice(app_state, &invoice.id).await?;
+            }
+            Err(e) => {
+                tracing::error!("Failed to expire invoice {}: {}", invoice.id, e);
+                return Err(status_update_error_response(&e));
+            }
+        }
+    }
+}
+
+/// The invoice's live locked quote, or a newly locked one if the previous window has passed
+pub(crate) async fn current_quote(
+    app_state: &AppState,
+    invoice: &Invoice,
+) -> Result<InvoiceQuote, (StatusCode, Json<ErrorResponse>)> {
+    if invoice.status != InvoiceStatus::Created {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "invoice_not_payable".to_string(),
+                message: format!("Invoice in status {:?} cannot be paid", invoice.status),
+            }),
+        ));
+    }
+
+    let now = Utc::now();
+    if let Some(quote) = invoice.live_quote(now) {
+        return Ok(quote.clone());
+    }
+
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&invoice.wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", invoice.wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    let quote = app_state
+        .quote_service
+        .lock_quote(
+            invoice.amount,
+            merchant.fee_schedule.quote_spread_bps,
+            chrono::Duration::seconds(merchant.quote_window_secs),
+            invoice.expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get Bitcoin price: {}", e);
+            price_error_response(e.as_ref())
+        })?;
+
+    match app_state.invoice_repository.set_quote(invoice, &quote).await {
+        Ok(_) => {
+            tracing::info!(
+                "Locked quote of {} sats for invoice {} until {}",
+                quote.amount,
+                invoice.id,
+                quote.expires_at
+            );
+            Ok(quote)
+        }
+        Err(StatusUpdateError::Conflict { .. }) => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "invoice_not_payable".to_string(),
+                message: "Invoice is no longer awaiting payment".to_string(),
+            }),
+        )),
+        // A concurrent request usually locked a quote first, which is honored as well
+        Err(e @ StatusUpdateError::VersionConflict { .. }) => {
+            let current = find_invoice(app_state, &invoice.id).await?;
+            match current.live_quote(Utc::now()) {
+                Some(quote) if current.status == InvoiceStatus::Created => Ok(quote.clone()),
+                _ => Err(status_update_error_response(&e)),
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to store quote for invoice {}: {}", invoice.id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to store quote".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+pub(crate) async fn find_invoice(
+    app_state: &AppState,
+    invoice_id: &ObjectId,
+) -> Result<Invoice, (StatusCode, Json<ErrorResponse>)> {
+    match app_state.invoice_repository.find_by_id(invoice_id).await {
+        Ok(Some(invoice)) => Ok(invoice),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "invoice_not_found".to_string(),
+                message: "Invoice not found".to_string(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Database error when retrieving invoice {}: {}", invoice_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve invoice".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+/// Cancellation and amendment only apply to invoices in `Created`
+fn invoice_locked_error_response(
+    err: &StatusUpdateError<InvoiceStatus>,
+) -> (StatusCode, Json<ErrorResponse>) {
+    match err {
+        StatusUpdateError::Database(_) | StatusUpdateError::VersionConflict { .. } => {
+            status_update_error_response(err)
+        }
+        _ => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "invoice_not_modifiable".to_string(),
+                message: "Invoice can only be changed while awaiting payment with no payment in progress".to_string(),
+            }),
+        ),
+    }
+}
+
+fn describe_amendment(invoice: &Invoice, amendment: &InvoiceAmendment) -> String {
+    let mut changes = Vec::new();
+    if let Some(amount) = amendment.amount {
+        changes.push(format!(
+            "amount {} -> {}",
+            format_money_amount(invoice.amount),
+            format_money_amount(amount)
+        ));
+    }
+    if let Some(merchant_order_id) = &amendment.merchant_order_id {
+        changes.push(format!(
+            "merchant_order_id {} -> {}",
+            invoice.merchant_order_id, merchant_order_id
+        ));
+    }
+    if let Some(expires_at) = amendment.expires_at {
+        changes.push(format!("expires_at {:?} -> {}", invoice.expires_at, expires_at));
+    }
+    format!("Amended {}", changes.join(", "))
+}
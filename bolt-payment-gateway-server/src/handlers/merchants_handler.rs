@@ -0,0 +1,296 @@
+// src/handlers/merchants_handler.rs
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use bson::oid::ObjectId;
+use chrono::Utc;
+
+use crate::models::{
+    CreateMerchantRequest, ErrorResponse, Merchant, MerchantResponse, UpdateMerchantRequest,
+};
+use crate::AppState;
+
+/// Longest invoice lifetime a merchant may configure (1 year)
+const MAX_INVOICE_EXPIRY_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Bounds of the quote window a merchant may configure (10 seconds to 1 hour)
+const MIN_QUOTE_WINDOW_SECS: i64 = 10;
+const MAX_QUOTE_WINDOW_SECS: i64 = 60 * 60;
+
+/// Create a merchant profile
+pub async fn create_merchant(
+    State(app_state): State<AppState>,
+    Json(request): Json<CreateMerchantRequest>,
+) -> Result<Json<MerchantResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let defaults = Merchant::with_defaults(&request.wallet_address);
+    let now = Utc::now();
+
+    let mut merchant = Merchant {
+        id: ObjectId::new(),
+        wallet_address: request.wallet_address,
+        display_name: request.display_name,
+        payout_address: None,
+        payout_address_verified: false,
+        custody_mode: request.custody_mode.unwrap_or(defaults.custody_mode),
+        default_settlement_asset: request
+            .default_settlement_asset
+            .unwrap_or(defaults.default_settlement_asset),
+        default_invoice_expiry_secs: request
+            .default_invoice_expiry_secs
+            .unwrap_or(defaults.default_invoice_expiry_secs),
+        min_invoice_expiry_secs: request
+            .min_invoice_expiry_secs
+            .unwrap_or(defaults.min_invoice_expiry_secs),
+        max_invoice_expiry_secs: request
+            .max_invoice_expiry_secs
+            .unwrap_or(defaults.max_invoice_expiry_secs),
+        quote_window_secs: request.quote_window_secs.unwrap_or(defaults.quote_window_secs),
+        allowed_payment_tokens: request
+            .allowed_payment_tokens
+            .unwrap_or(defaults.allowed_payment_tokens),
+        fee_schedule: request.fee_schedule.unwrap_or(defaults.fee_schedule),
+        webhook_url: request.webhook_url,
+        branding: request.branding.unwrap_or(defaults.branding),
+        created_at: now,
+        updated_at: now,
+    };
+    merchant.set_payout_address(request.payout_address);
+
+    validate_merchant(&merchant)?;
+
+    if let Err(e) = app_state.merchant_repository.create(&merchant).await {
+        if e.to_string().contains("duplicate key") || e.to_string().contains("E11000") {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: "merchant_already_exists".to_string(),
+                    message: "A merchant with this wallet address already exists".to_string(),
+                }),
+            ));
+        }
+        tracing::error!("Failed to create merchant in database: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to create merchant".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Created merchant profile for {}", merchant.wallet_address);
+
+    Ok(Json(MerchantResponse::from(merchant)))
+}
+
+/// Get a merchant profile
+pub async fn get_merchant(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+) -> Result<Json<MerchantResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let merchant = find_merchant(&app_state, &wallet_address).await?;
+    Ok(Json(MerchantResponse::from(merchant)))
+}
+
+/// Update the fields present in the request
+pub async fn update_merchant(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Json(request): Json<UpdateMerchantRequest>,
+) -> Result<Json<MerchantResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut merchant = find_merchant(&app_state, &wallet_address).await?;
+
+    if let Some(display_name) = request.display_name {
+        merchant.display_name = display_name;
+    }
+    if let Some(payout_address) = request.payout_address
+        && merchant.payout_address.as_ref() != Some(&payout_address)
+    {
+        merchant.set_payout_address(Some(payout_address));
+    }
+    if let Some(custody_mode) = request.custody_mode {
+        merchant.custody_mode = custody_mode;
+    }
+    if let Some(asset) = request.default_settlement_asset {
+        merchant.default_settlement_asset = asset;
+    }
+    if let Some(expiry) = request.default_invoice_expiry_secs {
+        merchant.default_invoice_expiry_secs = expiry;
+    }
+    if let Some(min_expiry) = request.min_invoice_expiry_secs {
+        merchant.min_invoice_expiry_secs = min_expiry;
+    }
+    if let Some(max_expiry) = request.max_invoice_expiry_secs {
+        merchant.max_invoice_expiry_secs = max_expiry;
+    }
+    if let Some(quote_window) = request.quote_window_secs {
+        merchant.quote_window_secs = quote_window;
+    }
+    if let Some(tokens) = request.allowed_payment_tokens {
+        merchant.allowed_payment_tokens = tokens;
+    }
+    if let Some(fee_schedule) = request.fee_schedule {
+        merchant.fee_schedule = fee_schedule;
+    }
+    if let Some(webhook_url) = request.webhook_url {
+        merchant.webhook_url = Some(webhook_url);
+    }
+    if let Some(branding) = request.branding {
+        merchant.branding = branding;
+    }
+    merchant.updated_at = Utc::now();
+
+    validate_merchant(&merchant)?;
+
+    match app_state.merchant_repository.update(&merchant).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "merchant_not_found".to_string(),
+                    message: "Merchant not found".to_string(),
+                }),
+            ));
+        }
+        Err(e) => {
+            tracing::error!("Failed to update merchant {}: {}", wallet_address, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to update merchant".to_string(),
+                }),
+            ));
+        }
+    }
+
+    tracing::info!("Updated merchant profile for {}", wallet_address);
+
+    Ok(Json(MerchantResponse::from(merchant)))
+}
+
+/// Delete a merchant profile, the merchant falls back to the gateway defaults
+pub async fn delete_merchant(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    match app_state.merchant_repository.delete(&wallet_address).await {
+        Ok(true) => {
+            tracing::info!("Deleted merchant profile for {}", wallet_address);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "merchant_not_found".to_string(),
+                message: "Merchant not found".to_string(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to delete merchant {}: {}", wallet_address, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to delete merchant".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+async fn find_merchant(
+    app_state: &AppState,
+    wallet_address: &str,
+) -> Result<Merchant, (StatusCode, Json<ErrorResponse>)> {
+    match app_state.merchant_repository.find_by_wallet_address(wallet_address).await {
+        Ok(Some(merchant)) => Ok(merchant),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "merchant_not_found".to_string(),
+                message: "Merchant not found".to_string(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Database error when retrieving merchant {}: {}", wallet_address, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+fn validate_merchant(merchant: &Merchant) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: &str| {
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        ))
+    };
+
+    if merchant.wallet_address.trim().is_empty() {
+        return invalid("invalid_wallet_address", "Wallet address cannot be empty");
+    }
+    if merchant.display_name.trim().is_empty() {
+        return invalid("invalid_display_name", "Display name cannot be empty");
+    }
+    if merchant.min_invoice_expiry_secs <= 0
+        || merchant.min_invoice_expiry_secs > merchant.max_invoice_expiry_secs
+        || merchant.max_invoice_expiry_secs > MAX_INVOICE_EXPIRY_SECS
+    {
+        return invalid(
+            "invalid_invoice_expiry",
+            "Invoice expiry bounds must satisfy 0 < min <= max <= 1 year",
+        );
+    }
+    if !merchant.accepts_invoice_lifetime(merchant.default_invoice_expiry_secs) {
+        return invalid(
+            "invalid_invoice_expiry",
+            "Default invoice expiry must be within the merchant's min and max expiry",
+        );
+    }
+    if merchant.quote_window_secs < MIN_QUOTE_WINDOW_SECS
+        || merchant.quote_window_secs > MAX_QUOTE_WINDOW_SECS
+    {
+        return invalid(
+            "invalid_quote_window",
+            "Quote window must be between 10 seconds and 1 hour",
+        );
+    }
+    if merchant.allowed_payment_tokens.is_empty() {
+        return invalid(
+            "invalid_payment_tokens",
+            "At least one payment token must be allowed",
+        );
+    }
+    if !merchant.allowed_payment_tokens.iter().all(|token| token.is_priced()) {
+        return invalid(
+            "invalid_payment_tokens",
+            "Only sBTC payments can be accepted for now",
+        );
+    }
+    if merchant.fee_schedule.quote_spread_bps > 10_000
+        || merchant.fee_schedule.payment_spread_bps > 10_000
+    {
+        return invalid("invalid_fee_schedule", "Spreads cannot exceed 10000 basis points");
+    }
+    if let Some(url) = &merchant.webhook_url
+        && !url.starts_with("https://")
+        && !url.starts_with("http://")
+    {
+        return invalid("invalid_webhook_url", "Webhook URL must be an http(s) URL");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// src/handlers/metrics_handler.rs
+use std::fmt::Write;
+
+use axum::{
+    extract::State,
+    http::header,
+    response::IntoResponse,
+};
+
+use crate::models::{format_money_amount, BTC_USD_PAIR};
+use crate::AppState;
+
+/// Prometheus text format, for alerting on the price guard.
+/// Counters are per process, a scraper sums them over the replicas.
+pub async fn get_metrics(State(app_state): State<AppState>) -> impl IntoResponse {
+    let mut body = String::new();
+    if let Some(guard) = app_state.quote_service.price_guard() {
+        let metrics = guard.metrics();
+        let pair = BTC_USD_PAIR;
+
+        let _ = writeln!(body, "# HELP price_guard_halted Whether new payments are halted by a failed price check");
+        let _ = writeln!(body, "# TYPE price_guard_halted gauge");
+        let _ = writeln!(body, "price_guard_halted{{pair=\"{}\"}} {}", pair, metrics.halted as u8);
+
+        let _ = writeln!(body, "# HELP price_guard_trips_total Halts tripped by this process, per failed check");
+        let _ = writeln!(body, "# TYPE price_guard_trips_total counter");
+        for (check, trips) in metrics.trips {
+            let _ = writeln!(
+                body,
+                "price_guard_trips_total{{pair=\"{}\",check=\"{}\"}} {}",
+                pair,
+                check.name(),
+                trips
+            );
+        }
+
+        if let Some(price) = metrics.last_price {
+            let _ = writeln!(body, "# HELP price_guard_last_price_usd Last price that passed the checks");
+            let _ = writeln!(body, "# TYPE price_guard_last_price_usd gauge");
+            let _ = writeln!(body, "price_guard_last_price_usd{{pair=\"{}\"}} {}", pair, format_money_amount(price));
+        }
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
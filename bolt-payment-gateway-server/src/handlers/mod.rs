@@ -1,4 +1,13 @@
 // src/handlers/mod.rs
+pub mod analytics_handler;
+pub mod checkout_handler;
+pub mod events_handler;
+pub mod export_handler;
 pub mod invoices_handler;
+pub mod merchants_handler;
+pub mod metrics_handler;
+pub mod payment_links_handler;
 pub mod payments_handler;
+pub mod prices_handler;
 pub mod quotes_handler;
+pub mod subscriptions_handler;
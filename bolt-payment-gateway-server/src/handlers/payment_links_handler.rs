@@ -0,0 +1,357 @@
+// src/handlers/payment_links_handler.rs
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use bson::oid::ObjectId;
+use chrono::Utc;
+
+use crate::handlers::invoices_handler::{new_invoice, save_invoice};
+use crate::models::{
+    convert_money_from_string, convert_string_to_object_id, format_money_amount, Actor,
+    AuditContext, CreateInvoiceRequest, CreatePaymentLinkRequest, ErrorResponse, InvoiceResponse,
+    LinkAmount, LinkAmountDto, ListPaymentLinksResponse, OpenPaymentLinkRequest, PaymentLink,
+    PaymentLinkResponse, UpdatePaymentLinkRequest,
+};
+use crate::shared::RequestId;
+use crate::AppState;
+
+/// Longest payment link title
+const MAX_TITLE_LEN: usize = 200;
+
+/// Create a payment link for a merchant
+pub async fn create_payment_link(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Json(request): Json<CreatePaymentLinkRequest>,
+) -> Result<Json<PaymentLinkResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    let now = Utc::now();
+    let link = PaymentLink {
+        id: ObjectId::new(),
+        wallet_address: wallet_address.clone(),
+        title: request.title,
+        description: request.description,
+        amount: parse_link_amount(request.amount)?,
+        settlement_asset: request
+            .settlement_asset
+            .unwrap_or(merchant.default_settlement_asset),
+        usage_limit: request.usage_limit,
+        usage_count: 0,
+        active: request.active.unwrap_or(true),
+        created_at: now,
+        updated_at: now,
+    };
+
+    validate_payment_link(&link)?;
+
+    if let Err(e) = app_state.payment_link_repository.create(&link).await {
+        tracing::error!("Failed to create payment link in database: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to create payment link".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Created payment link {} for merchant {}", link.id, wallet_address);
+
+    Ok(Json(PaymentLinkResponse::from(link)))
+}
+
+/// List a merchant's payment links, newest first
+pub async fn list_payment_links(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+) -> Result<Json<ListPaymentLinksResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let links = app_state
+        .payment_link_repository
+        .find_by_merchant(&wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when listing payment links of {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve payment links".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(ListPaymentLinksResponse {
+        total: links.len(),
+        items: links.into_iter().map(PaymentLinkResponse::from).collect(),
+    }))
+}
+
+/// Get a payment link by ID
+pub async fn get_payment_link(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<String>,
+) -> Result<Json<PaymentLinkResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let link = find_payment_link(&app_state, &link_id).await?;
+    Ok(Json(PaymentLinkResponse::from(link)))
+}
+
+/// Update the fields present in the request
+pub async fn update_payment_link(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<String>,
+    Json(request): Json<UpdatePaymentLinkRequest>,
+) -> Result<Json<PaymentLinkResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut link = find_payment_link(&app_state, &link_id).await?;
+
+    if let Some(title) = request.title {
+        link.title = title;
+    }
+    if let Some(description) = request.description {
+        link.description = Some(description);
+    }
+    if let Some(amount) = request.amount {
+        link.amount = parse_link_amount(amount)?;
+    }
+    if let Some(asset) = request.settlement_asset {
+        link.settlement_asset = asset;
+    }
+    if let Some(usage_limit) = request.usage_limit {
+        link.usage_limit = Some(usage_limit);
+    }
+    if let Some(active) = request.active {
+        link.active = active;
+    }
+    link.updated_at = Utc::now();
+
+    validate_payment_link(&link)?;
+
+    let link = match app_state.payment_link_repository.update(&link).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return Err(payment_link_not_found()),
+        Err(e) => {
+            tracing::error!("Failed to update payment link {}: {}", link_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to update payment link".to_string(),
+                }),
+            ));
+        }
+    };
+
+    tracing::info!("Updated payment link {}", link_id);
+
+    Ok(Json(PaymentLinkResponse::from(link)))
+}
+
+/// Open a payment link: mint a fresh invoice through the regular invoice creation
+pub async fn open_payment_link(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<String>,
+    RequestId(request_id): RequestId,
+    request: Option<Json<OpenPaymentLinkRequest>>,
+) -> Result<Json<InvoiceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let link = find_payment_link(&app_state, &link_id).await?;
+
+    if !link.is_available() {
+        return Err(payment_link_unavailable());
+    }
+
+    let chosen_amount = request
+        .amount
+        .map(|amount| {
+            convert_money_from_string(amount).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "invalid_amount_format".to_string(),
+                        message: "Amount format is invalid".to_string(),
+                    }),
+                )
+            })
+        })
+        .transpose()?;
+    let amount = link.amount.resolve(chosen_amount).ok_or_else(|| {
+        let message = match link.amount {
+            LinkAmount::Fixed { .. } => "This payment link has a fixed amount".to_string(),
+            LinkAmount::CustomerChosen { min_amount, max_amount } => format!(
+                "Amount must be between {} and {}",
+                format_money_amount(min_amount),
+                format_money_amount(max_amount)
+            ),
+        };
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_amount".to_string(),
+                message,
+            }),
+        )
+    })?;
+
+    // Count the use first so concurrent openings cannot go over the usage limit
+    let link = match app_state.payment_link_repository.claim_use(&link.id).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return Err(payment_link_unavailable()),
+        Err(e) => {
+            tracing::error!("Failed to claim a use of payment link {}: {}", link_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to open payment link".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let invoice_request = CreateInvoiceRequest {
+        amount: format_money_amount(amount),
+        settlement_asset: Some(link.settlement_asset),
+        merchant_order_id: format!("{}-{}", link.id, link.usage_count),
+        expires_in_secs: None,
+        due_date: None,
+        description: link.description.clone().or_else(|| Some(link.title.clone())),
+        line_items: None,
+        metadata: None,
+        buyer_email: request.buyer_email,
+        buyer_reference: request.buyer_reference,
+    };
+
+    let context = AuditContext::new(Actor::Customer { address: None }, request_id)
+        .with_reason(format!("Opened payment link {}", link.id));
+    let invoice = match new_invoice(&app_state, &link.wallet_address, invoice_request).await {
+        Ok(mut invoice) => {
+            invoice.payment_link_id = Some(link.id);
+            save_invoice(&app_state, &invoice, &context).await.map(|_| invoice)
+        }
+        Err(e) => Err(e),
+    };
+
+    let invoice = match invoice {
+        Ok(invoice) => invoice,
+        Err(e) => {
+            if let Err(release_error) = app_state.payment_link_repository.release_use(&link.id).await {
+                tracing::error!("Failed to release a use of payment link {}: {}", link.id, release_error);
+            }
+            return Err(e);
+        }
+    };
+
+    tracing::info!("Opened payment link {} as invoice {}", link.id, invoice.id);
+
+    Ok(Json(InvoiceResponse::from(invoice)))
+}
+
+async fn find_payment_link(
+    app_state: &AppState,
+    link_id: &str,
+) -> Result<PaymentLink, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(link_id)?;
+    match app_state.payment_link_repository.find_by_id(&object_id).await {
+        Ok(Some(link)) => Ok(link),
+        Ok(None) => Err(payment_link_not_found()),
+        Err(e) => {
+            tracing::error!("Database error when retrieving payment link {}: {}", link_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve payment link".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+fn payment_link_not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "payment_link_not_found".to_string(),
+            message: "Payment link not found".to_string(),
+        }),
+    )
+}
+
+fn payment_link_unavailable() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::CONFLICT,
+        Json(ErrorResponse {
+            error: "payment_link_unavailable".to_string(),
+            message: "Payment link is inactive or has reached its usage limit".to_string(),
+        }),
+    )
+}
+
+fn parse_link_amount(amount: LinkAmountDto) -> Result<LinkAmount, (StatusCode, Json<ErrorResponse>)> {
+    let parse = |amount: String| {
+        convert_money_from_string(amount).map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "invalid_amount_format".to_string(),
+                    message: "Amount format is invalid".to_string(),
+                }),
+            )
+        })
+    };
+
+    Ok(match amount {
+        LinkAmountDto::Fixed { amount } => LinkAmount::Fixed { amount: parse(amount)? },
+        LinkAmountDto::CustomerChosen { min_amount, max_amount } => LinkAmount::CustomerChosen {
+            min_amount: parse(min_amount)?,
+            max_amount: parse(max_amount)?,
+        },
+    })
+}
+
+fn validate_payment_link(link: &PaymentLink) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: &str| {
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        ))
+    };
+
+    if link.title.trim().is_empty() || link.title.len() > MAX_TITLE_LEN {
+        return invalid("invalid_title", "Title must be between 1 and 200 characters");
+    }
+    match link.amount {
+        LinkAmount::Fixed { amount: 0 } => {
+            return invalid("invalid_amount", "Amount must be a positive number");
+        }
+        LinkAmount::CustomerChosen { min_amount, max_amount }
+            if min_amount == 0 || min_amount > max_amount =>
+        {
+            return invalid("invalid_amount", "Amount bounds must satisfy 0 < min <= max");
+        }
+        _ => {}
+    }
+    if link.usage_limit == Some(0) {
+        return invalid("invalid_usage_limit", "Usage limit must be at least 1");
+    }
+
+    Ok(())
+}
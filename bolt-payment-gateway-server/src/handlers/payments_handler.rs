@@ -1,20 +1,28 @@
 // src/handlers/payments.rs
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
 };
+use chrono::Utc;
 
+use crate::database::{PaymentFilter, PaymentScope, StatusUpdateError};
+use crate::handlers::invoices_handler::{expire_if_due, find_invoice};
+use crate::services::bolt_protocol_service::{BoltTransactionResponse, BroadcastError};
 use crate::{models::{
-    convert_string_to_object_id, ErrorResponse, InvoiceStatus, Payment, PaymentResponse, PaymentStatus, SubmitPaymentRequest
-}, shared::calculate_satoshis_for_usd_with_spread, AppState};
+    convert_string_to_object_id, format_money_amount, payout_error_response, price_error_response, status_update_error_response, Actor, AuditContext, ErrorResponse, Invoice, InvoiceQuoteResponse, InvoiceStatus, ListPaymentsQuery, ListPaymentsResponse, Payment, PaymentPriceResponse, PaymentResponse, PaymentStatus, PriceBasis, PriceTickResponse, BTC_USD_PAIR, StateMachine, SubmitPaymentRequest
+}, shared::{calculate_satoshis_for_usd_with_spread, RequestId}, AppState};
+
+/// Tries to record the outcome of a broadcast before it is left for reconciliation
+const CONFIRM_ATTEMPTS: usize = 3;
 
 /// Submit a payment transaction for an invoice
 pub async fn submit_payment(
     State(app_state): State<AppState>,
     Path(invoice_id): Path<String>,
+    RequestId(request_id): RequestId,
     Json(request): Json<SubmitPaymentRequest>,
-) -> Result<Json<PaymentResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(StatusCode, Json<PaymentResponse>), (StatusCode, Json<ErrorResponse>)> {
     // Validate amount
     if request.amount.parse::<f64>().is_err() || request.amount.parse::<f64>().unwrap() <= 0.0 {
         return Err((
@@ -61,7 +69,9 @@ pub async fn submit_payment(
         }
     };
 
-    if invoice.status == crate::models::InvoiceStatus::Paid || invoice.status == crate::models::InvoiceStatus::Settled {
+    let invoice = expire_if_due(&app_state, invoice, request_id.clone()).await?;
+
+    if invoice.status == InvoiceStatus::Paid || invoice.status == InvoiceStatus::Settled {
         return Err((
             StatusCode::CONFLICT,
             Json(ErrorResponse {
@@ -71,7 +81,7 @@ pub async fn submit_payment(
         ));
     }
 
-    if invoice.status == crate::models::InvoiceStatus::Expired {
+    if invoice.status == InvoiceStatus::Expired {
         return Err((
             StatusCode::CONFLICT,
             Json(ErrorResponse {
@@ -81,6 +91,52 @@ pub async fn submit_payment(
         ));
     }
 
+    if invoice.status == InvoiceStatus::Pending {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "payment_already_exists".to_string(),
+                message: "Payment already exists or being processed".to_string(),
+            }),
+        ));
+    }
+
+    if !invoice.status.can_transition_to(InvoiceStatus::Pending) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "invoice_not_payable".to_string(),
+                message: format!("Invoice in status {:?} cannot be paid", invoice.status),
+            }),
+        ));
+    }
+
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&invoice.wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", invoice.wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    // Amounts are checked against satoshis, a token priced otherwise would be under- or overpaid
+    if !merchant.accepts_token(request.asset) || !request.asset.is_priced() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ErrorResponse {
+                error: "unsupported_payment_token".to_string(),
+                message: format!("Merchant does not accept payments in {:?}", request.asset),
+            }),
+        ));
+    }
+
     // Simulate underpayment detection
     let payment_amount = request.amount.parse::<f64>().unwrap();
     if payment_amount < 0.001 { // Minimum payment threshold for demo
@@ -104,24 +160,27 @@ pub async fn submit_payment(
     })?;
 
 
+    // No new payments while the price feed is suspect, locked quotes included
+    app_state.quote_service.ensure_not_halted().await.map_err(|e| {
+        tracing::error!("Refusing payment for invoice {}: {}", invoice.id, e);
+        price_error_response(e.as_ref())
+    })?;
+
     // Get quote
-    // Get Bitcoin price from QuoteService
-    let btc_price_usd_cents = app_state
-        .quote_service
-        .get_bitcoin_price()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to get Bitcoin price: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "price_fetch_error".to_string(),
-                    message: "Failed to fetch current Bitcoin price".to_string(),
-                }),
-            )
-        })?;
+    // Honor the price locked for the invoice while its window is open, otherwise use the current price
+    let btc_price_usd_cents = match invoice.live_quote(Utc::now()) {
+        Some(quote) => quote.btc_price,
+        None => app_state
+            .quote_service
+            .get_bitcoin_price()
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get Bitcoin price: {}", e);
+                price_error_response(e.as_ref())
+            })?,
+    };
 
-    let spread_percentage = 50 as u128; // 0.50% minimal spread accepted
+    let spread_percentage = merchant.fee_schedule.payment_spread_bps as u128; // minimal spread accepted
     let satoshis_with_spread = calculate_satoshis_for_usd_with_spread(invoice.amount, btc_price_usd_cents, spread_percentage);
 
     tracing::info!("Calculated satoshis with spread: {}", satoshis_with_spread);
@@ -138,10 +197,52 @@ pub async fn submit_payment(
     }
 
 
-    let payment: Payment = Payment::new(invoice.id, request.asset, amount);
+    // Invoices created before recipients were stored are resolved from the merchant's current settings
+    let recipient_address = match &invoice.recipient_address {
+        Some(recipient_address) => recipient_address.clone(),
+        None => app_state
+            .payout_service
+            .resolve_recipient(&merchant)
+            .map_err(|e| {
+                tracing::warn!("Cannot resolve recipient for invoice {}: {}", invoice.id, e);
+                payout_error_response(e)
+            })?,
+    };
+
+    let mut payment: Payment = Payment::new(invoice.id, request.asset, amount);
+    payment.recipient_address = Some(recipient_address.clone());
+    payment.wallet_address = Some(invoice.wallet_address.clone());
+    payment.btc_price = Some(btc_price_usd_cents);
+    let context = AuditContext::new(Actor::Customer { address: None }, request_id.clone());
+
+    // Claim the invoice before anything is broadcast, so a concurrent cancellation,
+    // amendment or second payment cannot go through as well
+    let claimed = match app_state
+        .invoice_repository
+        .claim_for_payment(&invoice, &context.clone().with_reason(format!("Payment {} accepted", payment.id)))
+        .await
+    {
+        Ok(claimed) => claimed,
+        Err(e) => {
+            tracing::warn!("Failed to claim invoice {} for payment: {}", invoice.id, e);
+            return Err(match e {
+                StatusUpdateError::Database(_) | StatusUpdateError::VersionConflict { .. } => {
+                    status_update_error_response(&e)
+                }
+                _ => (
+                    StatusCode::CONFLICT,
+                    Json(ErrorResponse {
+                        error: "invoice_changed".to_string(),
+                        message: "Invoice was paid, cancelled or amended concurrently".to_string(),
+                    }),
+                ),
+            });
+        }
+    };
 
     // Save payment to database with constraint checking
-    if let Err(error_msg) = app_state.payment_repository.create(&payment).await {
+    if let Err(error_msg) = app_state.payment_repository.create(&payment, &context).await {
+        release_invoice(&app_state, &claimed, &context, "Payment could not be saved").await;
         if error_msg.to_string().contains("duplicate key") || error_msg.to_string().contains("E11000") {
             return Err((
                 StatusCode::CONFLICT,
@@ -167,19 +268,39 @@ pub async fn submit_payment(
     let response = app_state.bolt_protocol_service.broadcast_transaction(
         request.serialized_transaction, 
         amount, 
-        "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF".to_string()
+        recipient_address
     ).await;
     
     let bolt_response = match response {
         Ok(res) => res,
+        Err(e @ BroadcastError::Unknown { .. }) => {
+            // The transaction may be on its way, releasing the invoice would let it be paid twice.
+            // The payment stays accepted and the invoice pending until the payment is reconciled.
+            tracing::error!(
+                "Outcome of the broadcast of payment {} for invoice {} is unknown, leaving it for reconciliation: {}",
+                payment.id,
+                invoice.id,
+                e
+            );
+            return Ok((StatusCode::ACCEPTED, Json(PaymentResponse::from(payment))));
+        }
         Err(e) => {
-            tracing::error!("Failed to broadcast transaction: {:?}", e);
+            tracing::error!("Failed to broadcast transaction: {}", e);
 
             // Update payment status to Rejected
-            if let Err(update_err) = app_state.payment_repository.update_status(&payment.id, PaymentStatus::Rejected).await {
+            if let Err(update_err) = app_state
+                .payment_repository
+                .update_status(
+                    &payment,
+                    PaymentStatus::Rejected,
+                    &context.clone().with_reason("Transaction broadcast failed"),
+                )
+                .await
+            {
                 tracing::error!("Failed to update payment status to Rejected: {}", update_err);
             }
-            
+            release_invoice(&app_state, &claimed, &context, "Transaction broadcast failed").await;
+
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
@@ -190,34 +311,37 @@ pub async fn submit_payment(
         }
     };
 
-    let payment_confirmed = match app_state.payment_repository.confirm(&payment.id, &bolt_response.txid).await {
-        Ok(confirmed_payment) => confirmed_payment.ok_or_else(|| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "payment_confirmation_error".to_string(),
-                    message: "Failed to confirm payment".to_string(),
-                }),
-            )
-        })?,
-        Err(e) => {
-            tracing::error!("Failed to confirm payment: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "payment_confirmation_error".to_string(),
-                    message: "Failed to confirm payment".to_string(),
-                }),
-            ));
+    let context = AuditContext::new(
+        Actor::Customer { address: Some(bolt_response.sender.clone()) },
+        request_id,
+    )
+    .with_reason(format!("Transaction {} broadcast", bolt_response.txid));
+
+    // The transaction is on its way, from here on nothing may tell the client to resubmit it
+    let Some(payment_confirmed) = confirm_broadcast_payment(&app_state, payment.clone(), &bolt_response, &context).await
+    else {
+        tracing::error!(
+            "Payment {} for invoice {} was broadcast as {} from {} but could not be confirmed, leaving it for reconciliation",
+            payment.id,
+            invoice.id,
+            bolt_response.txid,
+            bolt_response.sender
+        );
+        // The reconciler looks the payment up by its transaction
+        if let Err(e) = app_state
+            .payment_repository
+            .record_tx_id(&payment.id, &bolt_response.txid, &bolt_response.sender)
+            .await
+        {
+            tracing::error!("Failed to record transaction {} of payment {}: {}", bolt_response.txid, payment.id, e);
         }
+        let mut payment = payment;
+        payment.tx_id = Some(bolt_response.txid);
+        payment.sender_address = Some(bolt_response.sender);
+        return Ok((StatusCode::ACCEPTED, Json(PaymentResponse::from(payment))));
     };
 
-    match app_state.invoice_repository.update_status(&object_id, InvoiceStatus::Paid).await {
-        Ok(_) => (),
-        Err(e) => {
-            tracing::error!("Failed to update invoice status to Paid: {}", e);
-        }
-    }
+    mark_invoice_paid(&app_state, claimed, &context).await;
 
     tracing::info!(
         "Payment {} submitted for invoice {} with amount {} and tx_id {} {:?}",
@@ -228,5 +352,256 @@ pub async fn submit_payment(
         payment.asset
     );
 
-    Ok(Json(PaymentResponse::from(payment_confirmed)))
+    Ok((StatusCode::OK, Json(PaymentResponse::from(payment_confirmed))))
+}
+
+/// Confirm a broadcast payment, reading it again and retrying while concurrent updates or the database get in the way.
+/// Returns `None` when the payment is still unconfirmed after `CONFIRM_ATTEMPTS` tries or was moved out of `Accepted`.
+async fn confirm_broadcast_payment(
+    app_state: &AppState,
+    mut payment: Payment,
+    bolt_response: &BoltTransactionResponse,
+    context: &AuditContext,
+) -> Option<Payment> {
+    for attempt in 1..=CONFIRM_ATTEMPTS {
+        match app_state
+            .payment_repository
+            .confirm(&payment, &bolt_response.txid, &bolt_response.sender, context)
+            .await
+        {
+            Ok(confirmed) => return Some(confirmed),
+            Err(e @ (StatusUpdateError::VersionConflict { .. } | StatusUpdateError::Database(_))) => {
+                tracing::warn!("Attempt {} to confirm payment {} failed: {}", attempt, payment.id, e);
+                if let Ok(Some(current)) = app_state.payment_repository.find_by_id(&payment.id).await {
+                    payment = current;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to confirm payment {}: {}", payment.id, e);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/// Move the invoice of a confirmed payment from `Pending` to `Paid`, retrying like `confirm_broadcast_payment`
+async fn mark_invoice_paid(app_state: &AppState, mut invoice: Invoice, context: &AuditContext) {
+    for attempt in 1..=CONFIRM_ATTEMPTS {
+        match app_state
+            .invoice_repository
+            .update_status(&invoice, InvoiceStatus::Paid, context)
+            .await
+        {
+            Ok(_) => return,
+            Err(e @ (StatusUpdateError::VersionConflict { .. } | StatusUpdateError::Database(_))) => {
+                tracing::warn!("Attempt {} to mark invoice {} paid failed: {}", attempt, invoice.id, e);
+                if let Ok(Some(current)) = app_state.invoice_repository.find_by_id(&invoice.id).await {
+                    invoice = current;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to update invoice {} status to Paid: {}", invoice.id, e);
+                return;
+            }
+        }
+    }
+    tracing::error!(
+        "Invoice {} stays pending with a confirmed payment, settle it with `bolt-admin payment confirm`",
+        invoice.id
+    );
+}
+
+/// Give a claimed invoice back to `Created` after the payment attempt failed
+async fn release_invoice(app_state: &AppState, invoice: &Invoice, context: &AuditContext, reason: &str) {
+    if let Err(e) = app_state
+        .invoice_repository
+        .update_status(invoice, InvoiceStatus::Created, &context.clone().with_reason(reason))
+        .await
+    {
+        tracing::error!("Failed to release invoice {} after failed payment: {}", invoice.id, e);
+    }
+}
+
+/// Get a payment by ID
+pub async fn get_payment(
+    State(app_state): State<AppState>,
+    Path(payment_id): Path<String>,
+) -> Result<Json<PaymentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&payment_id)?;
+
+    match app_state.payment_repository.find_by_id(&object_id).await {
+        Ok(Some(payment)) => Ok(Json(PaymentResponse::from(payment))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "payment_not_found".to_string(),
+                message: "Payment not found".to_string(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Database error when retrieving payment {}: {}", payment_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve payment".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+/// The BTC price a payment was validated against, with the quote or recorded tick it came from
+pub async fn get_payment_price(
+    State(app_state): State<AppState>,
+    Path(payment_id): Path<String>,
+) -> Result<Json<PaymentPriceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&payment_id)?;
+    let database_error = |e: anyhow::Error| {
+        tracing::error!("Database error when looking up the price of payment {}: {}", payment_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to retrieve payment price".to_string(),
+            }),
+        )
+    };
+
+    let payment = app_state
+        .payment_repository
+        .find_by_id(&object_id)
+        .await
+        .map_err(database_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "payment_not_found".to_string(),
+                message: "Payment not found".to_string(),
+            }),
+        ))?;
+    let Some(btc_price) = payment.btc_price else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "payment_price_unknown".to_string(),
+                message: "Payment was received before prices were recorded".to_string(),
+            }),
+        ));
+    };
+
+    // A quote locked at this price and open when the payment arrived is the price it was validated against
+    let invoice = find_invoice(&app_state, &payment.invoice_id).await?;
+    let quote = invoice.quote.filter(|quote| {
+        quote.btc_price == btc_price
+            && quote.locked_at <= payment.received_at
+            && payment.received_at < quote.expires_at
+    });
+    let (basis, priced_at) = match &quote {
+        Some(quote) => (PriceBasis::LockedQuote, quote.locked_at),
+        None => (PriceBasis::CurrentPrice, payment.received_at),
+    };
+
+    let tick = app_state
+        .price_history_repository
+        .find_tick(BTC_USD_PAIR, btc_price, priced_at)
+        .await
+        .map_err(database_error)?;
+
+    Ok(Json(PaymentPriceResponse {
+        payment_id: payment.id.to_string(),
+        invoice_id: payment.invoice_id.to_string(),
+        pair: BTC_USD_PAIR.to_string(),
+        btc_price: format_money_amount(btc_price),
+        basis,
+        received_at: payment.received_at,
+        quote: quote.map(InvoiceQuoteResponse::from),
+        tick: tick.map(PriceTickResponse::from),
+    }))
+}
+
+/// Look up payments by transaction hash
+pub async fn list_payments(
+    State(app_state): State<AppState>,
+    Query(query): Query<ListPaymentsQuery>,
+) -> Result<Json<ListPaymentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Listing by status spans every merchant, it is only offered per merchant
+    if query.tx_id.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "missing_filter".to_string(),
+                message: "tx_id must be set, list payments by status per merchant instead".to_string(),
+            }),
+        ));
+    }
+
+    paginate_payments(&app_state, PaymentScope::All, &query).await.map(Json)
+}
+
+/// List every payment attempt for an invoice, including rejected ones
+pub async fn list_invoice_payments(
+    State(app_state): State<AppState>,
+    Path(invoice_id): Path<String>,
+    Query(query): Query<ListPaymentsQuery>,
+) -> Result<Json<ListPaymentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(&invoice_id)?;
+    find_invoice(&app_state, &object_id).await?;
+
+    paginate_payments(&app_state, PaymentScope::Invoice(&object_id), &query).await.map(Json)
+}
+
+/// List the payments made to all invoices of a merchant
+pub async fn list_merchant_payments(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Query(query): Query<ListPaymentsQuery>,
+) -> Result<Json<ListPaymentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    paginate_payments(&app_state, PaymentScope::Merchant(&wallet_address), &query).await.map(Json)
+}
+
+/// Apply the status and date filters, newest first, and read the requested page from the database
+async fn paginate_payments(
+    app_state: &AppState,
+    scope: PaymentScope<'_>,
+    query: &ListPaymentsQuery,
+) -> Result<ListPaymentsResponse, (StatusCode, Json<ErrorResponse>)> {
+    if query.limit > 100 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_limit".to_string(),
+                message: "Limit cannot exceed 100".to_string(),
+            }),
+        ));
+    }
+
+    let filter = PaymentFilter {
+        tx_id: query.tx_id.clone(),
+        status: query.status,
+        received_from: query.from_date,
+        received_to: query.to_date,
+    };
+    let (payments, total) = app_state
+        .payment_repository
+        .find_page(scope, &filter, query.offset as u64, query.limit as i64)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving payments ({:?}): {}", scope, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve payments".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(ListPaymentsResponse {
+        items: payments.into_iter().map(PaymentResponse::from).collect(),
+        total: total as usize,
+        limit: query.limit,
+        offset: query.offset,
+    })
 }
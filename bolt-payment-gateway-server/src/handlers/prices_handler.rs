@@ -0,0 +1,79 @@
+// src/handlers/prices_handler.rs
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+
+use crate::models::{
+    ErrorResponse, PriceCandleResponse, PriceHistoryQuery, PriceHistoryResponse, BTC_USD_PAIR,
+};
+use crate::AppState;
+
+/// Range used when the request does not set `from`
+const DEFAULT_PRICE_HISTORY_RANGE_HOURS: i64 = 24;
+
+/// Most candles a single request can cover
+const MAX_PRICE_CANDLES: i32 = 1000;
+
+/// OHLC candles of the prices fetched in a time range
+pub async fn get_price_history(
+    State(app_state): State<AppState>,
+    Query(query): Query<PriceHistoryQuery>,
+) -> Result<Json<PriceHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        )
+    };
+
+    let pair = query.pair.as_deref().unwrap_or(BTC_USD_PAIR).to_uppercase();
+    if pair != BTC_USD_PAIR {
+        return Err(invalid(
+            "unsupported_pair",
+            &format!("Pair '{}' is not supported at this time. Only 'BTCUSD' is supported.", pair),
+        ));
+    }
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query
+        .from
+        .unwrap_or_else(|| to - chrono::Duration::hours(DEFAULT_PRICE_HISTORY_RANGE_HOURS));
+    if from >= to {
+        return Err(invalid("invalid_range", "from must be before to"));
+    }
+    if to - from > query.interval.duration() * MAX_PRICE_CANDLES {
+        return Err(invalid(
+            "invalid_range",
+            &format!("The range cannot exceed {} intervals, use a longer interval", MAX_PRICE_CANDLES),
+        ));
+    }
+
+    let candles = app_state
+        .price_history_repository
+        .candles(&pair, from, to, query.interval)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to aggregate price history: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve price history".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(PriceHistoryResponse {
+        pair,
+        from,
+        to,
+        interval: query.interval,
+        candles: candles.into_iter().map(PriceCandleResponse::from).collect(),
+    }))
+}
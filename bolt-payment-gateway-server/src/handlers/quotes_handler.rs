@@ -8,7 +8,8 @@ use chrono::Utc;
 
 use crate::{shared::calculate_satoshis_for_usd_with_spread, AppState};
 use crate::models::{
-    ErrorResponse, QuoteQuery, QuoteResponse, convert_money_from_string, format_money_amount,
+    ErrorResponse, QuoteQuery, QuoteResponse, convert_money_from_string, format_money_amount, price_error_response,
+    DEFAULT_QUOTE_SPREAD_BPS,
 };
 
 /// Get a conversion quote
@@ -53,7 +54,7 @@ pub async fn get_quote(
         )
     })?;
 
-    if usd_amount <= 0 {
+    if usd_amount == 0 {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -70,16 +71,30 @@ pub async fn get_quote(
         .await
         .map_err(|e| {
             tracing::error!("Failed to get Bitcoin price: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "price_fetch_error".to_string(),
-                    message: "Failed to fetch current Bitcoin price".to_string(),
-                }),
-            )
+            price_error_response(e.as_ref())
         })?;
 
-    let spread_percentage = 100 as u128; // 1.00% spread
+    // Spread from the merchant's fee schedule, or the gateway default (1.00%)
+    let spread_bps = match &query.merchant {
+        Some(wallet_address) => app_state
+            .merchant_repository
+            .find_or_default(wallet_address)
+            .await
+            .map_err(|e| {
+                tracing::error!("Database error when retrieving merchant {}: {}", wallet_address, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "database_error".to_string(),
+                        message: "Failed to retrieve merchant".to_string(),
+                    }),
+                )
+            })?
+            .fee_schedule
+            .quote_spread_bps,
+        None => DEFAULT_QUOTE_SPREAD_BPS,
+    };
+    let spread_percentage = spread_bps as u128;
     let satoshis_with_spread = calculate_satoshis_for_usd_with_spread(usd_amount, btc_price_usd_cents, spread_percentage);
 
     Ok(Json(QuoteResponse {
@@ -0,0 +1,426 @@
+// src/handlers/subscriptions_handler.rs
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+
+use crate::handlers::invoices_handler::is_plausible_email;
+use crate::models::{
+    convert_money_from_string, convert_string_to_object_id, CancelSubscriptionRequest,
+    CreateSubscriptionPlanRequest, CreateSubscriptionRequest, ErrorResponse,
+    ListSubscriptionPlansResponse, ListSubscriptionsResponse, Subscription, SubscriptionPlan,
+    SubscriptionPlanResponse, SubscriptionResponse, SubscriptionStatus,
+    UpdateSubscriptionPlanRequest, DEFAULT_MAX_UNPAID_CYCLES,
+};
+use crate::AppState;
+
+/// Longest plan name
+const MAX_NAME_LEN: usize = 200;
+
+/// Most intervals in a billing period
+const MAX_INTERVAL_COUNT: u32 = 365;
+
+/// How far in the past a subscription's first period may start, to absorb clock skew
+const START_AT_TOLERANCE_SECS: i64 = 60;
+
+/// Create a subscription plan for a merchant
+pub async fn create_plan(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+    Json(request): Json<CreateSubscriptionPlanRequest>,
+) -> Result<Json<SubscriptionPlanResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let merchant = app_state
+        .merchant_repository
+        .find_or_default(&wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when retrieving merchant {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve merchant".to_string(),
+                }),
+            )
+        })?;
+
+    let now = Utc::now();
+    let plan = SubscriptionPlan {
+        id: ObjectId::new(),
+        wallet_address: wallet_address.clone(),
+        name: request.name,
+        description: request.description,
+        amount: parse_amount(request.amount)?,
+        settlement_asset: request
+            .settlement_asset
+            .unwrap_or(merchant.default_settlement_asset),
+        interval: request.interval,
+        interval_count: request.interval_count.unwrap_or(1),
+        payment_window_secs: request.payment_window_secs,
+        max_unpaid_cycles: request.max_unpaid_cycles.unwrap_or(DEFAULT_MAX_UNPAID_CYCLES),
+        active: request.active.unwrap_or(true),
+        created_at: now,
+        updated_at: now,
+    };
+
+    validate_plan(&plan)?;
+
+    if let Err(e) = app_state.subscription_plan_repository.create(&plan).await {
+        tracing::error!("Failed to create subscription plan in database: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to create subscription plan".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Created subscription plan {} for merchant {}", plan.id, wallet_address);
+
+    Ok(Json(SubscriptionPlanResponse::from(plan)))
+}
+
+/// List a merchant's subscription plans
+pub async fn list_plans(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+) -> Result<Json<ListSubscriptionPlansResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let plans = app_state
+        .subscription_plan_repository
+        .find_by_merchant(&wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when listing subscription plans of {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve subscription plans".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(ListSubscriptionPlansResponse {
+        total: plans.len(),
+        items: plans.into_iter().map(SubscriptionPlanResponse::from).collect(),
+    }))
+}
+
+/// Get a subscription plan by ID
+pub async fn get_plan(
+    State(app_state): State<AppState>,
+    Path(plan_id): Path<String>,
+) -> Result<Json<SubscriptionPlanResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let plan = find_plan(&app_state, &plan_id).await?;
+    Ok(Json(SubscriptionPlanResponse::from(plan)))
+}
+
+/// Update the fields present in the request, they apply from the next billed period
+pub async fn update_plan(
+    State(app_state): State<AppState>,
+    Path(plan_id): Path<String>,
+    Json(request): Json<UpdateSubscriptionPlanRequest>,
+) -> Result<Json<SubscriptionPlanResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut plan = find_plan(&app_state, &plan_id).await?;
+
+    if let Some(name) = request.name {
+        plan.name = name;
+    }
+    if let Some(description) = request.description {
+        plan.description = Some(description);
+    }
+    if let Some(amount) = request.amount {
+        plan.amount = parse_amount(amount)?;
+    }
+    if let Some(asset) = request.settlement_asset {
+        plan.settlement_asset = asset;
+    }
+    if let Some(interval) = request.interval {
+        plan.interval = interval;
+    }
+    if let Some(interval_count) = request.interval_count {
+        plan.interval_count = interval_count;
+    }
+    if let Some(payment_window_secs) = request.payment_window_secs {
+        plan.payment_window_secs = Some(payment_window_secs);
+    }
+    if let Some(max_unpaid_cycles) = request.max_unpaid_cycles {
+        plan.max_unpaid_cycles = max_unpaid_cycles;
+    }
+    if let Some(active) = request.active {
+        plan.active = active;
+    }
+    plan.updated_at = Utc::now();
+
+    validate_plan(&plan)?;
+
+    match app_state.subscription_plan_repository.update(&plan).await {
+        Ok(true) => {}
+        Ok(false) => return Err(plan_not_found()),
+        Err(e) => {
+            tracing::error!("Failed to update subscription plan {}: {}", plan_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to update subscription plan".to_string(),
+                }),
+            ));
+        }
+    }
+
+    tracing::info!("Updated subscription plan {}", plan_id);
+
+    Ok(Json(SubscriptionPlanResponse::from(plan)))
+}
+
+/// Subscribe a customer to a plan, the scheduler bills the first period at `start_at`
+pub async fn create_subscription(
+    State(app_state): State<AppState>,
+    Path(plan_id): Path<String>,
+    Json(request): Json<CreateSubscriptionRequest>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let plan = find_plan(&app_state, &plan_id).await?;
+    if !plan.active {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "plan_inactive".to_string(),
+                message: "Subscription plan is not active".to_string(),
+            }),
+        ));
+    }
+
+    if let Some(email) = &request.customer_email
+        && !is_plausible_email(email)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_customer_email".to_string(),
+                message: "Customer email is not a valid email address".to_string(),
+            }),
+        ));
+    }
+
+    let now = Utc::now();
+    let next_billing_at = request.start_at.unwrap_or(now);
+    if next_billing_at < now - Duration::seconds(START_AT_TOLERANCE_SECS) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_start_at".to_string(),
+                message: "start_at cannot be in the past".to_string(),
+            }),
+        ));
+    }
+
+    let subscription = Subscription {
+        id: ObjectId::new(),
+        plan_id: plan.id,
+        wallet_address: plan.wallet_address,
+        customer_email: request.customer_email,
+        customer_reference: request.customer_reference,
+        status: SubscriptionStatus::Active,
+        next_billing_at,
+        unpaid_cycles: 0,
+        cycles: Vec::new(),
+        cancel_reason: None,
+        created_at: now,
+        updated_at: now,
+    };
+
+    if let Err(e) = app_state.subscription_repository.create(&subscription).await {
+        tracing::error!("Failed to create subscription in database: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to create subscription".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Created subscription {} to plan {}", subscription.id, plan.id);
+
+    Ok(Json(SubscriptionResponse::from(subscription)))
+}
+
+/// List a merchant's subscriptions
+pub async fn list_subscriptions(
+    State(app_state): State<AppState>,
+    Path(wallet_address): Path<String>,
+) -> Result<Json<ListSubscriptionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let subscriptions = app_state
+        .subscription_repository
+        .find_by_merchant(&wallet_address)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error when listing subscriptions of {}: {}", wallet_address, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve subscriptions".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(ListSubscriptionsResponse {
+        total: subscriptions.len(),
+        items: subscriptions.into_iter().map(SubscriptionResponse::from).collect(),
+    }))
+}
+
+/// Get a subscription with its billing cycles
+pub async fn get_subscription(
+    State(app_state): State<AppState>,
+    Path(subscription_id): Path<String>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let subscription = find_subscription(&app_state, &subscription_id).await?;
+    Ok(Json(SubscriptionResponse::from(subscription)))
+}
+
+/// Stop billing a subscription, invoices already created stay payable
+pub async fn cancel_subscription(
+    State(app_state): State<AppState>,
+    Path(subscription_id): Path<String>,
+    request: Option<Json<CancelSubscriptionRequest>>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut subscription = find_subscription(&app_state, &subscription_id).await?;
+    let reason = request
+        .and_then(|Json(request)| request.reason)
+        .unwrap_or_else(|| "Cancelled by the merchant".to_string());
+
+    match app_state.subscription_repository.cancel(&subscription.id, &reason).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: "subscription_cancelled".to_string(),
+                    message: "Subscription is already cancelled".to_string(),
+                }),
+            ));
+        }
+        Err(e) => {
+            tracing::error!("Failed to cancel subscription {}: {}", subscription_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to cancel subscription".to_string(),
+                }),
+            ));
+        }
+    }
+
+    subscription.cancel(reason);
+    tracing::info!("Cancelled subscription {}", subscription_id);
+
+    Ok(Json(SubscriptionResponse::from(subscription)))
+}
+
+async fn find_plan(
+    app_state: &AppState,
+    plan_id: &str,
+) -> Result<SubscriptionPlan, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(plan_id)?;
+    match app_state.subscription_plan_repository.find_by_id(&object_id).await {
+        Ok(Some(plan)) => Ok(plan),
+        Ok(None) => Err(plan_not_found()),
+        Err(e) => {
+            tracing::error!("Database error when retrieving subscription plan {}: {}", plan_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve subscription plan".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+async fn find_subscription(
+    app_state: &AppState,
+    subscription_id: &str,
+) -> Result<Subscription, (StatusCode, Json<ErrorResponse>)> {
+    let object_id = convert_string_to_object_id(subscription_id)?;
+    match app_state.subscription_repository.find_by_id(&object_id).await {
+        Ok(Some(subscription)) => Ok(subscription),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "subscription_not_found".to_string(),
+                message: "Subscription not found".to_string(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Database error when retrieving subscription {}: {}", subscription_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to retrieve subscription".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+fn plan_not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "plan_not_found".to_string(),
+            message: "Subscription plan not found".to_string(),
+        }),
+    )
+}
+
+fn parse_amount(amount: String) -> Result<u128, (StatusCode, Json<ErrorResponse>)> {
+    convert_money_from_string(amount).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_amount_format".to_string(),
+                message: "Amount format is invalid".to_string(),
+            }),
+        )
+    })
+}
+
+fn validate_plan(plan: &SubscriptionPlan) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let invalid = |error: &str, message: &str| {
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        ))
+    };
+
+    if plan.name.trim().is_empty() || plan.name.len() > MAX_NAME_LEN {
+        return invalid("invalid_name", "Name must be between 1 and 200 characters");
+    }
+    if plan.amount == 0 {
+        return invalid("invalid_amount", "Amount must be a positive number");
+    }
+    if plan.interval_count == 0 || plan.interval_count > MAX_INTERVAL_COUNT {
+        return invalid("invalid_interval_count", "Interval count must be between 1 and 365");
+    }
+    if plan.payment_window_secs.is_some_and(|secs| secs <= 0) {
+        return invalid("invalid_payment_window", "Payment window must be a positive number of seconds");
+    }
+    if plan.max_unpaid_cycles == 0 {
+        return invalid("invalid_max_unpaid_cycles", "Max unpaid cycles must be at least 1");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+// src/lib.rs
+pub mod api;
+pub mod database;
+pub mod handlers;
+pub mod models;
+pub mod services;
+pub mod shared;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+use std::env;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use mongodb::Database;
+
+use database::{AuditRepository, InvoiceRepository, MerchantRepository, PaymentLinkRepository, PaymentRepository, PriceGuardRepository, PriceHistoryRepository, PriceSnapshotRepository, SubscriptionPlanRepository, SubscriptionRepository};
+use services::price_guard::{PriceGuard, PriceGuardConfig};
+use services::quote_service::{QuoteService, DEFAULT_PRICE_API_URL};
+use services::bolt_protocol_service::{BoltProtocolService, DEFAULT_BOLT_PROTOCOL_URL};
+use services::payout_service::{PayoutService, DEFAULT_TREASURY_ADDRESS};
+use services::event_bus::EventBus;
+use services::notification_service::NotificationService;
+use services::token_registry::{TokenRegistry, DEFAULT_BOLT_PROTOCOL_CONTRACT, DEFAULT_BOLT_TRANSFER_FEE, DEFAULT_SBTC_CONTRACT};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub invoice_repository: InvoiceRepository,
+    pub payment_repository: PaymentRepository,
+    pub audit_repository: AuditRepository,
+    pub merchant_repository: MerchantRepository,
+    pub payment_link_repository: PaymentLinkRepository,
+    pub subscription_plan_repository: SubscriptionPlanRepository,
+    pub subscription_repository: SubscriptionRepository,
+    pub price_history_repository: PriceHistoryRepository,
+    pub price_guard_repository: PriceGuardRepository,
+    pub quote_service: QuoteService,
+    pub bolt_protocol_service: BoltProtocolService,
+    pub payout_service: PayoutService,
+    pub event_bus: EventBus,
+    pub notification_service: NotificationService,
+    pub token_registry: TokenRegistry,
+}
+
+impl AppState {
+    /// Repositories on `database` and services configured from the environment (see the README)
+    pub fn from_env(database: &Database, event_bus: EventBus) -> Self {
+        let price_history_repository = PriceHistoryRepository::new(database);
+        let price_guard_repository = PriceGuardRepository::new(database);
+
+        // Prices failing the sanity checks halt new payments on every replica
+        let mut price_guard = PriceGuard::new(PriceGuardConfig::from_env())
+            .with_repository(price_guard_repository.clone());
+        if let Ok(url) = env::var("SECONDARY_PRICE_API_URL") {
+            price_guard = price_guard.with_secondary(QuoteService::with_cache_expiry(url, Duration::ZERO));
+        }
+
+        let mut quote_service = QuoteService::with_base_url(
+            env::var("PRICE_API_URL").unwrap_or_else(|_| DEFAULT_PRICE_API_URL.to_string()),
+        )
+        .with_history(price_history_repository.clone())
+        .with_guard(price_guard);
+        // Replicas quote from one price shared through the database
+        if env::var("SHARED_PRICE_SNAPSHOT").is_ok_and(|value| value == "true" || value == "1") {
+            quote_service = quote_service.with_snapshots(PriceSnapshotRepository::new(database));
+        }
+        let bolt_protocol_service = BoltProtocolService::with_base_url(
+            env::var("BOLT_PROTOCOL_URL").unwrap_or_else(|_| DEFAULT_BOLT_PROTOCOL_URL.to_string()),
+        );
+
+        // Address custodial merchants are paid through
+        let treasury_address = env::var("GATEWAY_TREASURY_ADDRESS")
+            .unwrap_or_else(|_| DEFAULT_TREASURY_ADDRESS.to_string());
+        let payout_service = PayoutService::new(treasury_address);
+
+        // Customer emails go through an HTTP relay, they are only logged without one
+        let notification_service = NotificationService::new(env::var("EMAIL_RELAY_URL").ok());
+
+        // Contracts wallets are told to transfer through at checkout
+        let token_registry = TokenRegistry::new(
+            env::var("SBTC_TOKEN_CONTRACT").unwrap_or_else(|_| DEFAULT_SBTC_CONTRACT.to_string()),
+            env::var("BOLT_PROTOCOL_CONTRACT")
+                .unwrap_or_else(|_| DEFAULT_BOLT_PROTOCOL_CONTRACT.to_string()),
+            env::var("BOLT_TRANSFER_FEE")
+                .ok()
+                .and_then(|fee| fee.parse().ok())
+                .unwrap_or(DEFAULT_BOLT_TRANSFER_FEE),
+        );
+
+        Self {
+            invoice_repository: InvoiceRepository::new(database, event_bus.clone()),
+            payment_repository: PaymentRepository::new(database, event_bus.clone()),
+            audit_repository: AuditRepository::new(database),
+            merchant_repository: MerchantRepository::new(database),
+            payment_link_repository: PaymentLinkRepository::new(database),
+            subscription_plan_repository: SubscriptionPlanRepository::new(database),
+            subscription_repository: SubscriptionRepository::new(database),
+            price_history_repository,
+            price_guard_repository,
+            quote_service,
+            bolt_protocol_service,
+            payout_service,
+            event_bus,
+            notification_service,
+            token_registry,
+        }
+    }
+
+    /// Create the indexes of every collection, creating an existing index again is a no-op
+    pub async fn create_indexes(&self) -> Result<()> {
+        self.payment_repository
+            .create_indexes()
+            .await
+            .context("Failed to create payment indexes")?;
+        self.audit_repository
+            .create_indexes()
+            .await
+            .context("Failed to create audit event indexes")?;
+        self.merchant_repository
+            .create_indexes()
+            .await
+            .context("Failed to create merchant indexes")?;
+        self.payment_link_repository
+            .create_indexes()
+            .await
+            .context("Failed to create payment link indexes")?;
+        self.subscription_plan_repository
+            .create_indexes()
+            .await
+            .context("Failed to create subscription plan indexes")?;
+        self.subscription_repository
+            .create_indexes()
+            .await
+            .context("Failed to create subscription indexes")?;
+        self.price_history_repository
+            .create_indexes()
+            .await
+            .context("Failed to create price history indexes")?;
+        Ok(())
+    }
+}
@@ -1,28 +1,24 @@
 // src/main.rs
-mod api;
-mod database;
-mod handlers;
-mod models;
-mod services;
-mod shared;
-
 use axum::{routing::get, Router};
 use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber;
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 use std::env;
-
-use database::{MongoDBClient, InvoiceRepository, PaymentRepository};
-use services::quote_service::QuoteService;
-use services::bolt_protocol_service::BoltProtocolService;
-
-#[derive(Clone)]
-pub struct AppState {
-    pub invoice_repository: InvoiceRepository,
-    pub payment_repository: PaymentRepository,
-    pub quote_service: QuoteService,
-    pub bolt_protocol_service: BoltProtocolService,
-}
+use std::net::SocketAddr;
+
+use bolt_payment_gateway_server::api;
+use bolt_payment_gateway_server::database::{LeaseRepository, MigrationRunner, MongoDBClient};
+use bolt_payment_gateway_server::handlers::metrics_handler;
+use bolt_payment_gateway_server::services::event_bus::EventBus;
+use bolt_payment_gateway_server::shared::{RateLimitConfig, RateLimitLayer};
+use bolt_payment_gateway_server::services::change_stream_service::{ChangeStreamService, RESUME_TOKEN_LEASE_TTL};
+use bolt_payment_gateway_server::services::leader_lease::LeaderLease;
+use bolt_payment_gateway_server::services::payment_reconciler::{PaymentReconciler, RECONCILER_LEASE_TTL};
+use bolt_payment_gateway_server::services::subscription_scheduler::{SubscriptionScheduler, SCHEDULER_LEASE_TTL};
+use bolt_payment_gateway_server::AppState;
 
 #[tokio::main]
 async fn main() {
@@ -43,41 +39,93 @@ async fn main() {
         .await
         .expect("Failed to connect to MongoDB");
 
-    // Initialize repositories
-    let invoice_repository = InvoiceRepository::new(mongodb_client.get_database());
-    let payment_repository = PaymentRepository::new(mongodb_client.get_database());
+    // Invoice and payment changes are published here for live subscribers
+    let event_bus = EventBus::new();
 
-    // Create indexes for payments
-    if let Err(e) = payment_repository.create_indexes().await {
-        eprintln!("Failed to create payment indexes: {}", e);
+    // Repositories, and services configured from the environment
+    let app_state = AppState::from_env(mongodb_client.get_database(), event_bus.clone());
+
+    let instance_id = env::var("EVENT_BUS_INSTANCE_ID")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "default".to_string());
+
+    // Bring stored documents to the shape this release reads, one replica at a time
+    let migrations = MigrationRunner::new(mongodb_client.get_database(), instance_id.clone());
+    if let Err(e) = migrations.run().await {
+        eprintln!("{:#}", e);
         std::process::exit(1);
     }
 
-    // Initialize services
-    let quote_service = QuoteService::new();
-    let bolt_protocol_service = BoltProtocolService::new();
+    if let Err(e) = app_state.create_indexes().await {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
 
-    // Create application state
-    let app_state = AppState {
-        invoice_repository,
-        payment_repository,
-        quote_service,
-        bolt_protocol_service,
-    };
+    // Singleton jobs are led by one replica through a lease.
+    // The holder is unique per process, so a restarted replica does not inherit its old lease.
+    let lease_holder = format!("{}:{}", instance_id, uuid::Uuid::new_v4());
+
+    // Feed the event bus from the change streams so changes made by other replicas are seen too.
+    // Every replica listens, each serves its own live subscribers, one saves the shared positions.
+    let resume_token_lease = LeaderLease::new(
+        LeaseRepository::new(mongodb_client.get_database()),
+        "change_stream_resume_tokens",
+        &lease_holder,
+        RESUME_TOKEN_LEASE_TTL,
+    );
+    let change_stream_service =
+        ChangeStreamService::new(mongodb_client.get_database(), event_bus).with_lease(resume_token_lease);
+    if let Err(e) = change_stream_service.start().await {
+        tracing::warn!(
+            "Change streams unavailable, live updates only include this instance's changes: {}",
+            e
+        );
+    }
+
+    // Bill subscriptions through the regular invoice creation, on one replica at a time
+    let scheduler_lease = LeaderLease::new(
+        LeaseRepository::new(mongodb_client.get_database()),
+        "subscription_scheduler",
+        &lease_holder,
+        SCHEDULER_LEASE_TTL,
+    );
+    SubscriptionScheduler::new(app_state.clone()).with_lease(scheduler_lease).start();
+
+    // Settle payments whose broadcast outcome was lost, on one replica at a time
+    let reconciler_lease = LeaderLease::new(
+        LeaseRepository::new(mongodb_client.get_database()),
+        "payment_reconciler",
+        &lease_holder,
+        RECONCILER_LEASE_TTL,
+    );
+    PaymentReconciler::new(app_state.clone()).with_lease(reconciler_lease).start();
 
     // Build our application with routes
     let routes = api::v1::routes::create_routes();
+
+    // Per-client limits on the public routes, configured per route group
+    let rate_limits = match RateLimitConfig::from_env("/apipaymentgateway/v1") {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
     
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(metrics_handler::get_metrics))
         .route("/", get(root_health_check))
         .route("/apipaymentgateway/", get(api_health_check))
         .nest("/apipaymentgateway/v1", routes)
         .with_state(app_state)
         .layer(
             ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
                 .layer(TraceLayer::new_for_http())
-                .layer(CorsLayer::permissive()),
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(CorsLayer::permissive())
+                .layer(RateLimitLayer::new(rate_limits)),
         );
 
     // Run the server
@@ -85,7 +133,9 @@ async fn main() {
     println!("🚀 Server running on http://0.0.0.0:4000");
     println!("📊 Connected to MongoDB at: {}", mongodb_uri);
     
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
 
 async fn health_check() -> &'static str {
@@ -0,0 +1,72 @@
+// src/models/analytics.rs
+use serde::{Deserialize, Serialize};
+
+use crate::models::SettlementAsset;
+
+/// `granularity`: ["day", "week", "month"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsGranularity {
+    #[default]
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+}
+
+impl AnalyticsGranularity {
+    /// Unit name understood by `$dateTrunc`.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            AnalyticsGranularity::Day => "day",
+            AnalyticsGranularity::Week => "week",
+            AnalyticsGranularity::Month => "month",
+        }
+    }
+}
+
+/// Output of the merchant analytics aggregation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MerchantAnalytics {
+    /// One entry per settlement asset.
+    pub totals: Vec<AssetAnalytics>,
+    /// Paid volume per period and settlement asset, oldest first.
+    pub series: Vec<VolumeBucket>,
+}
+
+/// Counts and volumes of the invoices created in the range, for one settlement asset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetAnalytics {
+    #[serde(rename = "_id")]
+    pub settlement_asset: SettlementAsset,
+    pub created: i64,
+    /// Paid or settled.
+    pub paid: i64,
+    pub expired: i64,
+    pub cancelled: i64,
+    /// Amount of the paid invoices, in cents of the settlement asset.
+    pub gross_volume: i64,
+    /// Satoshis received for the invoices paid in sBTC.
+    pub sats_received: i64,
+    /// Amount of the invoices paid in sBTC, in cents of the settlement asset.
+    pub sbtc_volume: i64,
+    /// Average time between creating an invoice and receiving its payment.
+    pub avg_time_to_pay_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolumeBucket {
+    #[serde(rename = "_id")]
+    pub key: VolumeBucketKey,
+    pub paid: i64,
+    pub gross_volume: i64,
+    pub sats_received: i64,
+    pub sbtc_volume: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolumeBucketKey {
+    pub settlement_asset: SettlementAsset,
+    /// Start of the period in the requested timezone.
+    pub period: bson::DateTime,
+}
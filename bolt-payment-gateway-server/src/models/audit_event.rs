@@ -0,0 +1,170 @@
+// src/models/audit_event.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A single entry of the audit trail: one create or status change of an invoice or payment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    /// Kind of entity that changed.
+    pub entity_type: AuditEntityType,
+
+    /// ID of the invoice or payment that changed.
+    pub entity_id: bson::oid::ObjectId,
+
+    /// Invoice the change belongs to (the invoice itself, or the invoice of a payment).
+    pub invoice_id: bson::oid::ObjectId,
+
+    /// Who caused the change.
+    pub actor: Actor,
+
+    /// Status before the change, `None` when the entity was created.
+    pub old_state: Option<String>,
+
+    /// Status after the change.
+    pub new_state: String,
+
+    /// Human-readable reason for the change.
+    pub reason: Option<String>,
+
+    /// ID of the HTTP request (or job run) that caused the change.
+    pub request_id: Option<String>,
+
+    /// When the change was recorded.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// `entity_type`: ["invoice", "payment"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntityType {
+    Invoice,
+    Payment,
+}
+
+/// The party responsible for a change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Actor {
+    /// The merchant owning the invoice, identified by wallet address.
+    Merchant { wallet_address: String },
+    /// The paying customer, identified by sender address once it is known.
+    Customer { address: Option<String> },
+    /// A background worker of the gateway itself.
+    System { worker: String },
+    /// A gateway operator.
+    Admin { operator: String },
+}
+
+/// Who and why, passed to repository writes so they can record the audit trail.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor: Actor,
+    pub reason: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn new(actor: Actor, request_id: Option<String>) -> Self {
+        Self {
+            actor,
+            reason: None,
+            request_id,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+impl AuditEvent {
+    pub fn new<S: Serialize>(
+        entity_type: AuditEntityType,
+        entity_id: bson::oid::ObjectId,
+        invoice_id: bson::oid::ObjectId,
+        old_state: Option<S>,
+        new_state: S,
+        context: &AuditContext,
+    ) -> Self {
+        Self {
+            id: bson::oid::ObjectId::new(),
+            entity_type,
+            entity_id,
+            invoice_id,
+            actor: context.actor.clone(),
+            old_state: old_state.map(|s| state_name(&s)),
+            new_state: state_name(&new_state),
+            reason: context.reason.clone(),
+            request_id: context.request_id.clone(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// A command run by a gateway operator through `bolt-admin`, recorded whether or not it changed anything.
+/// State changes made by the command are also in the invoice trail, with an `Admin` actor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminAction {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    /// Operator who ran the command.
+    pub operator: String,
+
+    /// Command line after the binary name, e.g. `invoice expire 66f0...`.
+    pub command: String,
+
+    /// Invoice, payment or merchant the command targeted.
+    pub target: Option<String>,
+
+    /// Reason given by the operator.
+    pub reason: Option<String>,
+
+    /// Why the command failed, `None` when it succeeded.
+    pub error: Option<String>,
+
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Serialized name of a status value, e.g. `InvoiceStatus::Paid` -> "paid"
+fn state_name<S: Serialize>(state: &S) -> String {
+    match serde_json::to_value(state) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InvoiceStatus;
+
+    #[test]
+    fn test_audit_event_records_serialized_state_names() {
+        let invoice_id = bson::oid::ObjectId::new();
+        let context = AuditContext::new(
+            Actor::Merchant { wallet_address: "ST1TEST".to_string() },
+            Some("req-1".to_string()),
+        )
+        .with_reason("test");
+
+        let event = AuditEvent::new(
+            AuditEntityType::Invoice,
+            invoice_id,
+            invoice_id,
+            Some(InvoiceStatus::Created),
+            InvoiceStatus::Paid,
+            &context,
+        );
+
+        assert_eq!(event.old_state.as_deref(), Some("created"));
+        assert_eq!(event.new_state, "paid");
+        assert_eq!(event.reason.as_deref(), Some("test"));
+        assert_eq!(event.request_id.as_deref(), Some("req-1"));
+    }
+}
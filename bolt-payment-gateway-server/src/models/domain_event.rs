@@ -0,0 +1,48 @@
+// src/models/domain_event.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{InvoiceQuote, InvoiceStatus, PaymentStatus};
+
+/// Something that happened to an invoice or one of its payments, published on the event bus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    /// An invoice was created (`from` is `None`) or changed status.
+    InvoiceStatusChanged {
+        invoice_id: bson::oid::ObjectId,
+        from: Option<InvoiceStatus>,
+        to: InvoiceStatus,
+        timestamp: DateTime<Utc>,
+    },
+    /// The amount, order ID or deadline of an invoice awaiting payment changed.
+    InvoiceAmended {
+        invoice_id: bson::oid::ObjectId,
+        timestamp: DateTime<Utc>,
+    },
+    /// A new BTC quote was locked for an invoice.
+    QuoteRefreshed {
+        invoice_id: bson::oid::ObjectId,
+        quote: InvoiceQuote,
+    },
+    /// A payment was created (`from` is `None`) or changed status.
+    PaymentStatusChanged {
+        invoice_id: bson::oid::ObjectId,
+        payment_id: bson::oid::ObjectId,
+        from: Option<PaymentStatus>,
+        to: PaymentStatus,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl DomainEvent {
+    /// Invoice the event belongs to.
+    pub fn invoice_id(&self) -> bson::oid::ObjectId {
+        match self {
+            DomainEvent::InvoiceStatusChanged { invoice_id, .. }
+            | DomainEvent::InvoiceAmended { invoice_id, .. }
+            | DomainEvent::QuoteRefreshed { invoice_id, .. }
+            | DomainEvent::PaymentStatusChanged { invoice_id, .. } => *invoice_id,
+        }
+    }
+}
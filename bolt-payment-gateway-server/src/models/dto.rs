@@ -1,15 +1,133 @@
 use axum::{http::StatusCode, Json};
 // src/models/dto.rs
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::models::{Invoice, Payment, InvoiceStatus, SettlementAsset, PaymentStatus, PaymentToken};
+use crate::database::StatusUpdateError;
+use crate::services::payout_service::PayoutError;
+use crate::models::{Actor, AnalyticsGranularity, AssetAnalytics, ExportRow, VolumeBucket, AuditEntityType, AuditEvent, Branding, CustodyMode, FeeSchedule, Invoice, InvoiceQuote, BillingCycle, BillingInterval, CycleStatus, LineItem, LinkAmount, Merchant, Payment, PaymentLink, PriceCandle, PriceHalt, PriceInterval, PriceTick, Subscription, SubscriptionPlan, SubscriptionStatus, InvoiceStatus, SettlementAsset, PaymentStatus, PaymentToken};
 
 // Request DTOs
 #[derive(Debug, Deserialize)]
 pub struct CreateInvoiceRequest {
     pub amount: String,
-    pub settlement_asset: SettlementAsset,
+    /// Defaults to the merchant's `default_settlement_asset`
+    pub settlement_asset: Option<SettlementAsset>,
     pub merchant_order_id: String,
+    /// Invoice lifetime in seconds, defaults to the merchant's `default_invoice_expiry_secs`
+    pub expires_in_secs: Option<i64>,
+    /// Absolute deadline, an alternative to `expires_in_secs`
+    pub due_date: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+    /// When present, the line totals must add up to `amount`
+    pub line_items: Option<Vec<LineItemRequest>>,
+    pub metadata: Option<BTreeMap<String, String>>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LineItemRequest {
+    pub name: String,
+    pub quantity: u32,
+    pub unit_price: String,
+    /// Tax for the whole line, defaults to zero
+    pub tax: Option<String>,
+}
+
+/// Partial update of an invoice awaiting payment, only the fields present are changed
+#[derive(Debug, Deserialize)]
+pub struct UpdateInvoiceRequest {
+    pub amount: Option<String>,
+    pub merchant_order_id: Option<String>,
+    /// New lifetime in seconds counted from now
+    pub expires_in_secs: Option<i64>,
+    /// New absolute deadline, an alternative to `expires_in_secs`
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelInvoiceRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentLinkRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub amount: LinkAmountDto,
+    /// Defaults to the merchant's `default_settlement_asset`
+    pub settlement_asset: Option<SettlementAsset>,
+    /// Unlimited when absent
+    pub usage_limit: Option<u32>,
+    /// Defaults to true
+    pub active: Option<bool>,
+}
+
+/// Partial update of a payment link, only the fields present are changed
+#[derive(Debug, Deserialize)]
+pub struct UpdatePaymentLinkRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub amount: Option<LinkAmountDto>,
+    pub settlement_asset: Option<SettlementAsset>,
+    pub usage_limit: Option<u32>,
+    pub active: Option<bool>,
+}
+
+/// Opening a link as a customer
+#[derive(Debug, Default, Deserialize)]
+pub struct OpenPaymentLinkRequest {
+    /// Required for links with a customer-chosen amount, refused otherwise
+    pub amount: Option<String>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSubscriptionPlanRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub amount: String,
+    /// Defaults to the merchant's `default_settlement_asset`
+    pub settlement_asset: Option<SettlementAsset>,
+    pub interval: BillingInterval,
+    /// Defaults to 1
+    pub interval_count: Option<u32>,
+    /// Defaults to the merchant's `default_invoice_expiry_secs`
+    pub payment_window_secs: Option<i64>,
+    /// Defaults to `DEFAULT_MAX_UNPAID_CYCLES`
+    pub max_unpaid_cycles: Option<u32>,
+    /// Defaults to true
+    pub active: Option<bool>,
+}
+
+/// Partial update of a plan, changes apply from the next billed period
+#[derive(Debug, Deserialize)]
+pub struct UpdateSubscriptionPlanRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub amount: Option<String>,
+    pub settlement_asset: Option<SettlementAsset>,
+    pub interval: Option<BillingInterval>,
+    pub interval_count: Option<u32>,
+    pub payment_window_secs: Option<i64>,
+    pub max_unpaid_cycles: Option<u32>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSubscriptionRequest {
+    pub customer_email: Option<String>,
+    pub customer_reference: Option<String>,
+    /// Start of the first period, defaults to now
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelSubscriptionRequest {
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,18 +137,88 @@ pub struct SubmitPaymentRequest {
     pub amount: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CreateMerchantRequest {
+    pub wallet_address: String,
+    pub display_name: String,
+    pub payout_address: Option<String>,
+    pub custody_mode: Option<CustodyMode>,
+    pub default_settlement_asset: Option<SettlementAsset>,
+    pub default_invoice_expiry_secs: Option<i64>,
+    pub min_invoice_expiry_secs: Option<i64>,
+    pub max_invoice_expiry_secs: Option<i64>,
+    pub quote_window_secs: Option<i64>,
+    pub allowed_payment_tokens: Option<Vec<PaymentToken>>,
+    pub fee_schedule: Option<FeeSchedule>,
+    pub webhook_url: Option<String>,
+    pub branding: Option<Branding>,
+}
+
+/// Partial update, only the fields present are changed
+#[derive(Debug, Deserialize)]
+pub struct UpdateMerchantRequest {
+    pub display_name: Option<String>,
+    pub payout_address: Option<String>,
+    pub custody_mode: Option<CustodyMode>,
+    pub default_settlement_asset: Option<SettlementAsset>,
+    pub default_invoice_expiry_secs: Option<i64>,
+    pub min_invoice_expiry_secs: Option<i64>,
+    pub max_invoice_expiry_secs: Option<i64>,
+    pub quote_window_secs: Option<i64>,
+    pub allowed_payment_tokens: Option<Vec<PaymentToken>>,
+    pub fee_schedule: Option<FeeSchedule>,
+    pub webhook_url: Option<String>,
+    pub branding: Option<Branding>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListInvoicesQuery {
     pub status: Option<InvoiceStatus>,
     pub merchant_order_id: Option<String>,
     pub from_date: Option<DateTime<Utc>>,
     pub to_date: Option<DateTime<Utc>>,
+    /// Case-insensitive substring of the description
+    pub description: Option<String>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+    /// Only invoices with this metadata key
+    pub metadata_key: Option<String>,
+    /// Only invoices whose `metadata_key` has this value
+    pub metadata_value: Option<String>,
+    pub payment_link_id: Option<String>,
+    pub subscription_id: Option<String>,
+    /// `payments` embeds each invoice's payments
+    pub include: Option<String>,
     #[serde(default = "default_limit")]
     pub limit: usize,
     #[serde(default)]
     pub offset: usize,
 }
 
+/// Filters shared by the payment listing endpoints, dates apply to `received_at`
+#[derive(Debug, Deserialize)]
+pub struct ListPaymentsQuery {
+    pub tx_id: Option<String>,
+    pub status: Option<PaymentStatus>,
+    pub from_date: Option<DateTime<Utc>>,
+    pub to_date: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Optional related resources to embed in a response, e.g. `?include=payments`
+#[derive(Debug, Default, Deserialize)]
+pub struct IncludeQuery {
+    pub include: Option<String>,
+}
+
+/// Whether a comma-separated `include` parameter asks for `resource`
+pub fn includes(include: Option<&str>, resource: &str) -> bool {
+    include.is_some_and(|include| include.split(',').any(|r| r.trim() == resource))
+}
+
 fn default_limit() -> usize {
     20
 }
@@ -44,23 +232,212 @@ pub struct InvoiceResponse {
     pub settlement_asset: SettlementAsset,
     pub merchant_order_id: String,
     pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub checkout_url: String,
+    pub recipient_address: Option<String>,
+    pub quote: Option<InvoiceQuoteResponse>,
+    pub description: Option<String>,
+    pub line_items: Vec<LineItemResponse>,
+    pub metadata: BTreeMap<String, String>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+    pub payment_link_id: Option<String>,
+    pub subscription_id: Option<String>,
+    /// Only present when requested with `include=payments`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payments: Option<Vec<PaymentResponse>>,
+}
+
+impl InvoiceResponse {
+    pub fn with_payments(mut self, payments: Vec<Payment>) -> Self {
+        self.payments = Some(payments.into_iter().map(PaymentResponse::from).collect());
+        self
+    }
 }
 
 impl From<Invoice> for InvoiceResponse {
     fn from(invoice: Invoice) -> Self {
         Self {
-            checkout_url: format!("https://test.boltproto.org/checkout/{}", invoice.id.to_string()),
+            checkout_url: format!("https://test.boltproto.org/checkout/{}", invoice.id),
             id: invoice.id.to_string(),
             status: invoice.status,
             amount: format_money_amount(invoice.amount),
             settlement_asset: invoice.settlement_asset,
             merchant_order_id: invoice.merchant_order_id,
             created_at: invoice.created_at,
+            expires_at: invoice.expires_at,
+            recipient_address: invoice.recipient_address,
+            quote: invoice.quote.map(InvoiceQuoteResponse::from),
+            description: invoice.description,
+            line_items: invoice.line_items.into_iter().map(LineItemResponse::from).collect(),
+            metadata: invoice.metadata,
+            buyer_email: invoice.buyer_email,
+            buyer_reference: invoice.buyer_reference,
+            payment_link_id: invoice.payment_link_id.map(|id| id.to_string()),
+            subscription_id: invoice.subscription_id.map(|id| id.to_string()),
+            payments: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineItemResponse {
+    pub name: String,
+    pub quantity: u32,
+    pub unit_price: String,
+    pub tax: String,
+    pub total: String,
+}
+
+impl From<LineItem> for LineItemResponse {
+    fn from(item: LineItem) -> Self {
+        Self {
+            total: item.total().map(format_money_amount).unwrap_or_default(),
+            unit_price: format_money_amount(item.unit_price),
+            tax: format_money_amount(item.tax),
+            quantity: item.quantity,
+            name: item.name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvoiceQuoteResponse {
+    pub asset: PaymentToken,
+    pub amount: String,
+    pub unit_price: String,
+    pub spread: String,
+    pub locked_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<InvoiceQuote> for InvoiceQuoteResponse {
+    fn from(quote: InvoiceQuote) -> Self {
+        Self {
+            asset: PaymentToken::SBTC,
+            amount: quote.amount.to_string(),
+            unit_price: format_money_amount(quote.btc_price),
+            spread: format!("{:.2}%", quote.spread_bps as f64 / 100.0),
+            locked_at: quote.locked_at,
+            expires_at: quote.expires_at,
+        }
+    }
+}
+
+/// Everything a wallet needs to build the transfer paying an invoice
+#[derive(Debug, Serialize)]
+pub struct CheckoutResponse {
+    pub invoice_id: String,
+    pub status: InvoiceStatus,
+    pub merchant_name: String,
+    pub branding: Branding,
+    pub amount: String,
+    pub settlement_asset: SettlementAsset,
+    pub merchant_order_id: String,
+    pub description: Option<String>,
+    /// Invoice deadline
+    pub expires_at: Option<DateTime<Utc>>,
+    pub recipient_address: String,
+    /// To include in the transfer
+    pub memo: String,
+    /// Locked quote the amounts due are based on, with its own expiry
+    pub quote: InvoiceQuoteResponse,
+    /// One per token the merchant accepts and the gateway can receive
+    pub payment_options: Vec<PaymentOptionResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentOptionResponse {
+    pub token: PaymentToken,
+    /// In the token's base unit
+    pub amount_due: String,
+    pub decimals: u8,
+    pub token_contract_id: String,
+    pub transfer_contract_id: String,
+    pub transfer_function: String,
+    /// Sponsoring fee in the token's base unit, on top of the amount due
+    pub fee: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentUriQuery {
+    /// Defaults to the first token the merchant accepts
+    pub token: Option<PaymentToken>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QrCodeQuery {
+    /// Defaults to the first token the merchant accepts
+    pub token: Option<PaymentToken>,
+    #[serde(default)]
+    pub format: QrCodeFormat,
+    /// Minimal width and height in pixels
+    pub size: Option<u32>,
+}
+
+/// `format`: ["png", "svg"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QrCodeFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrCodeFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrCodeFormat::Png => "image/png",
+            QrCodeFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Payment request a mobile wallet can scan, valid until the quote expires
+#[derive(Debug, Serialize)]
+pub struct PaymentUriResponse {
+    pub uri: String,
+    pub token: PaymentToken,
+    pub amount: String,
+    pub recipient_address: String,
+    pub memo: String,
+    pub quote_expires_at: DateTime<Utc>,
+}
+
+/// Message pushed to subscribers of an invoice's event stream
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum InvoiceUpdateResponse {
+    /// Current state of the invoice, sent on subscribe and after every invoice change
+    Invoice(Box<InvoiceResponse>),
+    /// A payment for the invoice was created or changed status
+    Payment(PaymentResponse),
+    /// A new BTC quote was locked
+    Quote(InvoiceQuoteResponse),
+    /// Time left while the invoice awaits payment
+    Countdown(InvoiceCountdownResponse),
+}
+
+impl InvoiceUpdateResponse {
+    /// Name of the update, used as the SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvoiceUpdateResponse::Invoice(_) => "invoice",
+            InvoiceUpdateResponse::Payment(_) => "payment",
+            InvoiceUpdateResponse::Quote(_) => "quote",
+            InvoiceUpdateResponse::Countdown(_) => "countdown",
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct InvoiceCountdownResponse {
+    /// Seconds until the invoice expires
+    pub invoice_expires_in_secs: Option<i64>,
+    /// Seconds until the locked quote has to be refreshed
+    pub quote_expires_in_secs: Option<i64>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PaymentResponse {
     pub id: String,
@@ -71,6 +448,9 @@ pub struct PaymentResponse {
     pub sender_address: Option<String>,
     pub received_at: DateTime<Utc>,
     pub tx_id: Option<String>,
+    pub recipient_address: Option<String>,
+    /// BTC price in USD the payment was validated against
+    pub btc_price: Option<String>,
 }
 
 impl From<Payment> for PaymentResponse {
@@ -85,10 +465,258 @@ impl From<Payment> for PaymentResponse {
             sender_address: payment.sender_address,
             received_at: payment.received_at,
             tx_id: payment.tx_id,
+            recipient_address: payment.recipient_address,
+            btc_price: payment.btc_price.map(format_money_amount),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantResponse {
+    pub id: String,
+    pub wallet_address: String,
+    pub display_name: String,
+    pub payout_address: Option<String>,
+    pub payout_address_verified: bool,
+    pub custody_mode: CustodyMode,
+    pub default_settlement_asset: SettlementAsset,
+    pub default_invoice_expiry_secs: i64,
+    pub min_invoice_expiry_secs: i64,
+    pub max_invoice_expiry_secs: i64,
+    pub quote_window_secs: i64,
+    pub allowed_payment_tokens: Vec<PaymentToken>,
+    pub fee_schedule: FeeSchedule,
+    pub webhook_url: Option<String>,
+    pub branding: Branding,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Merchant> for MerchantResponse {
+    fn from(merchant: Merchant) -> Self {
+        Self {
+            id: merchant.id.to_string(),
+            wallet_address: merchant.wallet_address,
+            display_name: merchant.display_name,
+            payout_address: merchant.payout_address,
+            payout_address_verified: merchant.payout_address_verified,
+            custody_mode: merchant.custody_mode,
+            default_settlement_asset: merchant.default_settlement_asset,
+            default_invoice_expiry_secs: merchant.default_invoice_expiry_secs,
+            min_invoice_expiry_secs: merchant.min_invoice_expiry_secs,
+            max_invoice_expiry_secs: merchant.max_invoice_expiry_secs,
+            quote_window_secs: merchant.quote_window_secs,
+            allowed_payment_tokens: merchant.allowed_payment_tokens,
+            fee_schedule: merchant.fee_schedule,
+            webhook_url: merchant.webhook_url,
+            branding: merchant.branding,
+            created_at: merchant.created_at,
+            updated_at: merchant.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventResponse {
+    pub id: String,
+    pub entity_type: AuditEntityType,
+    pub entity_id: String,
+    pub actor: Actor,
+    pub old_state: Option<String>,
+    pub new_state: String,
+    pub reason: Option<String>,
+    pub request_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            entity_type: event.entity_type,
+            entity_id: event.entity_id.to_string(),
+            actor: event.actor,
+            old_state: event.old_state,
+            new_state: event.new_state,
+            reason: event.reason,
+            request_id: event.request_id,
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvoiceTimelineResponse {
+    pub invoice_id: String,
+    pub events: Vec<AuditEventResponse>,
+}
+
+/// Amount of a payment link as exchanged over the API, amounts are decimal strings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkAmountDto {
+    Fixed { amount: String },
+    CustomerChosen { min_amount: String, max_amount: String },
+}
+
+impl From<LinkAmount> for LinkAmountDto {
+    fn from(amount: LinkAmount) -> Self {
+        match amount {
+            LinkAmount::Fixed { amount } => LinkAmountDto::Fixed {
+                amount: format_money_amount(amount),
+            },
+            LinkAmount::CustomerChosen { min_amount, max_amount } => LinkAmountDto::CustomerChosen {
+                min_amount: format_money_amount(min_amount),
+                max_amount: format_money_amount(max_amount),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentLinkResponse {
+    pub id: String,
+    pub wallet_address: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub amount: LinkAmountDto,
+    pub settlement_asset: SettlementAsset,
+    pub usage_limit: Option<u32>,
+    pub usage_count: u32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<PaymentLink> for PaymentLinkResponse {
+    fn from(link: PaymentLink) -> Self {
+        Self {
+            id: link.id.to_string(),
+            wallet_address: link.wallet_address,
+            title: link.title,
+            description: link.description,
+            amount: link.amount.into(),
+            settlement_asset: link.settlement_asset,
+            usage_limit: link.usage_limit,
+            usage_count: link.usage_count,
+            active: link.active,
+            created_at: link.created_at,
+            updated_at: link.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListPaymentLinksResponse {
+    pub items: Vec<PaymentLinkResponse>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionPlanResponse {
+    pub id: String,
+    pub wallet_address: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub amount: String,
+    pub settlement_asset: SettlementAsset,
+    pub interval: BillingInterval,
+    pub interval_count: u32,
+    pub payment_window_secs: Option<i64>,
+    pub max_unpaid_cycles: u32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<SubscriptionPlan> for SubscriptionPlanResponse {
+    fn from(plan: SubscriptionPlan) -> Self {
+        Self {
+            id: plan.id.to_string(),
+            wallet_address: plan.wallet_address,
+            name: plan.name,
+            description: plan.description,
+            amount: format_money_amount(plan.amount),
+            settlement_asset: plan.settlement_asset,
+            interval: plan.interval,
+            interval_count: plan.interval_count,
+            payment_window_secs: plan.payment_window_secs,
+            max_unpaid_cycles: plan.max_unpaid_cycles,
+            active: plan.active,
+            created_at: plan.created_at,
+            updated_at: plan.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListSubscriptionPlansResponse {
+    pub items: Vec<SubscriptionPlanResponse>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionResponse {
+    pub id: String,
+    pub plan_id: String,
+    pub wallet_address: String,
+    pub customer_email: Option<String>,
+    pub customer_reference: Option<String>,
+    pub status: SubscriptionStatus,
+    pub next_billing_at: DateTime<Utc>,
+    pub unpaid_cycles: u32,
+    pub cycles: Vec<BillingCycleResponse>,
+    pub cancel_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BillingCycleResponse {
+    pub number: u32,
+    pub invoice_id: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub status: CycleStatus,
+}
+
+impl From<Subscription> for SubscriptionResponse {
+    fn from(subscription: Subscription) -> Self {
+        Self {
+            id: subscription.id.to_string(),
+            plan_id: subscription.plan_id.to_string(),
+            wallet_address: subscription.wallet_address,
+            customer_email: subscription.customer_email,
+            customer_reference: subscription.customer_reference,
+            status: subscription.status,
+            next_billing_at: subscription.next_billing_at,
+            unpaid_cycles: subscription.unpaid_cycles,
+            cycles: subscription.cycles.into_iter().map(BillingCycleResponse::from).collect(),
+            cancel_reason: subscription.cancel_reason,
+            created_at: subscription.created_at,
+            updated_at: subscription.updated_at,
+        }
+    }
+}
+
+impl From<BillingCycle> for BillingCycleResponse {
+    fn from(cycle: BillingCycle) -> Self {
+        Self {
+            number: cycle.number,
+            invoice_id: cycle.invoice_id.to_string(),
+            period_start: cycle.period_start,
+            period_end: cycle.period_end,
+            status: cycle.status,
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct ListSubscriptionsResponse {
+    pub items: Vec<SubscriptionResponse>,
+    pub total: usize,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ListInvoicesResponse {
     pub items: Vec<InvoiceResponse>,
@@ -97,11 +725,346 @@ pub struct ListInvoicesResponse {
     pub offset: usize,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ListPaymentsResponse {
+    pub items: Vec<PaymentResponse>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    /// Start of the range (inclusive), defaults to 30 days before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// End of the range (exclusive), defaults to now
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub granularity: AnalyticsGranularity,
+    /// Olson timezone name or UTC offset the periods are aligned to, defaults to UTC
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MerchantAnalyticsResponse {
+    pub wallet_address: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub granularity: AnalyticsGranularity,
+    pub timezone: String,
+    pub totals: Vec<AssetAnalyticsResponse>,
+    pub series: Vec<VolumeBucketResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssetAnalyticsResponse {
+    pub settlement_asset: SettlementAsset,
+    pub created: i64,
+    pub paid: i64,
+    pub expired: i64,
+    pub cancelled: i64,
+    /// Share of the created invoices that were paid, between 0 and 1
+    pub conversion_rate: f64,
+    pub average_time_to_pay_secs: Option<f64>,
+    pub gross_volume: String,
+    /// Satoshis received, payments in other tokens are left out
+    pub sbtc_received: String,
+    /// Settlement amount received per BTC, `None` before any sBTC payment
+    pub effective_exchange_rate: Option<String>,
+}
+
+impl From<AssetAnalytics> for AssetAnalyticsResponse {
+    fn from(totals: AssetAnalytics) -> Self {
+        Self {
+            settlement_asset: totals.settlement_asset,
+            created: totals.created,
+            paid: totals.paid,
+            expired: totals.expired,
+            cancelled: totals.cancelled,
+            conversion_rate: if totals.created > 0 {
+                totals.paid as f64 / totals.created as f64
+            } else {
+                0.0
+            },
+            average_time_to_pay_secs: totals.avg_time_to_pay_ms.map(|ms| ms / 1000.0),
+            gross_volume: format_money_amount(totals.gross_volume.max(0) as u128),
+            sbtc_received: totals.sats_received.to_string(),
+            effective_exchange_rate: effective_exchange_rate(totals.sbtc_volume, totals.sats_received),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VolumeBucketResponse {
+    /// Start of the period
+    pub period: DateTime<Utc>,
+    pub settlement_asset: SettlementAsset,
+    pub paid: i64,
+    pub gross_volume: String,
+    pub sbtc_received: String,
+    pub effective_exchange_rate: Option<String>,
+}
+
+impl From<VolumeBucket> for VolumeBucketResponse {
+    fn from(bucket: VolumeBucket) -> Self {
+        Self {
+            period: bucket.key.period.to_chrono(),
+            settlement_asset: bucket.key.settlement_asset,
+            paid: bucket.paid,
+            gross_volume: format_money_amount(bucket.gross_volume.max(0) as u128),
+            sbtc_received: bucket.sats_received.to_string(),
+            effective_exchange_rate: effective_exchange_rate(bucket.sbtc_volume, bucket.sats_received),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceHistoryQuery {
+    /// Only `BTCUSD` is recorded
+    pub pair: Option<String>,
+    /// Start of the range (inclusive), defaults to 24 hours before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// End of the range (exclusive), defaults to now
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub interval: PriceInterval,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceHistoryResponse {
+    pub pair: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub interval: PriceInterval,
+    /// Oldest first, intervals without a recorded price are left out
+    pub candles: Vec<PriceCandleResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceCandleResponse {
+    /// Start of the interval
+    pub open_time: DateTime<Utc>,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    /// Prices recorded in the interval
+    pub ticks: i64,
+}
+
+impl From<PriceCandle> for PriceCandleResponse {
+    fn from(candle: PriceCandle) -> Self {
+        Self {
+            open_time: candle.open_time.to_chrono(),
+            open: format_money_amount(candle.open),
+            high: format_money_amount(candle.high),
+            low: format_money_amount(candle.low),
+            close: format_money_amount(candle.close),
+            ticks: candle.ticks,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceTickResponse {
+    pub price: String,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl From<PriceTick> for PriceTickResponse {
+    fn from(tick: PriceTick) -> Self {
+        Self {
+            price: format_money_amount(tick.price),
+            source: tick.source,
+            fetched_at: tick.fetched_at.to_chrono(),
+        }
+    }
+}
+
+/// `basis`: ["locked_quote", "current_price"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceBasis {
+    /// The price locked by the invoice's quote, open when the payment was received
+    LockedQuote,
+    /// The price current when the payment was received
+    CurrentPrice,
+}
+
+/// The BTC price a payment was validated against, and where it came from
+#[derive(Debug, Serialize)]
+pub struct PaymentPriceResponse {
+    pub payment_id: String,
+    pub invoice_id: String,
+    pub pair: String,
+    pub btc_price: String,
+    pub basis: PriceBasis,
+    pub received_at: DateTime<Utc>,
+    /// Present when `basis` is `locked_quote`
+    pub quote: Option<InvoiceQuoteResponse>,
+    /// The recorded tick the price came from, `None` when it is no longer or was never recorded
+    pub tick: Option<PriceTickResponse>,
+}
+
+/// Settlement amount per BTC: `volume` cents received for `sats` satoshis
+fn effective_exchange_rate(volume: i64, sats: i64) -> Option<String> {
+    if volume <= 0 || sats <= 0 {
+        return None;
+    }
+    Some(format_money_amount(volume as u128 * 100_000_000 / sats as u128))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Start of the range (inclusive)
+    pub from: DateTime<Utc>,
+    /// End of the range (exclusive)
+    pub to: DateTime<Utc>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// `format`: ["csv", "jsonl"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/jsonl",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// One exported line: an invoice with one of its payments
+#[derive(Debug, Serialize)]
+pub struct ExportRecord {
+    pub invoice_id: String,
+    pub merchant_order_id: String,
+    pub invoice_status: InvoiceStatus,
+    pub invoice_amount: String,
+    pub settlement_asset: SettlementAsset,
+    pub invoice_created_at: DateTime<Utc>,
+    pub invoice_expires_at: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+    pub payment_id: Option<String>,
+    pub payment_status: Option<PaymentStatus>,
+    pub payment_asset: Option<PaymentToken>,
+    /// Satoshis
+    pub payment_amount: Option<String>,
+    /// BTC price in USD the payment was validated against
+    pub btc_price: Option<String>,
+    pub tx_id: Option<String>,
+    pub sender_address: Option<String>,
+    pub recipient_address: Option<String>,
+    pub payment_received_at: Option<DateTime<Utc>>,
+}
+
+impl ExportRecord {
+    pub const CSV_HEADER: &'static str = "invoice_id,merchant_order_id,invoice_status,invoice_amount,settlement_asset,invoice_created_at,invoice_expires_at,description,buyer_email,buyer_reference,payment_id,payment_status,payment_asset,payment_amount,btc_price,tx_id,sender_address,recipient_address,payment_received_at\r\n";
+
+    /// The record as a CSV line in `CSV_HEADER` order, terminated by CRLF
+    pub fn to_csv_line(&self) -> String {
+        // Enum values serialize to plain JSON strings, e.g. "paid"
+        fn name<T: Serialize>(value: &T) -> String {
+            serde_json::to_value(value)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default()
+        }
+
+        let fields = [
+            self.invoice_id.clone(),
+            self.merchant_order_id.clone(),
+            name(&self.invoice_status),
+            self.invoice_amount.clone(),
+            name(&self.settlement_asset),
+            self.invoice_created_at.to_rfc3339(),
+            self.invoice_expires_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            self.description.clone().unwrap_or_default(),
+            self.buyer_email.clone().unwrap_or_default(),
+            self.buyer_reference.clone().unwrap_or_default(),
+            self.payment_id.clone().unwrap_or_default(),
+            self.payment_status.as_ref().map(name).unwrap_or_default(),
+            self.payment_asset.as_ref().map(name).unwrap_or_default(),
+            self.payment_amount.clone().unwrap_or_default(),
+            self.btc_price.clone().unwrap_or_default(),
+            self.tx_id.clone().unwrap_or_default(),
+            self.sender_address.clone().unwrap_or_default(),
+            self.recipient_address.clone().unwrap_or_default(),
+            self.payment_received_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        ];
+
+        let mut line = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+        line.push_str("\r\n");
+        line
+    }
+}
+
+impl From<ExportRow> for ExportRecord {
+    fn from(row: ExportRow) -> Self {
+        Self {
+            invoice_id: row.invoice_id.to_string(),
+            merchant_order_id: row.merchant_order_id,
+            invoice_status: row.invoice_status,
+            invoice_amount: format_money_amount(row.invoice_amount.max(0) as u128),
+            settlement_asset: row.settlement_asset,
+            invoice_created_at: row.created_at,
+            invoice_expires_at: row.expires_at,
+            description: row.description,
+            buyer_email: row.buyer_email,
+            buyer_reference: row.buyer_reference,
+            payment_id: row.payment_id.map(|id| id.to_string()),
+            payment_status: row.payment_status,
+            payment_asset: row.payment_asset,
+            payment_amount: row.payment_amount.map(|amount| amount.to_string()),
+            btc_price: row.btc_price.map(|price| format_money_amount(price.max(0) as u128)),
+            tx_id: row.tx_id,
+            sender_address: row.sender_address,
+            recipient_address: row.recipient_address,
+            payment_received_at: row.received_at,
+        }
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break (RFC 4180).
+/// Values a spreadsheet would run as a formula, e.g. a description set by a customer, are prefixed with `'`.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct QuoteQuery {
     pub from: String,
     pub to: String,
     pub to_amount: String,
+    /// Wallet address of the merchant whose fee schedule applies
+    pub merchant: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -136,6 +1099,85 @@ pub fn convert_string_to_object_id(id: &str) -> Result<bson::oid::ObjectId, (Sta
     })
 }
 
+/// Convert a failed conditional status update into an HTTP error response
+/// Illegal transitions and lost races both surface as 409 Conflict.
+pub fn status_update_error_response<S: std::fmt::Debug>(
+    err: &StatusUpdateError<S>,
+) -> (StatusCode, Json<ErrorResponse>) {
+    match err {
+        StatusUpdateError::IllegalTransition(e) => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "illegal_status_transition".to_string(),
+                message: format!("Cannot change status from {:?} to {:?}", e.from, e.to),
+            }),
+        ),
+        StatusUpdateError::Conflict { expected, .. } => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "status_conflict".to_string(),
+                message: format!("Resource is no longer in status {:?}", expected),
+            }),
+        ),
+        StatusUpdateError::VersionConflict { .. } => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "version_conflict".to_string(),
+                message: "Resource was modified by a concurrent request, fetch it again and retry".to_string(),
+            }),
+        ),
+        StatusUpdateError::Database(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to update status".to_string(),
+            }),
+        ),
+    }
+}
+
+/// Convert a failed price fetch into an HTTP error response
+/// Prices halted by the price guard are 503 Service Unavailable until the halt is cleared.
+pub fn price_error_response(err: &(dyn std::error::Error + Send + Sync + 'static)) -> (StatusCode, Json<ErrorResponse>) {
+    match err.downcast_ref::<PriceHalt>() {
+        Some(halt) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "price_feed_halted".to_string(),
+                message: format!("New payments are halted since {}: {}", halt.tripped_at.to_rfc3339(), halt),
+            }),
+        ),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "price_fetch_error".to_string(),
+                message: "Failed to fetch current Bitcoin price".to_string(),
+            }),
+        ),
+    }
+}
+
+/// Convert a failed recipient resolution into an HTTP error response
+pub fn payout_error_response(err: PayoutError) -> (StatusCode, Json<ErrorResponse>) {
+    let (error, message) = match err {
+        PayoutError::MissingPayoutAddress => (
+            "payout_address_missing",
+            "Merchant has no payout address configured",
+        ),
+        PayoutError::UnverifiedPayoutAddress => (
+            "payout_address_unverified",
+            "Merchant payout address has not been verified",
+        ),
+    };
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
+
 /// Convert u128 amount to USD string with 2 decimal places
 /// Example: 2345 -> "23.45", 4 -> "0.04", 100 -> "1.00"
 pub fn format_money_amount(amount: u128) -> String {
@@ -144,6 +1186,7 @@ pub fn format_money_amount(amount: u128) -> String {
     format!("{}.{:02}", dollars, cents)
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn convert_money_from_string(amount: String) -> Result<u128, ()> {
     // Parse the amount string (e.g., "23.45" -> 2345, "121.45454" -> 12145)
     let parts: Vec<&str> = amount.split('.').collect();
@@ -157,6 +1200,10 @@ pub fn convert_money_from_string(amount: String) -> Result<u128, ()> {
             // Has decimal point
             let dollars = parts[0].parse::<u128>().map_err(|_| ())?;
             let decimal_part = parts[1];
+            // Sliced by bytes below
+            if !decimal_part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(());
+            }
             
             // Take only first 2 digits for cents, pad with 0 if needed
             let cents_str = if decimal_part.is_empty() {
@@ -172,4 +1219,64 @@ pub fn convert_money_from_string(amount: String) -> Result<u128, ()> {
         }
         _ => Err(()),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ExportRecord {
+        ExportRecord {
+            invoice_id: "66f0c2a4e1b2c3d4e5f60718".to_string(),
+            merchant_order_id: "order-1".to_string(),
+            invoice_status: InvoiceStatus::Paid,
+            invoice_amount: "10.00".to_string(),
+            settlement_asset: SettlementAsset::USD,
+            invoice_created_at: "2026-01-02T03:04:05Z".parse().unwrap(),
+            invoice_expires_at: None,
+            description: None,
+            buyer_email: None,
+            buyer_reference: None,
+            payment_id: None,
+            payment_status: None,
+            payment_asset: None,
+            payment_amount: None,
+            btc_price: None,
+            tx_id: None,
+            sender_address: None,
+            recipient_address: None,
+            payment_received_at: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_line_quotes_fields() {
+        let record = ExportRecord {
+            description: Some("Mug, \"large\"\nblue".to_string()),
+            ..record()
+        };
+        let line = record.to_csv_line();
+        assert!(line.starts_with("66f0c2a4e1b2c3d4e5f60718,order-1,paid,10.00,"), "{}", line);
+        assert!(line.contains(",2026-01-02T03:04:05+00:00,,\"Mug, \"\"large\"\"\nblue\",,"), "{}", line);
+        assert!(line.ends_with(",,\r\n"));
+        assert_eq!(line.matches(',').count(), ExportRecord::CSV_HEADER.matches(',').count() + 1);
+    }
+
+    #[test]
+    fn test_csv_line_neutralizes_formulas() {
+        for (value, field) in [
+            ("=HYPERLINK(\"http://evil\")", "\"'=HYPERLINK(\"\"http://evil\"\")\""),
+            ("+1", "'+1"),
+            ("-2+3", "'-2+3"),
+            ("@SUM(A1)", "'@SUM(A1)"),
+            ("\t=1", "'\t=1"),
+            ("\r=1", "\"'\r=1\""),
+        ] {
+            let record = ExportRecord { buyer_reference: Some(value.to_string()), ..record() };
+            assert!(record.to_csv_line().contains(&format!(",{},", field)), "{}", record.to_csv_line());
+        }
+
+        let record = ExportRecord { buyer_reference: Some("a=b".to_string()), ..record() };
+        assert!(record.to_csv_line().contains(",a=b,"));
+    }
+}
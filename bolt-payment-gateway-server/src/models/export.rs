@@ -0,0 +1,33 @@
+// src/models/export.rs
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::models::{InvoiceStatus, PaymentStatus, PaymentToken, SettlementAsset};
+
+/// One invoice joined with one of its payments, as read by the export cursor.
+/// Invoices without payments produce a single row with the payment fields unset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportRow {
+    pub invoice_id: bson::oid::ObjectId,
+    pub merchant_order_id: String,
+    pub invoice_status: InvoiceStatus,
+    /// Cents of the settlement asset.
+    pub invoice_amount: i64,
+    pub settlement_asset: SettlementAsset,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+    pub buyer_email: Option<String>,
+    pub buyer_reference: Option<String>,
+    pub payment_id: Option<bson::oid::ObjectId>,
+    pub payment_status: Option<PaymentStatus>,
+    pub payment_asset: Option<PaymentToken>,
+    /// Satoshis.
+    pub payment_amount: Option<i64>,
+    /// USD cents per BTC.
+    pub btc_price: Option<i64>,
+    pub tx_id: Option<String>,
+    pub sender_address: Option<String>,
+    pub recipient_address: Option<String>,
+    pub received_at: Option<DateTime<Utc>>,
+}
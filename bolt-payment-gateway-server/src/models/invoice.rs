@@ -1,10 +1,12 @@
 // src/models/invoice.rs
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Invoice entity — mirrors the OpenAPI schema.
+/// Unknown fields are ignored, so replicas of the previous release still read invoices written by a newer one.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
 pub struct Invoice {
     /// MongoDB ObjectId (optional for new documents)
     #[serde(rename = "_id")]
@@ -31,14 +33,139 @@ pub struct Invoice {
 
     /// Timestamp when the invoice should expire if defined by the merchant
     pub expires_at: Option<DateTime<Utc>>,
+
+    /// Address the customer's transfer must be sent to, resolved from the merchant's
+    /// custody mode when the invoice was created.
+    #[serde(default)]
+    pub recipient_address: Option<String>,
+
+    /// BTC quote currently locked for this invoice. It is refreshed on its own short
+    /// window while the invoice itself stays payable until `expires_at`.
+    #[serde(default)]
+    pub quote: Option<InvoiceQuote>,
+
+    /// Free-text description shown to the customer.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Itemized breakdown; when present the line totals sum to `amount`.
+    #[serde(default)]
+    pub line_items: Vec<LineItem>,
+
+    /// Merchant-defined key/value pairs.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+
+    /// Email address of the buyer.
+    #[serde(default)]
+    pub buyer_email: Option<String>,
+
+    /// Merchant-defined reference of the buyer (customer ID, account number...).
+    #[serde(default)]
+    pub buyer_reference: Option<String>,
+
+    /// Payment link the invoice was opened from.
+    #[serde(default)]
+    pub payment_link_id: Option<bson::oid::ObjectId>,
+
+    /// Subscription the invoice bills a period of.
+    #[serde(default)]
+    pub subscription_id: Option<bson::oid::ObjectId>,
+
+    /// Incremented by every update, which only applies to the version it was read at.
+    /// Invoices stored before versions existed read as version 0.
+    #[serde(default)]
+    pub version: i64,
 }
 
-/// `status`: ["paid", "expired", "settled"]
+/// One line of an itemized invoice, amounts in the invoice's settlement asset (cents).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineItem {
+    pub name: String,
+
+    pub quantity: u32,
+
+    #[serde(with = "u128_as_i64")]
+    pub unit_price: u128,
+
+    /// Tax for the whole line.
+    #[serde(with = "u128_as_i64")]
+    pub tax: u128,
+}
+
+impl LineItem {
+    /// Unit price times quantity plus tax, `None` when it overflows.
+    pub fn total(&self) -> Option<u128> {
+        self.unit_price.checked_mul(self.quantity as u128)?.checked_add(self.tax)
+    }
+}
+
+impl Invoice {
+    /// Whether the invoice deadline has passed at `now`.
+    pub fn is_past_due(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Sum of the line totals, `None` for invoices without line items or when the sum overflows.
+    pub fn line_items_total(&self) -> Option<u128> {
+        if self.line_items.is_empty() {
+            None
+        } else {
+            self.line_items
+                .iter()
+                .try_fold(0u128, |sum, item| sum.checked_add(item.total()?))
+        }
+    }
+
+    /// The locked quote, if there is one still valid at `now`.
+    pub fn live_quote(&self, now: DateTime<Utc>) -> Option<&InvoiceQuote> {
+        self.quote.as_ref().filter(|quote| quote.expires_at > now)
+    }
+
+    /// Memo customers attach to their transfer so it can be traced back to the order.
+    pub fn payment_memo(&self) -> String {
+        format!("BG-MID: {}", self.merchant_order_id)
+    }
+}
+
+/// Changes a merchant may make to an invoice while it is awaiting payment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvoiceAmendment {
+    pub amount: Option<u128>,
+    pub merchant_order_id: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A BTC price locked for an invoice for a short window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvoiceQuote {
+    /// Amount in satoshis the customer has to pay, spread included.
+    #[serde(with = "u128_as_i64")]
+    pub amount: u128,
+
+    /// BTC price in USD cents the quote was calculated with.
+    #[serde(with = "u128_as_i64")]
+    pub btc_price: u128,
+
+    /// Spread added to the amount, per 10000.
+    pub spread_bps: u32,
+
+    /// When the quote was locked.
+    pub locked_at: DateTime<Utc>,
+
+    /// When the quote stops being honored (never after the invoice deadline).
+    pub expires_at: DateTime<Utc>,
+}
+
+/// `status`: ["created", "pending", "paid", "expired", "settled", "cancelled"]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum InvoiceStatus {
     /// Invoice has been created and is awaiting payment.
     Created,
+    /// A payment has been accepted and its transaction is being broadcast.
+    /// The invoice can no longer be cancelled or amended.
+    Pending,
     /// Customer has successfully completed the payment.
     Paid,
     /// Invoice has exceeded its validity period without receiving payment.
@@ -47,9 +174,12 @@ pub enum InvoiceStatus {
     /// Payment has been processed and funds have been transferred to the merchant.
     /// This is the final successful state of an invoice.
     Settled,
+    /// Invoice was cancelled by the merchant before being paid.
+    Cancelled,
 }
 
 /// `settlement_asset`: ["USD", "BRL"]
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SettlementAsset {
     USD,
@@ -57,7 +187,7 @@ pub enum SettlementAsset {
 }
 
 // Custom serialization/deserialization for u128 as i64 for MongoDB compatibility
-mod u128_as_i64 {
+pub(crate) mod u128_as_i64 {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
     pub fn serialize<S>(value: &u128, serializer: S) -> Result<S::Ok, S::Error>
@@ -79,4 +209,70 @@ mod u128_as_i64 {
         }
         Ok(i64_value as u128)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice_expiring_at(expires_at: DateTime<Utc>) -> Invoice {
+        Invoice {
+            id: bson::oid::ObjectId::new(),
+            wallet_address: "ST1TEST".to_string(),
+            status: InvoiceStatus::Created,
+            amount: 4990,
+            settlement_asset: SettlementAsset::USD,
+            merchant_order_id: "ORD-1".to_string(),
+            created_at: Utc::now(),
+            expires_at: Some(expires_at),
+            recipient_address: None,
+            quote: None,
+            description: None,
+            line_items: Vec::new(),
+            metadata: BTreeMap::new(),
+            buyer_email: None,
+            buyer_reference: None,
+            payment_link_id: None,
+            subscription_id: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_quote_window_is_independent_of_deadline() {
+        let now = Utc::now();
+        let mut invoice = invoice_expiring_at(now + chrono::Duration::days(7));
+        invoice.quote = Some(InvoiceQuote {
+            amount: 5000,
+            btc_price: 10_000_000,
+            spread_bps: 100,
+            locked_at: now - chrono::Duration::minutes(3),
+            expires_at: now - chrono::Duration::minutes(1),
+        });
+
+        assert!(!invoice.is_past_due(now));
+        assert!(invoice.live_quote(now).is_none());
+        assert!(invoice.live_quote(now - chrono::Duration::minutes(2)).is_some());
+        assert!(invoice.is_past_due(now + chrono::Duration::days(8)));
+    }
+
+    #[test]
+    fn test_line_items_total_includes_tax() {
+        let mut invoice = invoice_expiring_at(Utc::now());
+        assert_eq!(invoice.line_items_total(), None);
+
+        invoice.line_items = vec![
+            LineItem { name: "Coffee".to_string(), quantity: 2, unit_price: 1500, tax: 120 },
+            LineItem { name: "Cake".to_string(), quantity: 1, unit_price: 1600, tax: 50 },
+        ];
+        assert_eq!(invoice.line_items_total(), Some(4770));
+
+        invoice.line_items[1].unit_price = u128::MAX / 2;
+        invoice.line_items[1].quantity = 3;
+        assert_eq!(invoice.line_items[1].total(), None);
+        invoice.line_items[1].unit_price = u128::MAX - 1000;
+        invoice.line_items[1].quantity = 1;
+        invoice.line_items[1].tax = 0;
+        assert!(invoice.line_items[1].total().is_some());
+        assert_eq!(invoice.line_items_total(), None);
+    }
+}
@@ -0,0 +1,172 @@
+// src/models/merchant.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{PaymentToken, SettlementAsset};
+
+/// Invoice lifetime used when a merchant has not configured one (2 minutes).
+pub const DEFAULT_INVOICE_EXPIRY_SECS: i64 = 120;
+
+/// Shortest invoice lifetime a merchant accepts by default (1 minute).
+pub const DEFAULT_MIN_INVOICE_EXPIRY_SECS: i64 = 60;
+
+/// Longest invoice lifetime a merchant accepts by default (30 days).
+pub const DEFAULT_MAX_INVOICE_EXPIRY_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// How long a locked BTC quote stays valid by default (2 minutes).
+pub const DEFAULT_QUOTE_WINDOW_SECS: i64 = 120;
+
+/// Spread added to quotes shown to customers, per 10000 (1.00%).
+pub const DEFAULT_QUOTE_SPREAD_BPS: u32 = 100;
+
+/// Minimal spread a submitted payment must cover, per 10000 (0.50%).
+pub const DEFAULT_PAYMENT_SPREAD_BPS: u32 = 50;
+
+/// Merchant profile and per-merchant settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Merchant {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    /// Wallet address identifying the merchant (unique).
+    pub wallet_address: String,
+
+    /// Name shown to customers at checkout.
+    pub display_name: String,
+
+    /// Address that should receive the merchant's funds.
+    pub payout_address: Option<String>,
+
+    /// Whether the payout address has been verified as belonging to the merchant.
+    #[serde(default)]
+    pub payout_address_verified: bool,
+
+    /// Whether customers pay the merchant directly or through the gateway treasury.
+    #[serde(default)]
+    pub custody_mode: CustodyMode,
+
+    /// Settlement asset used when an invoice does not specify one.
+    pub default_settlement_asset: SettlementAsset,
+
+    /// Invoice lifetime in seconds used when an invoice does not specify one.
+    pub default_invoice_expiry_secs: i64,
+
+    /// Shortest invoice lifetime in seconds an invoice may request.
+    #[serde(default = "default_min_invoice_expiry_secs")]
+    pub min_invoice_expiry_secs: i64,
+
+    /// Longest invoice lifetime in seconds an invoice may request.
+    #[serde(default = "default_max_invoice_expiry_secs")]
+    pub max_invoice_expiry_secs: i64,
+
+    /// How long a BTC quote locked for one of the merchant's invoices stays valid.
+    #[serde(default = "default_quote_window_secs")]
+    pub quote_window_secs: i64,
+
+    /// Tokens customers may pay this merchant's invoices with.
+    pub allowed_payment_tokens: Vec<PaymentToken>,
+
+    /// Spreads applied to quotes and payments.
+    pub fee_schedule: FeeSchedule,
+
+    /// URL notified about invoice events.
+    pub webhook_url: Option<String>,
+
+    /// Checkout branding.
+    pub branding: Branding,
+
+    pub created_at: DateTime<Utc>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `custody_mode`: ["custodial", "self_custody"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustodyMode {
+    /// Payments go to the gateway treasury, which settles with the merchant.
+    #[default]
+    Custodial,
+    /// Payments go straight to the merchant's verified payout address.
+    SelfCustody,
+}
+
+/// Spreads in basis points (per 10000).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Spread added to the satoshi amount quoted to customers.
+    pub quote_spread_bps: u32,
+    /// Minimal spread a submitted payment must cover to be accepted.
+    pub payment_spread_bps: u32,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            quote_spread_bps: DEFAULT_QUOTE_SPREAD_BPS,
+            payment_spread_bps: DEFAULT_PAYMENT_SPREAD_BPS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Branding {
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+}
+
+impl Merchant {
+    /// Profile with the gateway defaults, used for merchants that never created one.
+    pub fn with_defaults(wallet_address: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: bson::oid::ObjectId::new(),
+            wallet_address: wallet_address.to_string(),
+            display_name: wallet_address.to_string(),
+            payout_address: None,
+            payout_address_verified: false,
+            custody_mode: CustodyMode::default(),
+            default_settlement_asset: SettlementAsset::USD,
+            default_invoice_expiry_secs: DEFAULT_INVOICE_EXPIRY_SECS,
+            min_invoice_expiry_secs: DEFAULT_MIN_INVOICE_EXPIRY_SECS,
+            max_invoice_expiry_secs: DEFAULT_MAX_INVOICE_EXPIRY_SECS,
+            quote_window_secs: DEFAULT_QUOTE_WINDOW_SECS,
+            allowed_payment_tokens: vec![PaymentToken::SBTC],
+            fee_schedule: FeeSchedule::default(),
+            webhook_url: None,
+            branding: Branding::default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn accepts_token(&self, token: PaymentToken) -> bool {
+        self.allowed_payment_tokens.contains(&token)
+    }
+
+    /// Whether an invoice lifetime in seconds is within the merchant's bounds.
+    pub fn accepts_invoice_lifetime(&self, secs: i64) -> bool {
+        secs >= self.min_invoice_expiry_secs && secs <= self.max_invoice_expiry_secs
+    }
+
+    /// Change the payout address. Only the merchant's own wallet counts as verified
+    /// right away, any other address has to be verified by an operator
+    /// (`bolt-admin merchant verify-payout`).
+    pub fn set_payout_address(&mut self, payout_address: Option<String>) {
+        self.payout_address_verified =
+            payout_address.as_deref() == Some(self.wallet_address.as_str());
+        self.payout_address = payout_address;
+    }
+}
+
+fn default_min_invoice_expiry_secs() -> i64 {
+    DEFAULT_MIN_INVOICE_EXPIRY_SECS
+}
+
+fn default_max_invoice_expiry_secs() -> i64 {
+    DEFAULT_MAX_INVOICE_EXPIRY_SECS
+}
+
+fn default_quote_window_secs() -> i64 {
+    DEFAULT_QUOTE_WINDOW_SECS
+}
@@ -1,8 +1,26 @@
 // src/models/mod.rs
+pub mod analytics;
+pub mod audit_event;
+pub mod domain_event;
+pub mod export;
 pub mod invoice;
+pub mod merchant;
 pub mod payment;
+pub mod payment_link;
+pub mod price;
+pub mod subscription;
 pub mod dto;
+pub mod state_machine;
 
+pub use analytics::*;
+pub use audit_event::*;
+pub use domain_event::*;
+pub use export::*;
 pub use invoice::*;
+pub use merchant::*;
 pub use payment::*;
+pub use payment_link::*;
+pub use price::*;
+pub use subscription::*;
 pub use dto::*;
+pub use state_machine::*;
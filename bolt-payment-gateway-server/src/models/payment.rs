@@ -3,8 +3,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Payment entity — mirrors the OpenAPI schema.
+/// Unknown fields are ignored, so replicas of the previous release still read payments written by a newer one.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
 pub struct Payment {
     /// Unique identifier of the payment attempt.
     #[serde(rename = "_id")]
@@ -31,6 +31,24 @@ pub struct Payment {
 
     /// Transaction ID/hash on the underlying network.
     pub tx_id: Option<String>,
+
+    /// Address the transfer was required to be sent to.
+    #[serde(default)]
+    pub recipient_address: Option<String>,
+
+    /// Merchant owning the invoice, copied so payments can be listed per merchant.
+    /// Backfilled from the invoices for payments stored before it existed.
+    #[serde(default)]
+    pub wallet_address: Option<String>,
+
+    /// BTC price in USD cents the payment was validated against.
+    #[serde(default, with = "option_u128_as_i64")]
+    pub btc_price: Option<u128>,
+
+    /// Incremented by every update, which only applies to the version it was read at.
+    /// Payments stored before versions existed read as version 0.
+    #[serde(default)]
+    pub version: i64,
 }
 
 impl Payment {
@@ -44,6 +62,10 @@ impl Payment {
             sender_address: None,
             received_at: Utc::now(),
             tx_id: None,
+            recipient_address: None,
+            wallet_address: None,
+            btc_price: None,
+            version: 0,
         }
     }
 }
@@ -60,7 +82,8 @@ pub enum PaymentStatus {
     Confirmed,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum PaymentToken {
     #[serde(rename = "sBTC")]
     SBTC,
@@ -68,6 +91,13 @@ pub enum PaymentToken {
     USDT,
 }
 
+impl PaymentToken {
+    /// Whether payments in the token can be priced, only satoshi amounts can for now.
+    pub fn is_priced(self) -> bool {
+        self == PaymentToken::SBTC
+    }
+}
+
 // Custom serialization/deserialization for u128 as i64 for MongoDB compatibility
 mod u128_as_i64 {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -91,4 +121,30 @@ mod u128_as_i64 {
         }
         Ok(i64_value as u128)
     }
-}
\ No newline at end of file
+}
+mod option_u128_as_i64 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(value: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let i64_value = value
+            .map(i64::try_from)
+            .transpose()
+            .map_err(|_| serde::ser::Error::custom("Value too large for i64"))?;
+        i64_value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u128>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<i64>::deserialize(deserializer)? {
+            Some(i64_value) if i64_value < 0 => {
+                Err(serde::de::Error::custom("Negative values not allowed"))
+            }
+            i64_value => Ok(i64_value.map(|v| v as u128)),
+        }
+    }
+}
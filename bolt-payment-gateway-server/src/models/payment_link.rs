@@ -0,0 +1,97 @@
+// src/models/payment_link.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::invoice::u128_as_i64;
+use crate::models::SettlementAsset;
+
+/// Reusable link a merchant shares with customers, each opening mints a fresh invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentLink {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    /// Wallet address of the merchant owning the link.
+    pub wallet_address: String,
+
+    pub title: String,
+
+    /// Copied to the description of the invoices opened from the link.
+    pub description: Option<String>,
+
+    pub amount: LinkAmount,
+
+    pub settlement_asset: SettlementAsset,
+
+    /// How many invoices may be opened from the link, unlimited when `None`.
+    pub usage_limit: Option<u32>,
+
+    /// How many invoices have been opened from the link.
+    pub usage_count: u32,
+
+    /// Inactive links cannot be opened.
+    pub active: bool,
+
+    pub created_at: DateTime<Utc>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `amount`: a fixed amount or bounds the customer picks an amount within, in cents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkAmount {
+    Fixed {
+        #[serde(with = "u128_as_i64")]
+        amount: u128,
+    },
+    CustomerChosen {
+        #[serde(with = "u128_as_i64")]
+        min_amount: u128,
+        #[serde(with = "u128_as_i64")]
+        max_amount: u128,
+    },
+}
+
+impl LinkAmount {
+    /// Amount of an invoice opened with the amount the customer chose, if any.
+    /// `None` when a fixed link gets a chosen amount, or a chosen amount is missing or out of bounds.
+    pub fn resolve(&self, chosen: Option<u128>) -> Option<u128> {
+        match (*self, chosen) {
+            (LinkAmount::Fixed { amount }, None) => Some(amount),
+            (LinkAmount::Fixed { .. }, Some(_)) => None,
+            (LinkAmount::CustomerChosen { min_amount, max_amount }, Some(chosen))
+                if chosen >= min_amount && chosen <= max_amount =>
+            {
+                Some(chosen)
+            }
+            (LinkAmount::CustomerChosen { .. }, _) => None,
+        }
+    }
+}
+
+impl PaymentLink {
+    /// Whether another invoice may be opened from the link.
+    pub fn is_available(&self) -> bool {
+        self.active && self.usage_limit.is_none_or(|limit| self.usage_count < limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_amount_resolution() {
+        let fixed = LinkAmount::Fixed { amount: 1500 };
+        assert_eq!(fixed.resolve(None), Some(1500));
+        assert_eq!(fixed.resolve(Some(1500)), None);
+
+        let chosen = LinkAmount::CustomerChosen { min_amount: 500, max_amount: 10_000 };
+        assert_eq!(chosen.resolve(Some(500)), Some(500));
+        assert_eq!(chosen.resolve(Some(10_000)), Some(10_000));
+        assert_eq!(chosen.resolve(Some(499)), None);
+        assert_eq!(chosen.resolve(Some(10_001)), None);
+        assert_eq!(chosen.resolve(None), None);
+    }
+}
@@ -0,0 +1,196 @@
+// src/models/price.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::format_money_amount;
+use crate::models::invoice::u128_as_i64;
+
+/// Trading pair prices are quoted for
+pub const BTC_USD_PAIR: &str = "BTCUSD";
+
+/// Latest price of a pair shared by the replicas, so they all quote from the same tick.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceSnapshot {
+    #[serde(rename = "_id")]
+    pub pair: String,
+
+    /// Price in USD cents.
+    #[serde(with = "u128_as_i64")]
+    pub price: u128,
+
+    /// Where the price was fetched from, e.g. `binance`.
+    pub source: String,
+
+    /// Stored as a BSON date so the snapshot's age can be compared by the server.
+    pub fetched_at: bson::DateTime,
+}
+
+/// A price fetched from a source, kept for `PRICE_HISTORY_RETENTION` to tell what price was in effect when.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceTick {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    pub pair: String,
+
+    /// Price in USD cents.
+    #[serde(with = "u128_as_i64")]
+    pub price: u128,
+
+    pub source: String,
+
+    /// Stored as a BSON date, the TTL index removes ticks by it.
+    pub fetched_at: bson::DateTime,
+}
+
+impl PriceTick {
+    pub fn new(pair: &str, price: u128, source: &str) -> Self {
+        Self {
+            id: bson::oid::ObjectId::new(),
+            pair: pair.to_string(),
+            price,
+            source: source.to_string(),
+            fetched_at: bson::DateTime::now(),
+        }
+    }
+}
+
+/// `interval`: ["1m", "5m", "15m", "1h", "4h", "1d"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[default]
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "4h")]
+    FourHours,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl PriceInterval {
+    /// Unit and bin size understood by `$dateTrunc`.
+    pub fn date_trunc(&self) -> (&'static str, i32) {
+        match self {
+            PriceInterval::OneMinute => ("minute", 1),
+            PriceInterval::FiveMinutes => ("minute", 5),
+            PriceInterval::FifteenMinutes => ("minute", 15),
+            PriceInterval::OneHour => ("hour", 1),
+            PriceInterval::FourHours => ("hour", 4),
+            PriceInterval::OneDay => ("day", 1),
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            PriceInterval::OneMinute => chrono::Duration::minutes(1),
+            PriceInterval::FiveMinutes => chrono::Duration::minutes(5),
+            PriceInterval::FifteenMinutes => chrono::Duration::minutes(15),
+            PriceInterval::OneHour => chrono::Duration::hours(1),
+            PriceInterval::FourHours => chrono::Duration::hours(4),
+            PriceInterval::OneDay => chrono::Duration::days(1),
+        }
+    }
+}
+
+/// Open, high, low and close of the ticks of one interval.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PriceCandle {
+    /// Start of the interval.
+    #[serde(rename = "_id")]
+    pub open_time: bson::DateTime,
+    #[serde(with = "u128_as_i64")]
+    pub open: u128,
+    #[serde(with = "u128_as_i64")]
+    pub high: u128,
+    #[serde(with = "u128_as_i64")]
+    pub low: u128,
+    #[serde(with = "u128_as_i64")]
+    pub close: u128,
+    /// Number of ticks in the interval.
+    pub ticks: i64,
+}
+
+/// `check`: ["bounds", "tick_deviation", "cross_source", "malformed"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceCheck {
+    /// Price outside the plausible range.
+    Bounds,
+    /// Price moved too far from the previous tick.
+    TickDeviation,
+    /// Price too far from the secondary source's.
+    CrossSource,
+    /// Price the source answered that is not a number.
+    Malformed,
+}
+
+impl PriceCheck {
+    pub const ALL: [PriceCheck; 4] =
+        [PriceCheck::Bounds, PriceCheck::TickDeviation, PriceCheck::CrossSource, PriceCheck::Malformed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceCheck::Bounds => "bounds",
+            PriceCheck::TickDeviation => "tick_deviation",
+            PriceCheck::CrossSource => "cross_source",
+            PriceCheck::Malformed => "malformed",
+        }
+    }
+}
+
+/// A fetched price that failed a sanity check, new payments are halted until it is cleared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceHalt {
+    pub check: PriceCheck,
+
+    /// Rejected price in USD cents, 0 when it was malformed.
+    #[serde(with = "u128_as_i64")]
+    pub price: u128,
+
+    /// Price it was checked against in USD cents: the bound crossed, the previous tick
+    /// or the secondary source's price.
+    #[serde(with = "u128_as_i64")]
+    pub reference: u128,
+
+    pub tripped_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for PriceHalt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.check == PriceCheck::Malformed {
+            return write!(f, "BTC price failed the {} check", self.check.name());
+        }
+        write!(
+            f,
+            "BTC price {} failed the {} check against {}",
+            format_money_amount(self.price),
+            self.check.name(),
+            format_money_amount(self.reference)
+        )
+    }
+}
+
+impl std::error::Error for PriceHalt {}
+
+/// Whether new payments are halted on a pair's price, one document per pair shared by the replicas.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceGuardState {
+    #[serde(rename = "_id")]
+    pub pair: String,
+
+    /// Set by a failed check until the feed recovers or an operator resumes payments.
+    pub halt: Option<PriceHalt>,
+
+    /// Operator who last resumed payments, `recovery` when the feed recovered on its own.
+    pub resumed_by: Option<String>,
+
+    pub resume_reason: Option<String>,
+
+    pub resumed_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,111 @@
+// src/models/state_machine.rs
+use std::fmt;
+
+use crate::models::{InvoiceStatus, PaymentStatus};
+
+/// A status enum whose values may only change along a fixed set of edges.
+pub trait StateMachine: Copy + PartialEq + fmt::Debug {
+    /// Whether moving from `self` to `next` is a legal transition.
+    fn can_transition_to(&self, next: Self) -> bool;
+
+    /// Validate the transition from `self` to `next`, returning `next` on success.
+    fn transition_to(self, next: Self) -> Result<Self, IllegalTransition<Self>> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(IllegalTransition { from: self, to: next })
+        }
+    }
+}
+
+/// Returned when a status change is not an edge of the state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalTransition<S> {
+    pub from: S,
+    pub to: S,
+}
+
+impl<S: fmt::Debug> fmt::Display for IllegalTransition<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal status transition from {:?} to {:?}", self.from, self.to)
+    }
+}
+
+impl<S: fmt::Debug> std::error::Error for IllegalTransition<S> {}
+
+/// Invoice lifecycle:
+///
+/// ```text
+/// Created ──► Pending ──► Paid ──► Settled
+///  │  │  ▲       │
+///  │  │  └───────┘ (broadcast failed)
+///  │  └─────────► Expired
+///  └────────────► Cancelled
+/// ```
+impl StateMachine for InvoiceStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        use InvoiceStatus::*;
+        matches!(
+            (self, next),
+            (Created, Pending)
+                | (Created, Expired)
+                | (Created, Cancelled)
+                | (Pending, Paid)
+                | (Pending, Created)
+                | (Paid, Settled)
+        )
+    }
+}
+
+/// Payment lifecycle:
+///
+/// ```text
+/// Accepted ──► Confirmed
+///    │
+///    └───────► Rejected
+/// ```
+impl StateMachine for PaymentStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        use PaymentStatus::*;
+        matches!((self, next), (Accepted, Confirmed) | (Accepted, Rejected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoice_transitions() {
+        assert!(InvoiceStatus::Created.can_transition_to(InvoiceStatus::Pending));
+        assert!(InvoiceStatus::Pending.can_transition_to(InvoiceStatus::Paid));
+        assert!(InvoiceStatus::Pending.can_transition_to(InvoiceStatus::Created));
+        assert!(InvoiceStatus::Created.can_transition_to(InvoiceStatus::Expired));
+        assert!(InvoiceStatus::Created.can_transition_to(InvoiceStatus::Cancelled));
+        assert!(InvoiceStatus::Paid.can_transition_to(InvoiceStatus::Settled));
+
+        assert!(!InvoiceStatus::Created.can_transition_to(InvoiceStatus::Paid));
+        assert!(!InvoiceStatus::Pending.can_transition_to(InvoiceStatus::Cancelled));
+        assert!(!InvoiceStatus::Cancelled.can_transition_to(InvoiceStatus::Created));
+
+        assert!(!InvoiceStatus::Settled.can_transition_to(InvoiceStatus::Created));
+        assert!(!InvoiceStatus::Expired.can_transition_to(InvoiceStatus::Paid));
+        assert!(!InvoiceStatus::Paid.can_transition_to(InvoiceStatus::Created));
+        assert!(!InvoiceStatus::Created.can_transition_to(InvoiceStatus::Created));
+    }
+
+    #[test]
+    fn test_payment_transitions() {
+        assert!(PaymentStatus::Accepted.can_transition_to(PaymentStatus::Confirmed));
+        assert!(PaymentStatus::Accepted.can_transition_to(PaymentStatus::Rejected));
+
+        assert_eq!(
+            PaymentStatus::Rejected.transition_to(PaymentStatus::Confirmed),
+            Err(IllegalTransition {
+                from: PaymentStatus::Rejected,
+                to: PaymentStatus::Confirmed
+            })
+        );
+        assert!(!PaymentStatus::Confirmed.can_transition_to(PaymentStatus::Rejected));
+    }
+}
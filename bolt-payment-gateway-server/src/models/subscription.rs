@@ -0,0 +1,276 @@
+// src/models/subscription.rs
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::invoice::u128_as_i64;
+use crate::models::SettlementAsset;
+
+/// Unpaid cycles in a row after which a subscription is cancelled by default.
+pub const DEFAULT_MAX_UNPAID_CYCLES: u32 = 3;
+
+/// What a merchant bills every period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionPlan {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    /// Wallet address of the merchant owning the plan.
+    pub wallet_address: String,
+
+    pub name: String,
+
+    /// Copied to the description of the invoices created for the plan.
+    pub description: Option<String>,
+
+    /// Amount billed every period, in cents.
+    #[serde(with = "u128_as_i64")]
+    pub amount: u128,
+
+    pub settlement_asset: SettlementAsset,
+
+    pub interval: BillingInterval,
+
+    /// Number of intervals in a period, 3 months for a quarterly plan.
+    pub interval_count: u32,
+
+    /// How long customers have to pay each period's invoice, defaults to the merchant's invoice expiry.
+    pub payment_window_secs: Option<i64>,
+
+    /// Subscriptions are cancelled once this many cycles in a row went unpaid.
+    pub max_unpaid_cycles: u32,
+
+    /// Customers cannot subscribe to inactive plans, existing subscriptions keep running.
+    pub active: bool,
+
+    pub created_at: DateTime<Utc>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `interval`: ["day", "week", "month", "year"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BillingInterval {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl BillingInterval {
+    /// Start of the period following the one starting at `from`.
+    /// Months and years keep the day of month, clamped to the end of shorter months.
+    pub fn advance(&self, from: DateTime<Utc>, count: u32) -> Option<DateTime<Utc>> {
+        match self {
+            BillingInterval::Day => from.checked_add_signed(Duration::days(count.into())),
+            BillingInterval::Week => from.checked_add_signed(Duration::weeks(count.into())),
+            BillingInterval::Month => from.checked_add_months(Months::new(count)),
+            BillingInterval::Year => from.checked_add_months(Months::new(count.checked_mul(12)?)),
+        }
+    }
+}
+
+/// A customer subscribed to a plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+
+    pub plan_id: bson::oid::ObjectId,
+
+    /// Wallet address of the merchant owning the plan.
+    pub wallet_address: String,
+
+    /// Address notified about the customer's invoices.
+    pub customer_email: Option<String>,
+
+    /// Merchant-defined reference of the customer, copied to the invoices.
+    pub customer_reference: Option<String>,
+
+    pub status: SubscriptionStatus,
+
+    /// When the next period starts and its invoice is created.
+    pub next_billing_at: DateTime<Utc>,
+
+    /// Cycles in a row whose invoice went unpaid.
+    pub unpaid_cycles: u32,
+
+    /// Every billed period, oldest first.
+    pub cycles: Vec<BillingCycle>,
+
+    pub cancel_reason: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `status`: ["active", "past_due", "cancelled"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    /// Every billed cycle so far is paid or still open.
+    Active,
+    /// The last closed cycle went unpaid, billing continues until the unpaid limit.
+    PastDue,
+    /// No more invoices are created.
+    Cancelled,
+}
+
+/// One billed period of a subscription and its invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BillingCycle {
+    /// 1 for the first period.
+    pub number: u32,
+
+    pub invoice_id: bson::oid::ObjectId,
+
+    pub period_start: DateTime<Utc>,
+
+    pub period_end: DateTime<Utc>,
+
+    pub status: CycleStatus,
+}
+
+/// `status`: ["open", "paid", "overdue"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleStatus {
+    /// The invoice is awaiting payment.
+    Open,
+    Paid,
+    /// The invoice expired or was cancelled without being paid.
+    Overdue,
+}
+
+impl Subscription {
+    /// Record the outcome of an open cycle and update the unpaid streak and status.
+    /// Returns false if the cycle is unknown or already closed.
+    pub fn close_cycle(&mut self, number: u32, outcome: CycleStatus) -> bool {
+        let Some(cycle) = self
+            .cycles
+            .iter_mut()
+            .find(|cycle| cycle.number == number && cycle.status == CycleStatus::Open)
+        else {
+            return false;
+        };
+        cycle.status = outcome;
+
+        if self.status == SubscriptionStatus::Cancelled {
+            return true;
+        }
+        match outcome {
+            CycleStatus::Paid => {
+                self.unpaid_cycles = 0;
+                self.status = SubscriptionStatus::Active;
+            }
+            CycleStatus::Overdue => {
+                self.unpaid_cycles += 1;
+                self.status = SubscriptionStatus::PastDue;
+            }
+            CycleStatus::Open => {}
+        }
+        true
+    }
+
+    pub fn cancel(&mut self, reason: impl Into<String>) {
+        self.status = SubscriptionStatus::Cancelled;
+        self.cancel_reason = Some(reason.into());
+    }
+}
+
+/// Body of the webhooks sent about subscriptions.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionNotice {
+    pub event: SubscriptionNoticeKind,
+    pub subscription_id: String,
+    pub plan_id: String,
+    pub wallet_address: String,
+    pub status: SubscriptionStatus,
+    pub cycle: Option<u32>,
+    pub invoice_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionNoticeKind {
+    InvoiceCreated,
+    CyclePaid,
+    CycleOverdue,
+    SubscriptionCancelled,
+}
+
+impl SubscriptionNotice {
+    pub fn new(kind: SubscriptionNoticeKind, subscription: &Subscription, cycle: Option<&BillingCycle>) -> Self {
+        Self {
+            event: kind,
+            subscription_id: subscription.id.to_string(),
+            plan_id: subscription.plan_id.to_string(),
+            wallet_address: subscription.wallet_address.clone(),
+            status: subscription.status,
+            cycle: cycle.map(|cycle| cycle.number),
+            invoice_id: cycle.map(|cycle| cycle.invoice_id.to_string()),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_monthly_interval_clamps_to_month_end() {
+        let jan_31 = Utc.with_ymd_and_hms(2025, 1, 31, 9, 0, 0).unwrap();
+        assert_eq!(
+            BillingInterval::Month.advance(jan_31, 1),
+            Some(Utc.with_ymd_and_hms(2025, 2, 28, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            BillingInterval::Year.advance(jan_31, 1),
+            Some(Utc.with_ymd_and_hms(2026, 1, 31, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            BillingInterval::Week.advance(jan_31, 2),
+            Some(Utc.with_ymd_and_hms(2025, 2, 14, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_unpaid_streak_resets_on_payment() {
+        let now = Utc::now();
+        let cycle = |number| BillingCycle {
+            number,
+            invoice_id: bson::oid::ObjectId::new(),
+            period_start: now,
+            period_end: now,
+            status: CycleStatus::Open,
+        };
+        let mut subscription = Subscription {
+            id: bson::oid::ObjectId::new(),
+            plan_id: bson::oid::ObjectId::new(),
+            wallet_address: "ST1TEST".to_string(),
+            customer_email: None,
+            customer_reference: None,
+            status: SubscriptionStatus::Active,
+            next_billing_at: now,
+            unpaid_cycles: 0,
+            cycles: vec![cycle(1), cycle(2), cycle(3)],
+            cancel_reason: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        assert!(subscription.close_cycle(1, CycleStatus::Overdue));
+        assert!(subscription.close_cycle(2, CycleStatus::Overdue));
+        assert_eq!(subscription.unpaid_cycles, 2);
+        assert_eq!(subscription.status, SubscriptionStatus::PastDue);
+        assert!(!subscription.close_cycle(2, CycleStatus::Paid));
+
+        assert!(subscription.close_cycle(3, CycleStatus::Paid));
+        assert_eq!(subscription.unpaid_cycles, 0);
+        assert_eq!(subscription.status, SubscriptionStatus::Active);
+    }
+}
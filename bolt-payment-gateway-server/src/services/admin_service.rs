@@ -0,0 +1,295 @@
+// src/services/admin_service.rs
+use anyhow::{anyhow, bail, Result};
+use bson::oid::ObjectId;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::models::{
+    Actor, AdminAction, AuditContext, AuditEvent, Invoice, InvoiceStatus, Merchant, Payment,
+    PaymentLink, PaymentStatus, PriceGuardState, PriceHalt, Subscription, SubscriptionPlan, BTC_USD_PAIR,
+};
+use crate::AppState;
+
+/// Payments still `Accepted` after this long are considered stuck, the broadcast
+/// that would have confirmed or rejected them is long over
+pub const STUCK_PAYMENT_AGE: Duration = Duration::minutes(10);
+
+/// An invoice with its payments and audit trail.
+#[derive(Debug, Serialize)]
+pub struct InvoiceDetails {
+    pub invoice: Invoice,
+    pub payments: Vec<Payment>,
+    pub timeline: Vec<AuditEvent>,
+}
+
+/// Everything stored for a merchant.
+#[derive(Debug, Serialize)]
+pub struct MerchantExport {
+    pub wallet_address: String,
+    /// `None` when the merchant never changed the default settings.
+    pub merchant: Option<Merchant>,
+    pub invoices: Vec<Invoice>,
+    pub payments: Vec<Payment>,
+    pub payment_links: Vec<PaymentLink>,
+    pub subscription_plans: Vec<SubscriptionPlan>,
+    pub subscriptions: Vec<Subscription>,
+    pub exported_at: DateTime<Utc>,
+}
+
+/// Operations behind the `bolt-admin` binary, changes are audited as done by `operator`.
+#[derive(Clone)]
+pub struct AdminService {
+    app_state: AppState,
+    operator: String,
+}
+
+impl AdminService {
+    pub fn new(app_state: AppState, operator: impl Into<String>) -> Self {
+        Self { app_state, operator: operator.into() }
+    }
+
+    fn context(&self, reason: Option<&str>) -> AuditContext {
+        let context = AuditContext::new(Actor::Admin { operator: self.operator.clone() }, None);
+        match reason {
+            Some(reason) => context.with_reason(reason),
+            None => context,
+        }
+    }
+
+    /// Record a command in the admin audit log, `error` is `None` when it succeeded
+    pub async fn record(
+        &self,
+        command: &str,
+        target: Option<&str>,
+        reason: Option<&str>,
+        error: Option<String>,
+    ) -> Result<()> {
+        let action = AdminAction {
+            id: ObjectId::new(),
+            operator: self.operator.clone(),
+            command: command.to_string(),
+            target: target.map(str::to_string),
+            reason: reason.map(str::to_string),
+            error,
+            timestamp: Utc::now(),
+        };
+        self.app_state.audit_repository.record_admin_action(&action).await
+    }
+
+    pub async fn list_invoices(&self, wallet_address: &str, status: Option<InvoiceStatus>) -> Result<Vec<Invoice>> {
+        let mut invoices = self.app_state.invoice_repository.find_by_merchant(wallet_address).await?;
+        if let Some(status) = status {
+            invoices.retain(|invoice| invoice.status == status);
+        }
+        Ok(invoices)
+    }
+
+    pub async fn invoice(&self, invoice_id: &ObjectId) -> Result<InvoiceDetails> {
+        let invoice = self.find_invoice(invoice_id).await?;
+        let payments = self.app_state.payment_repository.find_by_invoice_id(invoice_id).await?;
+        let timeline = self.app_state.audit_repository.find_by_invoice_id(invoice_id).await?;
+        Ok(InvoiceDetails { invoice, payments, timeline })
+    }
+
+    pub async fn list_payments(&self, status: PaymentStatus) -> Result<Vec<Payment>> {
+        self.app_state.payment_repository.find_by_status(status).await
+    }
+
+    pub async fn payment(&self, payment_id: &ObjectId) -> Result<Payment> {
+        self.app_state
+            .payment_repository
+            .find_by_id(payment_id)
+            .await?
+            .ok_or_else(|| anyhow!("Payment {} not found", payment_id))
+    }
+
+    /// Expire an invoice awaiting payment, before its deadline
+    pub async fn expire_invoice(&self, invoice_id: &ObjectId, reason: Option<&str>) -> Result<Invoice> {
+        self.close_invoice(invoice_id, InvoiceStatus::Expired, reason).await
+    }
+
+    pub async fn cancel_invoice(&self, invoice_id: &ObjectId, reason: Option<&str>) -> Result<Invoice> {
+        self.close_invoice(invoice_id, InvoiceStatus::Cancelled, reason).await
+    }
+
+    async fn close_invoice(&self, invoice_id: &ObjectId, to: InvoiceStatus, reason: Option<&str>) -> Result<Invoice> {
+        let invoice = self.find_invoice(invoice_id).await?;
+        if invoice.status == InvoiceStatus::Pending {
+            bail!(
+                "Invoice {} has a payment in progress, reject the stuck payment first",
+                invoice_id
+            );
+        }
+
+        self.app_state
+            .invoice_repository
+            .update_status(&invoice, to, &self.context(reason))
+            .await
+            .map_err(|e| anyhow!("Cannot move invoice {} to {:?}: {}", invoice_id, to, e))
+    }
+
+    /// Reject a payment stuck in `Accepted` and give its invoice back to `Created` so it can be paid again.
+    /// Payments younger than `STUCK_PAYMENT_AGE` may still be broadcasting and are only rejected with `force`.
+    pub async fn reject_payment(&self, payment_id: &ObjectId, reason: Option<&str>, force: bool) -> Result<Payment> {
+        let payment = self.payment(payment_id).await?;
+        if payment.status != PaymentStatus::Accepted {
+            bail!("Payment {} is {:?}, only accepted payments can be rejected", payment_id, payment.status);
+        }
+        let age = Utc::now() - payment.received_at;
+        if age < STUCK_PAYMENT_AGE && !force {
+            bail!(
+                "Payment {} was accepted {} seconds ago and may still be broadcasting, use --force to reject it anyway",
+                payment_id,
+                age.num_seconds()
+            );
+        }
+
+        let context = self.context(reason);
+        let rejected = self
+            .app_state
+            .payment_repository
+            .update_status(&payment, PaymentStatus::Rejected, &context)
+            .await
+            .map_err(|e| anyhow!("Cannot reject payment {}: {}", payment_id, e))?;
+
+        let invoice = self.find_invoice(&payment.invoice_id).await?;
+        if invoice.status == InvoiceStatus::Pending
+            && let Err(e) = self
+                .app_state
+                .invoice_repository
+                .update_status(&invoice, InvoiceStatus::Created, &context)
+                .await
+        {
+            bail!("Payment {} rejected but invoice {} could not be released: {}", payment_id, invoice.id, e);
+        }
+
+        Ok(rejected)
+    }
+
+    /// Confirm a payment whose transaction was broadcast as `tx_id` from `sender_address` and mark its invoice paid.
+    /// Settles payments the gateway could not record after the broadcast, a confirmed payment only gets its invoice paid.
+    pub async fn confirm_payment(
+        &self,
+        payment_id: &ObjectId,
+        tx_id: &str,
+        sender_address: &str,
+        reason: Option<&str>,
+    ) -> Result<Payment> {
+        let payment = self.payment(payment_id).await?;
+        let context = self.context(reason);
+        let confirmed = match payment.status {
+            PaymentStatus::Accepted => self
+                .app_state
+                .payment_repository
+                .confirm(&payment, tx_id, sender_address, &context)
+                .await
+                .map_err(|e| anyhow!("Cannot confirm payment {}: {}", payment_id, e))?,
+            PaymentStatus::Confirmed if payment.tx_id.as_deref() == Some(tx_id) => payment,
+            PaymentStatus::Confirmed => bail!(
+                "Payment {} is confirmed with transaction {}",
+                payment_id,
+                payment.tx_id.as_deref().unwrap_or("unknown")
+            ),
+            PaymentStatus::Rejected => bail!("Payment {} is rejected", payment_id),
+        };
+
+        let invoice = self.find_invoice(&confirmed.invoice_id).await?;
+        if invoice.status == InvoiceStatus::Pending {
+            self.app_state
+                .invoice_repository
+                .update_status(&invoice, InvoiceStatus::Paid, &context)
+                .await
+                .map_err(|e| {
+                    anyhow!("Payment {} confirmed but invoice {} could not be paid: {}", payment_id, invoice.id, e)
+                })?;
+        }
+
+        Ok(confirmed)
+    }
+
+    pub async fn create_indexes(&self) -> Result<()> {
+        self.app_state.create_indexes().await
+    }
+
+    /// Current BTC price in USD cents, fetched from the price API
+    pub async fn btc_price(&self) -> Result<u128> {
+        self.app_state.quote_service.clear_cache();
+        self.app_state
+            .quote_service
+            .get_bitcoin_price()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch the BTC price: {}", e))
+    }
+
+    /// Verify a self-custody merchant's payout address, once its ownership was checked out of band.
+    /// `payout_address` is the address that was checked, nothing is verified if the merchant changed it since.
+    pub async fn verify_payout_address(&self, wallet_address: &str, payout_address: &str) -> Result<Merchant> {
+        let merchant = self
+            .app_state
+            .merchant_repository
+            .find_by_wallet_address(wallet_address)
+            .await?
+            .ok_or_else(|| anyhow!("Merchant {} not found", wallet_address))?;
+        if merchant.payout_address.as_deref() != Some(payout_address) {
+            bail!(
+                "Merchant {} pays out to {}, not {}",
+                wallet_address,
+                merchant.payout_address.as_deref().unwrap_or("no address"),
+                payout_address
+            );
+        }
+
+        if !self
+            .app_state
+            .merchant_repository
+            .verify_payout_address(wallet_address, payout_address)
+            .await?
+        {
+            bail!("Merchant {} changed its payout address concurrently, check it again", wallet_address);
+        }
+        self.app_state
+            .merchant_repository
+            .find_by_wallet_address(wallet_address)
+            .await?
+            .ok_or_else(|| anyhow!("Merchant {} not found", wallet_address))
+    }
+
+    /// Shared price guard state, `None` when payments were never halted
+    pub async fn price_guard(&self) -> Result<Option<PriceGuardState>> {
+        self.app_state.price_guard_repository.find(BTC_USD_PAIR).await
+    }
+
+    /// Resume payments halted by the price guard, e.g. once the price moved for good.
+    /// Replicas follow when they next check the price, the following tick is not compared with the last one.
+    pub async fn resume_payments(&self, reason: Option<&str>) -> Result<PriceHalt> {
+        self.app_state
+            .price_guard_repository
+            .resume(BTC_USD_PAIR, &self.operator, reason)
+            .await?
+            .ok_or_else(|| anyhow!("Payments are not halted by the price guard"))
+    }
+
+    pub async fn export_merchant(&self, wallet_address: &str) -> Result<MerchantExport> {
+        let invoices = self.app_state.invoice_repository.find_by_merchant(wallet_address).await?;
+        let invoice_ids: Vec<ObjectId> = invoices.iter().map(|invoice| invoice.id).collect();
+
+        Ok(MerchantExport {
+            wallet_address: wallet_address.to_string(),
+            merchant: self.app_state.merchant_repository.find_by_wallet_address(wallet_address).await?,
+            payments: self.app_state.payment_repository.find_by_invoice_ids(&invoice_ids).await?,
+            invoices,
+            payment_links: self.app_state.payment_link_repository.find_by_merchant(wallet_address).await?,
+            subscription_plans: self.app_state.subscription_plan_repository.find_by_merchant(wallet_address).await?,
+            subscriptions: self.app_state.subscription_repository.find_by_merchant(wallet_address).await?,
+            exported_at: Utc::now(),
+        })
+    }
+
+    async fn find_invoice(&self, invoice_id: &ObjectId) -> Result<Invoice> {
+        self.app_state
+            .invoice_repository
+            .find_by_id(invoice_id)
+            .await?
+            .ok_or_else(|| anyhow!("Invoice {} not found", invoice_id))
+    }
+}
@@ -1,15 +1,22 @@
+use std::time::Duration;
+
 use anyhow::Result;
-use axum::{Json, response::ErrorResponse};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
-struct BroadcastTransactionRequest {
+/// Bolt protocol API used when none is configured.
+pub const DEFAULT_BOLT_PROTOCOL_URL: &str = "https://test.boltproto.org";
+
+/// How long the Bolt protocol gets to answer a broadcast
+pub const BROADCAST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BroadcastTransactionRequest {
     #[serde(rename = "serializedTx")]
-    serialized_tx: String,
-    amount: String,
+    pub serialized_tx: String,
+    pub amount: String,
     #[serde(rename = "recipientAddress")]
-    recipient_address: String,
+    pub recipient_address: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,6 +43,53 @@ impl BoltTransactionResponse {
     }
 }
 
+/// `status`: ["pending", "success", "failed"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// Broadcast, not mined yet.
+    Pending,
+    /// Mined, the transfer went through.
+    Success,
+    /// Mined or dropped without transferring anything.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionStatusResponse {
+    pub txid: String,
+    pub status: TransactionStatus,
+    pub sender: String,
+    pub amount: String,
+}
+
+/// Why a broadcast returned no transaction
+#[derive(Debug)]
+pub enum BroadcastError {
+    /// The transaction was not broadcast: the Bolt protocol refused it (4xx) or could not be reached
+    Rejected { status: StatusCode, message: String },
+    /// The transaction may have been broadcast: no answer in time, a server error, or a success
+    /// that could not be read. Only reconciliation tells whether the customer paid.
+    Unknown { status: StatusCode, message: String },
+}
+
+impl BroadcastError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            BroadcastError::Rejected { status, .. } | BroadcastError::Unknown { status, .. } => *status,
+        }
+    }
+}
+
+impl std::fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BroadcastError::Rejected { status, message } => write!(f, "broadcast rejected ({}): {}", status, message),
+            BroadcastError::Unknown { status, message } => write!(f, "broadcast outcome unknown ({}): {}", status, message),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BoltProtocolService {
     base_url: String,
@@ -44,9 +98,21 @@ pub struct BoltProtocolService {
 
 impl BoltProtocolService {
     pub fn new() -> Self {
-        let client = reqwest::Client::new();
+        Self::with_base_url(DEFAULT_BOLT_PROTOCOL_URL)
+    }
+
+    /// Service talking to the Bolt protocol API at `base_url`, such as a local fake server
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self::with_timeout(base_url, BROADCAST_TIMEOUT)
+    }
+
+    pub fn with_timeout(base_url: impl Into<String>, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
         BoltProtocolService {
-            base_url: "https://test.boltproto.org".to_string(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
             client,
         }
     }
@@ -56,7 +122,7 @@ impl BoltProtocolService {
         serialized_tx: String,
         valid_amount: u128,
         valid_recipient_address: String,
-    ) -> Result<BoltTransactionResponse, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<BoltTransactionResponse, BroadcastError> {
         let request = BroadcastTransactionRequest {
             serialized_tx,
             amount: valid_amount.to_string(),
@@ -66,24 +132,60 @@ impl BoltProtocolService {
         let url = format!("{}/api/v1/transaction/bolt/broadcast", self.base_url);
 
         let response = self.client.post(&url).json(&request).send().await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse::from(e.to_string()))))?;
+            .map_err(|e| {
+                // Only a failed connection guarantees the request never reached the Bolt protocol
+                if e.is_connect() || e.is_builder() {
+                    BroadcastError::Rejected { status: StatusCode::INTERNAL_SERVER_ERROR, message: e.to_string() }
+                } else {
+                    let status = if e.is_timeout() { StatusCode::GATEWAY_TIMEOUT } else { StatusCode::INTERNAL_SERVER_ERROR };
+                    BroadcastError::Unknown { status, message: e.to_string() }
+                }
+            })?;
 
-        if response.status().is_success() {
+        let status = response.status();
+        if status.is_success() {
+            // The transaction went through, only the answer could not be read
+            let unreadable = |e: String| {
+                tracing::error!("BoltProtocolService broadcast_transaction unreadable success response: {}", e);
+                BroadcastError::Unknown { status: StatusCode::INTERNAL_SERVER_ERROR, message: e }
+            };
             let broadcast_response: BroadcastTransactionResponse = response.json().await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse::from(e.to_string()))))?;
-            let bolt_response = BoltTransactionResponse::from(broadcast_response)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse::from(e.to_string()))))?;
-            Ok(bolt_response)
+                .map_err(|e| unreadable(e.to_string()))?;
+            BoltTransactionResponse::from(broadcast_response).map_err(|e| unreadable(e.to_string()))
         } else {
-            let status = response.status();
             let error_text = response
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            let error_response = ErrorResponse::from(error_text.clone());
             tracing::error!("BoltProtocolService broadcast_transaction error - status: {}, body: {}", status, error_text);
-            Err((status, Json(error_response)))
+            if status.is_client_error() {
+                Err(BroadcastError::Rejected { status, message: error_text })
+            } else {
+                Err(BroadcastError::Unknown { status, message: error_text })
+            }
         }
     }
+
+    /// Look up a transaction the Bolt protocol broadcast, `None` when it does not know `txid`
+    pub async fn transaction_status(&self, txid: &str) -> Result<Option<TransactionStatusResponse>> {
+        let url = format!("{}/api/v1/transaction/{}", self.base_url, txid);
+        let response = self.client.get(&url).send().await?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Bolt protocol answered {} looking up {}: {}", status, txid, error_text);
+        }
+        Ok(Some(response.json().await?))
+    }
+}
+impl Default for BoltProtocolService {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 // Add fields and methods as needed
@@ -0,0 +1,341 @@
+// src/services/change_stream_service.rs
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    change_stream::{
+        event::{ChangeStreamEvent, OperationType, ResumeToken},
+        ChangeStream,
+    },
+    options::{FullDocumentBeforeChangeType, FullDocumentType},
+    Database,
+};
+use serde::de::DeserializeOwned;
+
+use crate::database::ResumeTokenRepository;
+use crate::models::{DomainEvent, InvoiceQuote, InvoiceStatus, PaymentStatus};
+use crate::services::event_bus::EventBus;
+use crate::services::leader_lease::LeaderLease;
+
+/// Wait before reopening a change stream that failed
+const REOPEN_DELAY: Duration = Duration::from_secs(5);
+
+/// The position is saved after this many changes, or `SAVE_INTERVAL` after the last save
+const SAVE_EVERY_CHANGES: usize = 100;
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Lease of the replica saving the positions, another one takes over after missing a few saves
+pub const RESUME_TOKEN_LEASE_TTL: Duration = Duration::from_secs(60);
+
+type ToDomainEvent = fn(&ChangeStreamEvent<Document>) -> Option<DomainEvent>;
+
+/// Feeds the event bus from MongoDB change streams on the `invoices` and `payments` collections,
+/// so changes made by any server replica reach this instance's subscribers.
+///
+/// Changes are watched as plain documents and only the fields events need are read, so documents
+/// written by a newer release with fields this one does not know never stall the stream.
+#[derive(Clone)]
+pub struct ChangeStreamService {
+    database: Database,
+    event_bus: EventBus,
+    resume_tokens: ResumeTokenRepository,
+    /// Only the replica holding the lease saves positions, every replica saves without one
+    lease: Option<LeaderLease>,
+}
+
+impl ChangeStreamService {
+    pub fn new(database: &Database, event_bus: EventBus) -> Self {
+        Self {
+            database: database.clone(),
+            event_bus,
+            resume_tokens: ResumeTokenRepository::new(database),
+            lease: None,
+        }
+    }
+
+    /// Save positions from whichever replica holds `lease`
+    pub fn with_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    /// Open the change streams and start publishing from them in the background.
+    /// Fails when the deployment has no change streams (standalone server), in which case
+    /// the bus keeps publishing the changes made by this instance only.
+    pub async fn start(self) -> Result<()> {
+        self.enable_pre_images("invoices").await;
+        self.enable_pre_images("payments").await;
+
+        let invoices = self.open("invoices", self.resume_tokens.find("invoices").await?).await?;
+        let payments = self.open("payments", self.resume_tokens.find("payments").await?).await?;
+        self.event_bus.use_change_streams();
+
+        tokio::spawn(self.clone().run("invoices", invoices, invoice_event));
+        tokio::spawn(self.run("payments", payments, payment_event));
+        Ok(())
+    }
+
+    /// Pre-images give status changes their previous status (MongoDB 6.0+).
+    /// Without them events are still published, with an unknown `from`.
+    async fn enable_pre_images(&self, collection: &str) {
+        let command = doc! {
+            "collMod": collection,
+            "changeStreamPreAndPostImages": { "enabled": true },
+        };
+        if let Err(e) = self.database.run_command(command).await {
+            tracing::warn!("Cannot enable change stream pre-images on {}: {}", collection, e);
+        }
+    }
+
+    /// Open a change stream on `collection`, resuming after `resume_token` if there is one
+    async fn open(
+        &self,
+        collection: &str,
+        resume_token: Option<ResumeToken>,
+    ) -> Result<ChangeStream<ChangeStreamEvent<Document>>> {
+        let changes = self.database.collection::<Document>(collection);
+        let watch = |resume_token| {
+            changes
+                .watch()
+                .full_document(FullDocumentType::UpdateLookup)
+                .full_document_before_change(FullDocumentBeforeChangeType::WhenAvailable)
+                .resume_after(resume_token)
+        };
+
+        match watch(resume_token.clone()).await {
+            Ok(stream) => Ok(stream),
+            // The saved position may have left the oplog, continue from now rather than not at all
+            Err(e) if resume_token.is_some() => {
+                tracing::error!(
+                    "Cannot resume change stream on {}, changes since the last run are lost: {}",
+                    collection,
+                    e
+                );
+                Ok(watch(None).await?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Publish the changes of `stream`. The position is saved per collection, shared by the replicas:
+    /// a restarted replica has no subscribers to catch up, it only needs a position still in the oplog.
+    /// So one replica saves it, every `SAVE_EVERY_CHANGES` changes or `SAVE_INTERVAL`.
+    /// Reopening after a failure resumes from this process' own position.
+    async fn run(
+        self,
+        collection: &'static str,
+        mut stream: ChangeStream<ChangeStreamEvent<Document>>,
+        to_domain_event: ToDomainEvent,
+    ) {
+        tracing::info!("Publishing {} changes from the change stream", collection);
+        let mut position = None;
+        let mut unsaved = 0;
+        let mut save_timer = tokio::time::interval(SAVE_INTERVAL);
+
+        loop {
+            let next = tokio::select! {
+                next = stream.next() => next,
+                _ = save_timer.tick() => {
+                    if unsaved > 0 {
+                        self.save(collection, position.clone()).await;
+                        unsaved = 0;
+                    }
+                    continue;
+                }
+            };
+
+            match next {
+                Some(Ok(change)) => {
+                    match to_domain_event(&change) {
+                        Some(event) => self.event_bus.publish(event),
+                        None => tracing::debug!("No event for {:?} on {}", change.operation_type, collection),
+                    }
+                    position = stream.resume_token();
+                    unsaved += 1;
+                    if unsaved >= SAVE_EVERY_CHANGES {
+                        self.save(collection, position.clone()).await;
+                        unsaved = 0;
+                        save_timer.reset();
+                    }
+                    continue;
+                }
+                Some(Err(e)) => {
+                    tracing::error!("Change stream on {} failed: {}", collection, e);
+                }
+                None => {
+                    tracing::warn!("Change stream on {} was closed", collection);
+                }
+            }
+
+            // The driver already retried resumable errors, reopen from the last processed change
+            loop {
+                tokio::time::sleep(REOPEN_DELAY).await;
+                match self.open(collection, position.clone().or_else(|| stream.resume_token())).await {
+                    Ok(reopened) => {
+                        stream = reopened;
+                        break;
+                    }
+                    Err(e) => tracing::error!("Failed to reopen change stream on {}: {}", collection, e),
+                }
+            }
+        }
+    }
+
+    /// Save the position of `collection`'s stream, if this replica leads the saving
+    async fn save(&self, collection: &str, position: Option<ResumeToken>) {
+        let Some(token) = position else { return };
+        if let Some(lease) = &self.lease
+            && !lease.hold().await
+        {
+            return;
+        }
+        if let Err(e) = self.resume_tokens.save(collection, token).await {
+            tracing::error!("Failed to save resume token of {}: {}", collection, e);
+        }
+    }
+}
+
+fn invoice_event(change: &ChangeStreamEvent<Document>) -> Option<DomainEvent> {
+    let timestamp = change_time(change);
+    match change.operation_type {
+        OperationType::Insert => {
+            let invoice = change.full_document.as_ref()?;
+            Some(DomainEvent::InvoiceStatusChanged {
+                invoice_id: invoice.get_object_id("_id").ok()?,
+                from: None,
+                to: field(invoice, "status")?,
+                timestamp,
+            })
+        }
+        OperationType::Update => {
+            let invoice_id = document_id(change)?;
+            let updated_fields = &change.update_description.as_ref()?.updated_fields;
+
+            if let Some(to) = field::<InvoiceStatus>(updated_fields, "status") {
+                Some(DomainEvent::InvoiceStatusChanged {
+                    invoice_id,
+                    from: change.full_document_before_change.as_ref().and_then(|before| field(before, "status")),
+                    to,
+                    timestamp,
+                })
+            } else if let Some(quote) = field::<InvoiceQuote>(updated_fields, "quote") {
+                Some(DomainEvent::QuoteRefreshed { invoice_id, quote })
+            } else {
+                Some(DomainEvent::InvoiceAmended { invoice_id, timestamp })
+            }
+        }
+        OperationType::Replace => Some(DomainEvent::InvoiceAmended {
+            invoice_id: document_id(change)?,
+            timestamp,
+        }),
+        _ => None,
+    }
+}
+
+fn payment_event(change: &ChangeStreamEvent<Document>) -> Option<DomainEvent> {
+    let timestamp = change_time(change);
+    match change.operation_type {
+        OperationType::Insert => {
+            let payment = change.full_document.as_ref()?;
+            Some(DomainEvent::PaymentStatusChanged {
+                invoice_id: payment.get_object_id("invoice_id").ok()?,
+                payment_id: payment.get_object_id("_id").ok()?,
+                from: None,
+                to: field(payment, "status")?,
+                timestamp,
+            })
+        }
+        OperationType::Update => {
+            let updated_fields = &change.update_description.as_ref()?.updated_fields;
+            let to = field::<PaymentStatus>(updated_fields, "status")?;
+            // Looked up after the change, only the invoice ID is taken from it
+            let payment = change
+                .full_document
+                .as_ref()
+                .or(change.full_document_before_change.as_ref())?;
+            Some(DomainEvent::PaymentStatusChanged {
+                invoice_id: payment.get_object_id("invoice_id").ok()?,
+                payment_id: document_id(change)?,
+                from: change.full_document_before_change.as_ref().and_then(|before| field(before, "status")),
+                to,
+                timestamp,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn change_time(change: &ChangeStreamEvent<Document>) -> chrono::DateTime<Utc> {
+    change.wall_time.map(|time| time.to_chrono()).unwrap_or_else(Utc::now)
+}
+
+fn document_id(change: &ChangeStreamEvent<Document>) -> Option<bson::oid::ObjectId> {
+    change.document_key.as_ref()?.get_object_id("_id").ok()
+}
+
+/// Read one field of a document, `None` when missing or of another shape
+fn field<V: DeserializeOwned>(document: &Document, name: &str) -> Option<V> {
+    let value = document.get(name)?;
+    bson::from_bson(value.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::oid::ObjectId;
+
+    fn invoice_update(invoice_id: ObjectId, updated_fields: Document) -> ChangeStreamEvent<Document> {
+        bson::from_document(doc! {
+            "_id": { "_data": "token" },
+            "operationType": "update",
+            "documentKey": { "_id": invoice_id },
+            "updateDescription": { "updatedFields": updated_fields, "removedFields": [] },
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_invoice_updates_become_typed_events() {
+        let invoice_id = ObjectId::new();
+
+        let event = invoice_event(&invoice_update(invoice_id, doc! { "status": "paid" }));
+        assert!(matches!(
+            event,
+            Some(DomainEvent::InvoiceStatusChanged { to: InvoiceStatus::Paid, from: None, .. })
+        ));
+
+        let event = invoice_event(&invoice_update(invoice_id, doc! { "merchant_order_id": "ORD-2" }));
+        assert!(matches!(event, Some(DomainEvent::InvoiceAmended { .. })));
+        assert_eq!(event.unwrap().invoice_id(), invoice_id);
+    }
+
+    #[test]
+    fn test_documents_with_unknown_fields_still_become_events() {
+        let payment_id = ObjectId::new();
+        let invoice_id = ObjectId::new();
+        // As a newer release could write it
+        let change: ChangeStreamEvent<Document> = bson::from_document(doc! {
+            "_id": { "_data": "token" },
+            "operationType": "insert",
+            "documentKey": { "_id": payment_id },
+            "fullDocument": {
+                "_id": payment_id,
+                "invoice_id": invoice_id,
+                "status": "accepted",
+                "field_from_the_future": true,
+            },
+        })
+        .unwrap();
+
+        let event = payment_event(&change);
+        assert!(matches!(
+            event,
+            Some(DomainEvent::PaymentStatusChanged { to: PaymentStatus::Accepted, from: None, .. })
+        ));
+        assert_eq!(event.unwrap().invoice_id(), invoice_id);
+    }
+}
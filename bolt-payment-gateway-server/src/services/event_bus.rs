@@ -0,0 +1,97 @@
+// src/services/event_bus.rs
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tokio::sync::broadcast;
+
+use crate::models::DomainEvent;
+
+/// Events kept for subscribers that fall behind before they start missing some
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// In-process fan-out of domain events to every subscriber (SSE and WebSocket streams, workers).
+///
+/// The bus is fed by the MongoDB change streams when the deployment supports them, so changes
+/// made by any server replica reach every subscriber. Otherwise (standalone MongoDB) each
+/// instance only publishes the changes it makes itself.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+    publishes_local_changes: Arc<AtomicBool>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self {
+            sender,
+            publishes_local_changes: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Deliver an event to the current subscribers, if any
+    pub fn publish(&self, event: DomainEvent) {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.sender.send(event);
+    }
+
+    /// Publish a change written by this instance. Skipped once the change streams feed the bus,
+    /// which deliver the same change for every instance.
+    pub fn publish_local(&self, event: DomainEvent) {
+        if self.publishes_local_changes.load(Ordering::Relaxed) {
+            self.publish(event);
+        }
+    }
+
+    /// Called once the change streams are open and feed the bus
+    pub fn use_change_streams(&self) {
+        self.publishes_local_changes.store(false, Ordering::Relaxed);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InvoiceStatus;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_every_subscriber_receives_published_events() {
+        let bus = EventBus::new();
+        // Publishing without subscribers is not an error
+        bus.publish(DomainEvent::InvoiceAmended {
+            invoice_id: bson::oid::ObjectId::new(),
+            timestamp: Utc::now(),
+        });
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        let event = DomainEvent::InvoiceStatusChanged {
+            invoice_id: bson::oid::ObjectId::new(),
+            from: Some(InvoiceStatus::Created),
+            to: InvoiceStatus::Pending,
+            timestamp: Utc::now(),
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(first.recv().await.unwrap(), event);
+        assert_eq!(second.recv().await.unwrap(), event);
+
+        // Local changes arrive through the change streams instead
+        bus.use_change_streams();
+        bus.publish_local(event);
+        assert!(first.try_recv().is_err());
+    }
+}
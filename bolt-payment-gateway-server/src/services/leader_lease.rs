@@ -0,0 +1,62 @@
+// src/services/leader_lease.rs
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::database::LeaseRepository;
+
+/// Leadership of a singleton job among the replicas, held through a lease in the database.
+///
+/// The job calls `hold` before every run: the leader extends its lease, the others take it over
+/// once it expired, e.g. because the leader stopped. The lease must outlive the interval between
+/// runs, and jobs must stay safe to run twice as a slow leader may overlap with its successor.
+#[derive(Clone)]
+pub struct LeaderLease {
+    repository: LeaseRepository,
+    name: String,
+    holder: String,
+    ttl: Duration,
+    /// Whether the last `hold` succeeded, to log leadership changes once
+    leader: Arc<AtomicBool>,
+}
+
+impl LeaderLease {
+    /// `holder` must be unique per process, e.g. the instance ID with a random suffix
+    pub fn new(repository: LeaseRepository, name: &str, holder: &str, ttl: Duration) -> Self {
+        Self {
+            repository,
+            name: name.to_string(),
+            holder: holder.to_string(),
+            ttl,
+            leader: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Take or extend the lease, returns whether this process leads the job until the next call.
+    /// A database failure is reported as not leading, so the job never runs without the lease.
+    pub async fn hold(&self) -> bool {
+        let leader = match self.repository.try_acquire(&self.name, &self.holder, self.ttl).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                tracing::warn!("Failed to renew the {} lease: {}", self.name, e);
+                false
+            }
+        };
+
+        let was_leader = self.leader.swap(leader, Ordering::Relaxed);
+        if leader && !was_leader {
+            tracing::info!("{} now leads {}", self.holder, self.name);
+        } else if !leader && was_leader {
+            tracing::warn!("{} lost the {} lease", self.holder, self.name);
+        }
+        leader
+    }
+
+    /// Hand the job over to another replica right away, e.g. on shutdown
+    pub async fn release(&self) {
+        self.leader.store(false, Ordering::Relaxed);
+        if let Err(e) = self.repository.release(&self.name, &self.holder).await {
+            tracing::warn!("Failed to release the {} lease: {}", self.name, e);
+        }
+    }
+}
@@ -1,2 +1,12 @@
 pub mod quote_service;
-pub mod bolt_protocol_service;
\ No newline at end of file
+pub mod bolt_protocol_service;
+pub mod payout_service;
+pub mod event_bus;
+pub mod change_stream_service;
+pub mod notification_service;
+pub mod subscription_scheduler;
+pub mod token_registry;
+pub mod admin_service;
+pub mod leader_lease;
+pub mod price_guard;
+pub mod payment_reconciler;
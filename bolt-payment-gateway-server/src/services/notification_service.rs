@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// How long a webhook or email relay gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+struct EmailRequest<'a> {
+    to: &'a str,
+    subject: &'a str,
+    text: &'a str,
+}
+
+/// Sends notifications to merchants' webhooks and, through an HTTP email relay, to customers.
+#[derive(Clone)]
+pub struct NotificationService {
+    client: reqwest::Client,
+    email_relay_url: Option<String>,
+}
+
+impl NotificationService {
+    /// Emails are only logged when no relay is configured
+    pub fn new(email_relay_url: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { client, email_relay_url }
+    }
+
+    /// POST `body` as JSON to a merchant's webhook URL
+    pub async fn send_webhook<T: Serialize>(&self, url: &str, body: &T) -> Result<()> {
+        self.client
+            .post(url)
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn send_email(&self, to: &str, subject: &str, text: &str) -> Result<()> {
+        let Some(relay_url) = &self.email_relay_url else {
+            tracing::info!("No email relay configured, not sending \"{}\" to {}", subject, to);
+            return Ok(());
+        };
+
+        self.client
+            .post(relay_url)
+            .json(&EmailRequest { to, subject, text })
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
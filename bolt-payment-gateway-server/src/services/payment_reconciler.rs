@@ -0,0 +1,165 @@
+// src/services/payment_reconciler.rs
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+
+use crate::models::{Actor, AuditContext, InvoiceStatus, Payment, PaymentStatus};
+use crate::services::admin_service::STUCK_PAYMENT_AGE;
+use crate::services::bolt_protocol_service::TransactionStatus;
+use crate::services::leader_lease::LeaderLease;
+use crate::AppState;
+
+/// How often accepted payments are checked against their transaction
+const RECONCILER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lease of the replica running the reconciler, another one takes over after missing two runs
+pub const RECONCILER_LEASE_TTL: Duration = Duration::from_secs(3 * 60);
+
+/// Payments are left to the request that accepted them for this long, past the broadcast timeout
+const RECONCILE_AFTER: chrono::Duration = chrono::Duration::minutes(2);
+
+/// Actor recorded in the audit trail of the payments and invoices the reconciler settles
+const WORKER: &str = "payment_reconciler";
+
+/// What reconciling one payment did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconciled {
+    Confirmed,
+    Rejected,
+    /// Still pending on chain, unknown to the Bolt protocol, or without a transaction to look up
+    Unsettled,
+}
+
+/// Settles payments left `Accepted` after their broadcast, e.g. when its outcome was unknown or the
+/// payment could not be confirmed. Their transaction is looked up on the Bolt protocol, a mined one
+/// confirms the payment and pays its invoice, a failed one rejects the payment and releases the invoice.
+///
+/// Payments without a transaction ID cannot be looked up and are left to operators
+/// (`bolt-admin payment confirm` or `reject`) once they are older than `STUCK_PAYMENT_AGE`.
+#[derive(Clone)]
+pub struct PaymentReconciler {
+    app_state: AppState,
+    /// Only the replica holding the lease runs, every replica runs without one
+    lease: Option<LeaderLease>,
+}
+
+impl PaymentReconciler {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state, lease: None }
+    }
+
+    /// Run on whichever replica holds `lease`
+    pub fn with_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        let mut interval = tokio::time::interval(RECONCILER_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Some(lease) = &self.lease
+                && !lease.hold().await
+            {
+                continue;
+            }
+            if let Err(e) = self.reconcile().await {
+                tracing::warn!("Payment reconciler failed to load accepted payments: {}", e);
+            }
+        }
+    }
+
+    /// Reconcile every payment accepted more than `RECONCILE_AFTER` ago, returns how many got settled
+    pub async fn reconcile(&self) -> Result<usize> {
+        let payments = self
+            .app_state
+            .payment_repository
+            .find_accepted_before(Utc::now() - RECONCILE_AFTER)
+            .await?;
+
+        let mut settled = 0;
+        for payment in payments {
+            let id = payment.id;
+            match self.reconcile_payment(payment).await {
+                Ok(Reconciled::Unsettled) => {}
+                Ok(outcome) => {
+                    tracing::info!("Reconciled payment {}: {:?}", id, outcome);
+                    settled += 1;
+                }
+                Err(e) => tracing::warn!("Failed to reconcile payment {}: {}", id, e),
+            }
+        }
+        Ok(settled)
+    }
+
+    async fn reconcile_payment(&self, payment: Payment) -> Result<Reconciled> {
+        let stuck = Utc::now() - payment.received_at >= STUCK_PAYMENT_AGE;
+        let Some(tx_id) = payment.tx_id.clone() else {
+            if stuck {
+                tracing::warn!(
+                    "Payment {} has no transaction to look up, settle it with `bolt-admin payment confirm` or `reject`",
+                    payment.id
+                );
+            }
+            return Ok(Reconciled::Unsettled);
+        };
+
+        let Some(transaction) = self.app_state.bolt_protocol_service.transaction_status(&tx_id).await? else {
+            if stuck {
+                tracing::warn!("Transaction {} of payment {} is unknown to the Bolt protocol", tx_id, payment.id);
+            }
+            return Ok(Reconciled::Unsettled);
+        };
+
+        let context = AuditContext::new(Actor::System { worker: WORKER.to_string() }, None);
+        match transaction.status {
+            TransactionStatus::Pending => Ok(Reconciled::Unsettled),
+            TransactionStatus::Success => {
+                let context = context.with_reason(format!("Transaction {} succeeded", tx_id));
+                let confirmed = self
+                    .app_state
+                    .payment_repository
+                    .confirm(&payment, &tx_id, &transaction.sender, &context)
+                    .await
+                    .map_err(|e| anyhow!("Cannot confirm payment: {}", e))?;
+                self.settle_invoice(&confirmed, InvoiceStatus::Paid, &context).await?;
+                Ok(Reconciled::Confirmed)
+            }
+            TransactionStatus::Failed => {
+                let context = context.with_reason(format!("Transaction {} failed", tx_id));
+                let rejected = self
+                    .app_state
+                    .payment_repository
+                    .update_status(&payment, PaymentStatus::Rejected, &context)
+                    .await
+                    .map_err(|e| anyhow!("Cannot reject payment: {}", e))?;
+                self.settle_invoice(&rejected, InvoiceStatus::Created, &context).await?;
+                Ok(Reconciled::Rejected)
+            }
+        }
+    }
+
+    /// Move the invoice the payment claimed out of `Pending`, an invoice moved on already is left alone
+    async fn settle_invoice(&self, payment: &Payment, to: InvoiceStatus, context: &AuditContext) -> Result<()> {
+        let invoice = self
+            .app_state
+            .invoice_repository
+            .find_by_id(&payment.invoice_id)
+            .await?
+            .ok_or_else(|| anyhow!("Invoice {} not found", payment.invoice_id))?;
+        if invoice.status != InvoiceStatus::Pending {
+            return Ok(());
+        }
+        self.app_state
+            .invoice_repository
+            .update_status(&invoice, to, context)
+            .await
+            .map_err(|e| anyhow!("Payment settled but invoice {} could not move to {:?}: {}", invoice.id, to, e))?;
+        Ok(())
+    }
+}
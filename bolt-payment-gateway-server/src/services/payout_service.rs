@@ -0,0 +1,100 @@
+use std::fmt;
+
+use crate::models::{CustodyMode, Merchant};
+
+/// Recipient used when no treasury address is configured.
+pub const DEFAULT_TREASURY_ADDRESS: &str = "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutError {
+    /// A self-custody merchant has no payout address configured.
+    MissingPayoutAddress,
+    /// A self-custody merchant's payout address has not been verified yet.
+    UnverifiedPayoutAddress,
+}
+
+impl fmt::Display for PayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayoutError::MissingPayoutAddress => write!(f, "merchant has no payout address"),
+            PayoutError::UnverifiedPayoutAddress => {
+                write!(f, "merchant payout address is not verified")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PayoutError {}
+
+/// Decides which address a customer's transfer must be sent to.
+#[derive(Debug, Clone)]
+pub struct PayoutService {
+    treasury_address: String,
+}
+
+impl PayoutService {
+    pub fn new(treasury_address: String) -> Self {
+        tracing::info!("Initializing PayoutService with treasury address: {}", treasury_address);
+        Self { treasury_address }
+    }
+
+    /// Custodial merchants are paid through the gateway treasury, self-custody merchants
+    /// directly to their verified payout address.
+    pub fn resolve_recipient(&self, merchant: &Merchant) -> Result<String, PayoutError> {
+        match merchant.custody_mode {
+            CustodyMode::Custodial => Ok(self.treasury_address.clone()),
+            CustodyMode::SelfCustody => {
+                let payout_address = merchant
+                    .payout_address
+                    .as_ref()
+                    .ok_or(PayoutError::MissingPayoutAddress)?;
+                if !merchant.payout_address_verified {
+                    return Err(PayoutError::UnverifiedPayoutAddress);
+                }
+                Ok(payout_address.clone())
+            }
+        }
+    }
+}
+
+impl Default for PayoutService {
+    fn default() -> Self {
+        Self::new(DEFAULT_TREASURY_ADDRESS.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custodial_merchant_pays_treasury() {
+        let service = PayoutService::new("ST1TREASURY".to_string());
+        let mut merchant = Merchant::with_defaults("ST1MERCHANT");
+        merchant.payout_address = Some("ST1PAYOUT".to_string());
+        merchant.payout_address_verified = true;
+
+        assert_eq!(service.resolve_recipient(&merchant), Ok("ST1TREASURY".to_string()));
+    }
+
+    #[test]
+    fn test_self_custody_merchant_requires_verified_address() {
+        let service = PayoutService::new("ST1TREASURY".to_string());
+        let mut merchant = Merchant::with_defaults("ST1MERCHANT");
+        merchant.custody_mode = CustodyMode::SelfCustody;
+
+        assert_eq!(
+            service.resolve_recipient(&merchant),
+            Err(PayoutError::MissingPayoutAddress)
+        );
+
+        merchant.payout_address = Some("ST1PAYOUT".to_string());
+        assert_eq!(
+            service.resolve_recipient(&merchant),
+            Err(PayoutError::UnverifiedPayoutAddress)
+        );
+
+        merchant.payout_address_verified = true;
+        assert_eq!(service.resolve_recipient(&merchant), Ok("ST1PAYOUT".to_string()));
+    }
+}
@@ -0,0 +1,437 @@
+// src/services/price_guard.rs
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::database::PriceGuardRepository;
+use crate::models::{convert_money_from_string, format_money_amount, PriceCheck, PriceGuardState, PriceHalt, BTC_USD_PAIR};
+use crate::services::quote_service::QuoteService;
+
+/// Largest move from one tick to the next, in basis points (10.00%)
+pub const DEFAULT_MAX_TICK_DEVIATION_BPS: u32 = 1000;
+
+/// Largest difference with the secondary source, in basis points (2.00%)
+pub const DEFAULT_MAX_SOURCE_DEVIATION_BPS: u32 = 200;
+
+/// Lowest plausible BTC price in USD cents ($1,000.00)
+pub const DEFAULT_MIN_PRICE: u128 = 100_000;
+
+/// Highest plausible BTC price in USD cents ($10,000,000.00)
+pub const DEFAULT_MAX_PRICE: u128 = 1_000_000_000;
+
+/// Consecutive ticks passing every check against the last good price before payments resume on their own
+pub const RECOVERY_TICKS: u32 = 3;
+
+/// Recorded as the one who resumed payments when the feed recovered on its own
+pub const RECOVERY_OPERATOR: &str = "recovery";
+
+/// Bounds fetched prices are checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceGuardConfig {
+    /// 0 disables the check
+    pub max_tick_deviation_bps: u32,
+    /// 0 disables the check, which only runs with a secondary source
+    pub max_source_deviation_bps: u32,
+    pub min_price: u128,
+    pub max_price: u128,
+}
+
+impl Default for PriceGuardConfig {
+    fn default() -> Self {
+        Self {
+            max_tick_deviation_bps: DEFAULT_MAX_TICK_DEVIATION_BPS,
+            max_source_deviation_bps: DEFAULT_MAX_SOURCE_DEVIATION_BPS,
+            min_price: DEFAULT_MIN_PRICE,
+            max_price: DEFAULT_MAX_PRICE,
+        }
+    }
+}
+
+impl PriceGuardConfig {
+    /// Defaults overridden by `PRICE_GUARD_MAX_TICK_DEVIATION_BPS`, `PRICE_GUARD_MAX_SOURCE_DEVIATION_BPS`,
+    /// `PRICE_GUARD_MIN_PRICE` and `PRICE_GUARD_MAX_PRICE` (in USD, e.g. `1000.00`)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let bps = |name: &str, default: u32| {
+            env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        };
+        let price = |name: &str, default: u128| {
+            env::var(name)
+                .ok()
+                .and_then(|value| convert_money_from_string(value).ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_tick_deviation_bps: bps("PRICE_GUARD_MAX_TICK_DEVIATION_BPS", defaults.max_tick_deviation_bps),
+            max_source_deviation_bps: bps("PRICE_GUARD_MAX_SOURCE_DEVIATION_BPS", defaults.max_source_deviation_bps),
+            min_price: price("PRICE_GUARD_MIN_PRICE", defaults.min_price),
+            max_price: price("PRICE_GUARD_MAX_PRICE", defaults.max_price),
+        }
+    }
+
+    /// First check `price` fails, with the price it was checked against
+    fn violation(&self, price: u128, previous: Option<u128>, secondary: Option<u128>) -> Option<(PriceCheck, u128)> {
+        if price < self.min_price {
+            return Some((PriceCheck::Bounds, self.min_price));
+        }
+        if price > self.max_price {
+            return Some((PriceCheck::Bounds, self.max_price));
+        }
+        if let Some(previous) = previous
+            && exceeds(price, previous, self.max_tick_deviation_bps)
+        {
+            return Some((PriceCheck::TickDeviation, previous));
+        }
+        if let Some(secondary) = secondary
+            && exceeds(price, secondary, self.max_source_deviation_bps)
+        {
+            return Some((PriceCheck::CrossSource, secondary));
+        }
+        None
+    }
+}
+
+/// Whether `price` is more than `max_bps` away from `reference`, a zero `max_bps` never is
+fn exceeds(price: u128, reference: u128, max_bps: u32) -> bool {
+    max_bps > 0 && price.abs_diff(reference) * 10_000 > reference * max_bps as u128
+}
+
+/// Outcome of checking one price
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Verdict {
+    Accepted,
+    /// The price failed a check, payments are halted from now on
+    Tripped(PriceHalt),
+    /// Payments stay halted, the price is not used
+    Halted(PriceHalt),
+    /// Enough good ticks in a row, payments resume with this price
+    Recovered(PriceHalt),
+}
+
+#[derive(Debug, Default)]
+struct GuardState {
+    /// Last price that passed the checks, what the next tick is compared with
+    last_price: Option<u128>,
+    halt: Option<PriceHalt>,
+    /// Consecutive good ticks since the halt
+    good_ticks: u32,
+    checked_at: Option<Instant>,
+    trips: HashMap<PriceCheck, u64>,
+}
+
+impl GuardState {
+    fn evaluate(&mut self, config: &PriceGuardConfig, price: u128, secondary: Option<u128>) -> Verdict {
+        let violation = config.violation(price, self.last_price, secondary);
+        self.judge(price, violation)
+    }
+
+    /// A price that could not be parsed fails a check of its own, and is never a good tick
+    fn malformed(&mut self) -> Verdict {
+        self.judge(0, Some((PriceCheck::Malformed, 0)))
+    }
+
+    fn judge(&mut self, price: u128, violation: Option<(PriceCheck, u128)>) -> Verdict {
+        match (self.halt.clone(), violation) {
+            (None, None) => {
+                self.last_price = Some(price);
+                Verdict::Accepted
+            }
+            (None, Some((check, reference))) => {
+                let halt = PriceHalt { check, price, reference, tripped_at: Utc::now() };
+                *self.trips.entry(check).or_default() += 1;
+                self.halt = Some(halt.clone());
+                self.good_ticks = 0;
+                Verdict::Tripped(halt)
+            }
+            (Some(halt), Some(_)) => {
+                self.good_ticks = 0;
+                Verdict::Halted(halt)
+            }
+            (Some(halt), None) => {
+                self.good_ticks += 1;
+                if self.good_ticks < RECOVERY_TICKS {
+                    return Verdict::Halted(halt);
+                }
+                self.halt = None;
+                self.good_ticks = 0;
+                self.last_price = Some(price);
+                Verdict::Recovered(halt)
+            }
+        }
+    }
+
+    /// Follow halts and resumes made by other replicas or operators
+    fn adopt(&mut self, stored: &PriceGuardState) {
+        match (&self.halt, &stored.halt) {
+            (None, Some(halt)) => {
+                self.halt = Some(halt.clone());
+                self.good_ticks = 0;
+            }
+            (Some(halt), None) if stored.resumed_at.is_some_and(|resumed_at| resumed_at > halt.tripped_at) => {
+                // The price may have moved for good while halted, the next tick starts over
+                self.halt = None;
+                self.good_ticks = 0;
+                self.last_price = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Counters exposed on `/metrics`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceGuardMetrics {
+    pub halted: bool,
+    pub last_price: Option<u128>,
+    /// Halts tripped by this process per check
+    pub trips: Vec<(PriceCheck, u64)>,
+}
+
+/// Sanity checks on fetched BTC prices: a plausible range, the move since the last tick and,
+/// with a secondary source, the difference between both sources.
+///
+/// A failed check halts new payments until `RECOVERY_TICKS` ticks in a row pass the checks against
+/// the last good price, or an operator resumes them (`bolt-admin price resume`). With a repository
+/// the halt is shared, so every replica stops as soon as it checks its next price.
+#[derive(Debug, Clone)]
+pub struct PriceGuard {
+    config: PriceGuardConfig,
+    secondary: Option<Box<QuoteService>>,
+    repository: Option<PriceGuardRepository>,
+    state: Arc<Mutex<GuardState>>,
+}
+
+impl PriceGuard {
+    pub fn new(config: PriceGuardConfig) -> Self {
+        Self {
+            config,
+            secondary: None,
+            repository: None,
+            state: Arc::new(Mutex::new(GuardState::default())),
+        }
+    }
+
+    /// Compare every price with the one `secondary` answers, a failing secondary skips the comparison
+    pub fn with_secondary(mut self, secondary: QuoteService) -> Self {
+        self.secondary = Some(Box::new(secondary));
+        self
+    }
+
+    /// Share halts and resumes with the other replicas
+    pub fn with_repository(mut self, repository: PriceGuardRepository) -> Self {
+        self.repository = Some(repository);
+        self
+    }
+
+    /// Why payments are halted, `None` while prices are good
+    pub fn halt(&self) -> Option<PriceHalt> {
+        self.state.lock().unwrap().halt.clone()
+    }
+
+    /// The halt while it was checked less than `interval` ago, so a halted feed is not fetched on every request
+    pub fn halt_until_recheck(&self, interval: Duration) -> Option<PriceHalt> {
+        let state = self.state.lock().unwrap();
+        let recent = state.checked_at.is_some_and(|checked_at| checked_at.elapsed() < interval);
+        state.halt.clone().filter(|_| recent)
+    }
+
+    /// Check a price fetched from the primary source, it may only be used when `Ok`
+    pub async fn check(&self, price: u128) -> Result<(), PriceHalt> {
+        self.adopt_shared().await;
+
+        let secondary = match &self.secondary {
+            // Boxed as the secondary is a `QuoteService` too, which checks its prices here
+            Some(secondary) => match Box::pin(secondary.get_bitcoin_price()).await {
+                Ok(price) => Some(price),
+                Err(e) => {
+                    tracing::warn!("Failed to fetch the secondary BTC price, skipping the comparison: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let verdict = {
+            let mut state = self.state.lock().unwrap();
+            state.checked_at = Some(Instant::now());
+            state.evaluate(&self.config, price, secondary)
+        };
+        self.settle(verdict, price).await
+    }
+
+    /// Count a price the primary source answered that could not be parsed as a failed check
+    pub async fn reject_malformed(&self) -> PriceHalt {
+        self.adopt_shared().await;
+        let verdict = {
+            let mut state = self.state.lock().unwrap();
+            state.checked_at = Some(Instant::now());
+            state.malformed()
+        };
+        match self.settle(verdict, 0).await {
+            Err(halt) => halt,
+            Ok(()) => unreachable!("a malformed price never passes"),
+        }
+    }
+
+    async fn adopt_shared(&self) {
+        if let Some(repository) = &self.repository {
+            match repository.find(BTC_USD_PAIR).await {
+                Ok(Some(stored)) => self.state.lock().unwrap().adopt(&stored),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to read the shared price guard state: {}", e),
+            }
+        }
+    }
+
+    async fn settle(&self, verdict: Verdict, price: u128) -> Result<(), PriceHalt> {
+        match verdict {
+            Verdict::Accepted => Ok(()),
+            Verdict::Tripped(halt) => {
+                tracing::error!("Price guard halted new payments: {}", halt);
+                if let Some(repository) = &self.repository
+                    && let Err(e) = repository.halt(BTC_USD_PAIR, &halt).await
+                {
+                    tracing::error!("Failed to share the price halt with other replicas: {}", e);
+                }
+                Err(halt)
+            }
+            Verdict::Halted(halt) => {
+                tracing::warn!(
+                    "Payments still halted since {}, rejected BTC price {}",
+                    halt.tripped_at,
+                    if price == 0 { "(malformed)".to_string() } else { format_money_amount(price) }
+                );
+                Err(halt)
+            }
+            Verdict::Recovered(halt) => {
+                tracing::warn!(
+                    "BTC price recovered to {} after {} good ticks, resuming payments halted since {}",
+                    format_money_amount(price),
+                    RECOVERY_TICKS,
+                    halt.tripped_at
+                );
+                if let Some(repository) = &self.repository
+                    && let Err(e) = repository.resume(BTC_USD_PAIR, RECOVERY_OPERATOR, None).await
+                {
+                    tracing::error!("Failed to share the price recovery with other replicas: {}", e);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> PriceGuardMetrics {
+        let state = self.state.lock().unwrap();
+        PriceGuardMetrics {
+            halted: state.halt.is_some(),
+            last_price: state.last_price,
+            trips: PriceCheck::ALL
+                .iter()
+                .map(|check| (*check, state.trips.get(check).copied().unwrap_or(0)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICE: u128 = 10_000_000;
+
+    fn accepted(state: &mut GuardState, price: u128) {
+        assert_eq!(state.evaluate(&PriceGuardConfig::default(), price, None), Verdict::Accepted);
+    }
+
+    #[test]
+    fn test_implausible_prices_trip_the_bounds_check() {
+        let mut state = GuardState::default();
+        let config = PriceGuardConfig::default();
+
+        let Verdict::Tripped(halt) = state.evaluate(&config, 50_000, None) else { panic!("not tripped") };
+        assert_eq!((halt.check, halt.reference), (PriceCheck::Bounds, DEFAULT_MIN_PRICE));
+        assert!(state.last_price.is_none());
+        assert_eq!(state.trips[&PriceCheck::Bounds], 1);
+    }
+
+    #[test]
+    fn test_large_moves_trip_and_recover_after_good_ticks() {
+        let mut state = GuardState::default();
+        let config = PriceGuardConfig::default();
+        accepted(&mut state, PRICE);
+        // 9.99% is a move, not a glitch
+        accepted(&mut state, 10_999_000);
+        accepted(&mut state, PRICE);
+
+        let Verdict::Tripped(halt) = state.evaluate(&config, 5_000_000, None) else { panic!("not tripped") };
+        assert_eq!((halt.check, halt.reference), (PriceCheck::TickDeviation, PRICE));
+
+        // The streak restarts on every bad tick and compares with the last good price
+        assert!(matches!(state.evaluate(&config, PRICE, None), Verdict::Halted(_)));
+        assert!(matches!(state.evaluate(&config, 5_000_000, None), Verdict::Halted(_)));
+        for _ in 1..RECOVERY_TICKS {
+            assert!(matches!(state.evaluate(&config, PRICE, None), Verdict::Halted(_)));
+        }
+        assert_eq!(state.evaluate(&config, PRICE, None), Verdict::Recovered(halt));
+        accepted(&mut state, PRICE);
+    }
+
+    #[test]
+    fn test_prices_far_from_the_secondary_source_trip() {
+        let mut state = GuardState::default();
+        let config = PriceGuardConfig::default();
+
+        assert_eq!(state.evaluate(&config, PRICE, Some(9_850_000)), Verdict::Accepted);
+        let Verdict::Tripped(halt) = state.evaluate(&config, PRICE, Some(9_700_000)) else { panic!("not tripped") };
+        assert_eq!((halt.check, halt.reference), (PriceCheck::CrossSource, 9_700_000));
+    }
+
+    #[test]
+    fn test_malformed_prices_trip_and_never_count_as_good_ticks() {
+        let mut state = GuardState::default();
+        let config = PriceGuardConfig::default();
+        accepted(&mut state, PRICE);
+
+        let Verdict::Tripped(halt) = state.malformed() else { panic!("not tripped") };
+        assert_eq!(halt.check, PriceCheck::Malformed);
+        assert_eq!(state.trips[&PriceCheck::Malformed], 1);
+        assert_eq!(state.last_price, Some(PRICE));
+
+        assert!(matches!(state.evaluate(&config, PRICE, None), Verdict::Halted(_)));
+        assert!(matches!(state.malformed(), Verdict::Halted(_)));
+        assert_eq!(state.good_ticks, 0);
+    }
+
+    #[test]
+    fn test_resumes_from_elsewhere_are_adopted() {
+        let mut state = GuardState::default();
+        let config = PriceGuardConfig::default();
+        accepted(&mut state, PRICE);
+        let Verdict::Tripped(halt) = state.evaluate(&config, 5_000_000, None) else { panic!("not tripped") };
+
+        // A resume older than the halt, e.g. because sharing the halt failed, does not clear it
+        let mut stored = PriceGuardState {
+            pair: BTC_USD_PAIR.to_string(),
+            halt: None,
+            resumed_by: Some("ops".to_string()),
+            resume_reason: None,
+            resumed_at: Some(halt.tripped_at - chrono::Duration::seconds(1)),
+        };
+        state.adopt(&stored);
+        assert!(state.halt.is_some());
+
+        stored.resumed_at = Some(Utc::now());
+        state.adopt(&stored);
+        assert!(state.halt.is_none());
+        // An operator accepted the new price level
+        accepted(&mut state, 5_000_000);
+
+        stored.halt = Some(halt);
+        state.adopt(&stored);
+        assert!(state.halt.is_some());
+    }
+}
@@ -3,7 +3,19 @@ use serde::Deserialize;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::models::convert_money_from_string;
+use crate::database::{PriceHistoryRepository, PriceSnapshotRepository};
+use crate::models::{convert_money_from_string, InvoiceQuote, PriceSnapshot, PriceTick, BTC_USD_PAIR};
+use crate::services::price_guard::PriceGuard;
+use crate::shared::calculate_satoshis_for_usd_with_spread;
+
+/// Binance API the BTC price is fetched from
+pub const DEFAULT_PRICE_API_URL: &str = "https://api.binance.com/api/v3";
+
+/// How long a fetched BTC price is reused
+pub const PRICE_CACHE_EXPIRY: Duration = Duration::from_secs(30);
+
+/// Source recorded with prices fetched from the price API
+pub const PRICE_SOURCE: &str = "binance";
 
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
@@ -14,6 +26,25 @@ struct BinanceAvgPriceResponse {
     close_time: u64,
 }
 
+/// Why fetching the price failed
+#[derive(Debug)]
+enum FetchError {
+    Request(reqwest::Error),
+    /// The source answered a price that is not a number
+    Malformed(String),
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Request(e) => write!(f, "{}", e),
+            FetchError::Malformed(price) => write!(f, "malformed price {:?}", price),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
 #[derive(Debug, Clone)]
 struct CachedPrice {
     price: u128,
@@ -26,22 +57,81 @@ pub struct QuoteService {
     client: reqwest::Client,
     cached_bitcoin_price: Arc<Mutex<Option<CachedPrice>>>,
     cache_expiry_duration: Duration,
+    /// Snapshot shared with the other replicas, `None` to only cache the price in this process
+    snapshots: Option<PriceSnapshotRepository>,
+    /// Where every fetched price is recorded, `None` to discard them
+    history: Option<PriceHistoryRepository>,
+    /// Sanity checks fetched prices must pass, `None` to trust the price API
+    guard: Option<PriceGuard>,
 }
 
 impl QuoteService {
     pub fn new() -> Self {
-        tracing::info!("Initializing QuoteService with Binance API base URL: https://api.binance.com/api/v3");
+        Self::with_base_url(DEFAULT_PRICE_API_URL)
+    }
+
+    /// Service fetching prices from a Binance compatible API at `base_url`, such as a local fake server
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self::with_cache_expiry(base_url, PRICE_CACHE_EXPIRY)
+    }
+
+    /// A zero `cache_expiry` fetches the price on every call
+    pub fn with_cache_expiry(base_url: impl Into<String>, cache_expiry: Duration) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        tracing::info!("Initializing QuoteService with Binance API base URL: {}", base_url);
         Self {
-            base_url: "https://api.binance.com/api/v3".to_string(),
+            base_url,
             client: reqwest::Client::new(),
             cached_bitcoin_price: Arc::new(Mutex::new(None)),
-            cache_expiry_duration: Duration::from_secs(30), // 30 seconds as requested
+            cache_expiry_duration: cache_expiry,
+            snapshots: None,
+            history: None,
+            guard: None,
         }
     }
 
+    /// Record every price fetched from the API, to tell later which price was in effect
+    pub fn with_history(mut self, history: PriceHistoryRepository) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Quote from a price snapshot shared through the database, so replicas quote from the same tick.
+    /// The price API is only called once the snapshot is older than the cache expiry.
+    pub fn with_snapshots(mut self, snapshots: PriceSnapshotRepository) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+
+    /// Check every fetched price before it is used, recorded or shared. While a check is failed
+    /// no price is returned, so quotes and payments stop until the guard clears the halt.
+    pub fn with_guard(mut self, guard: PriceGuard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    pub fn price_guard(&self) -> Option<&PriceGuard> {
+        self.guard.as_ref()
+    }
+
+    /// Fails while the price guard halts payments, checking a fresh price once the last check is due
+    pub async fn ensure_not_halted(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.guard.as_ref().is_some_and(|guard| guard.halt().is_some()) {
+            self.get_bitcoin_price().await?;
+        }
+        Ok(())
+    }
+
     /// Get the current average Bitcoin price
     /// Returns the current average Bitcoin price in USDT (cents)
     pub async fn get_bitcoin_price(&self) -> Result<u128, Box<dyn std::error::Error + Send + Sync>> {
+        // A halted feed is checked again at the pace it is cached at
+        if let Some(guard) = &self.guard
+            && let Some(halt) = guard.halt_until_recheck(self.cache_expiry_duration)
+        {
+            return Err(Box::new(halt));
+        }
+
         let now = Instant::now();
 
         // Check if we have a cached price and it's not expired
@@ -64,19 +154,20 @@ impl QuoteService {
             }
         }
 
-        // Fetch fresh price
-        tracing::info!("Fetching fresh BTC price from Binance API");
-        let price = self.fetch_bitcoin_price().await.map_err(|e| {
-            tracing::error!("Failed to fetch Bitcoin price from API: {}", e);
-            Box::new(e) as Box<dyn std::error::Error + Send + Sync>
-        })?;
+        // While halted the price is fetched and checked here, even if another replica shared one
+        let halted = self.guard.as_ref().is_some_and(|guard| guard.halt().is_some());
+        let (price, age) = match &self.snapshots {
+            Some(snapshots) if !halted => self.shared_price(snapshots).await?,
+            _ => (self.fetch_fresh_price().await?, Duration::ZERO),
+        };
 
-        // Update the cache
+        // Update the cache, a shared price expires with its snapshot
         {
             let mut cached = self.cached_bitcoin_price.lock().unwrap();
+            let now = Instant::now();
             *cached = Some(CachedPrice {
                 price,
-                timestamp: Instant::now(),
+                timestamp: now.checked_sub(age).unwrap_or(now),
             });
             tracing::info!("Updated BTC price cache with new value: ${:.2}", price);
         }
@@ -84,7 +175,97 @@ impl QuoteService {
         Ok(price)
     }
 
-    async fn fetch_bitcoin_price(&self) -> Result<u128, reqwest::Error> {
+    async fn fetch_fresh_price(&self) -> Result<u128, Box<dyn std::error::Error + Send + Sync>> {
+        tracing::info!("Fetching fresh BTC price from Binance API");
+        let price = match self.fetch_bitcoin_price().await {
+            Ok(price) => price,
+            Err(e) => {
+                tracing::error!("Failed to fetch Bitcoin price from API: {}", e);
+                // A price that is not a number is a bad tick, not just a failed request
+                if let (FetchError::Malformed(_), Some(guard)) = (&e, &self.guard) {
+                    self.clear_cache();
+                    return Err(Box::new(guard.reject_malformed().await));
+                }
+                return Err(Box::new(e));
+            }
+        };
+
+        // A rejected price is neither recorded nor shared, and the cached one is dropped
+        if let Some(guard) = &self.guard
+            && let Err(halt) = guard.check(price).await
+        {
+            self.clear_cache();
+            return Err(Box::new(halt));
+        }
+
+        // Losing a tick only leaves a gap in the history, the price is still good to quote
+        if let Some(history) = &self.history
+            && let Err(e) = history.record(&PriceTick::new(BTC_USD_PAIR, price, PRICE_SOURCE)).await
+        {
+            tracing::warn!("Failed to record BTC price tick: {}", e);
+        }
+        Ok(price)
+    }
+
+    /// Price of the shared snapshot while it is fresh, otherwise fetched and shared.
+    /// Returns the price with its age. Database failures fall back to this process' own fetch.
+    async fn shared_price(
+        &self,
+        snapshots: &PriceSnapshotRepository,
+    ) -> Result<(u128, Duration), Box<dyn std::error::Error + Send + Sync>> {
+        match snapshots.find(BTC_USD_PAIR).await {
+            Ok(Some(snapshot)) if snapshot_age(&snapshot) < self.cache_expiry_duration => {
+                tracing::debug!("Using shared BTC price from {}", snapshot.fetched_at);
+                return Ok((snapshot.price, snapshot_age(&snapshot)));
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to read the shared BTC price, fetching it directly: {}", e),
+        }
+
+        let snapshot = PriceSnapshot {
+            pair: BTC_USD_PAIR.to_string(),
+            price: self.fetch_fresh_price().await?,
+            source: PRICE_SOURCE.to_string(),
+            fetched_at: bson::DateTime::now(),
+        };
+        match snapshots.replace_if_older(&snapshot, self.cache_expiry_duration).await {
+            // Another replica may have shared a price first, quote from that one
+            Ok(shared) => Ok((shared.price, snapshot_age(&shared))),
+            Err(e) => {
+                tracing::warn!("Failed to share the BTC price with other replicas: {}", e);
+                Ok((snapshot.price, Duration::ZERO))
+            }
+        }
+    }
+
+    /// Lock a quote for `usd_cents` at the current price, valid for `window`
+    /// but never past `deadline`
+    pub async fn lock_quote(
+        &self,
+        usd_cents: u128,
+        spread_bps: u32,
+        window: chrono::Duration,
+        deadline: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<InvoiceQuote, Box<dyn std::error::Error + Send + Sync>> {
+        let btc_price = self.get_bitcoin_price().await?;
+        let amount = calculate_satoshis_for_usd_with_spread(usd_cents, btc_price, spread_bps as u128);
+
+        let locked_at = chrono::Utc::now();
+        let mut expires_at = locked_at + window;
+        if let Some(deadline) = deadline {
+            expires_at = expires_at.min(deadline);
+        }
+
+        Ok(InvoiceQuote {
+            amount,
+            btc_price,
+            spread_bps,
+            locked_at,
+            expires_at,
+        })
+    }
+
+    async fn fetch_bitcoin_price(&self) -> Result<u128, FetchError> {
         let url = format!("{}/avgPrice", self.base_url);
         let query_params = [("symbol", "BTCUSDT")];
 
@@ -114,16 +295,16 @@ impl QuoteService {
                         );
 
                         // Parse the price string to u128
-                        let price = convert_money_from_string(avg_price_response.price).map_err(|parse_err| {
+                        let price = convert_money_from_string(avg_price_response.price.clone()).map_err(|parse_err| {
                             tracing::error!("Failed to convert price string to u128: {:?}", parse_err);
-                            panic!("Failed to parse price from Binance API - invalid number format");
+                            FetchError::Malformed(avg_price_response.price)
                         })?;
 
                         Ok(price)
                     }
                     Err(json_err) => {
                         tracing::error!("Failed to parse Binance API JSON response: {}", json_err);
-                        Err(json_err)
+                        Err(FetchError::Request(json_err))
                     }
                 }
             }
@@ -134,7 +315,7 @@ impl QuoteService {
                     query_params,
                     req_err
                 );
-                Err(req_err)
+                Err(FetchError::Request(req_err))
             }
         }
     }
@@ -166,6 +347,12 @@ impl QuoteService {
     }
 }
 
+fn snapshot_age(snapshot: &PriceSnapshot) -> Duration {
+    (chrono::Utc::now() - snapshot.fetched_at.to_chrono())
+        .to_std()
+        .unwrap_or(Duration::ZERO)
+}
+
 impl Default for QuoteService {
     fn default() -> Self {
         Self::new()
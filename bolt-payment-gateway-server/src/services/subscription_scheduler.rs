@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use axum::Json;
+use bson::oid::ObjectId;
+use chrono::Utc;
+
+use crate::handlers::invoices_handler::{expire_if_due, new_invoice, save_invoice};
+use crate::models::{
+    format_money_amount, Actor, AuditContext, BillingCycle, CreateInvoiceRequest, CycleStatus,
+    Invoice, InvoiceStatus, Subscription, SubscriptionNotice, SubscriptionNoticeKind, SubscriptionPlan,
+};
+use crate::services::leader_lease::LeaderLease;
+use crate::AppState;
+
+/// How often subscriptions are checked for periods to bill and cycles to close
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lease of the replica running the scheduler, another one takes over after missing two runs
+pub const SCHEDULER_LEASE_TTL: Duration = Duration::from_secs(3 * 60);
+
+/// Actor recorded in the audit trail of the invoices the scheduler creates
+const WORKER: &str = "subscription_scheduler";
+
+/// Creates an invoice for every new period of the subscriptions, through the regular invoice
+/// creation, and closes cycles once their invoice is paid or expired.
+#[derive(Clone)]
+pub struct SubscriptionScheduler {
+    app_state: AppState,
+    /// Only the replica holding the lease runs, every replica runs without one
+    lease: Option<LeaderLease>,
+}
+
+impl SubscriptionScheduler {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state, lease: None }
+    }
+
+    /// Run on whichever replica holds `lease`
+    pub fn with_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Some(lease) = &self.lease
+                && !lease.hold().await
+            {
+                continue;
+            }
+            if let Err(e) = self.tick().await {
+                tracing::warn!("Subscription scheduler failed to load subscriptions: {}", e);
+            }
+        }
+    }
+
+    async fn tick(&self) -> Result<()> {
+        let subscriptions = self.app_state.subscription_repository.find_billable().await?;
+        for subscription in subscriptions {
+            let id = subscription.id;
+            if let Err(e) = self.process(subscription).await {
+                tracing::warn!("Failed to process subscription {}: {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn process(&self, mut subscription: Subscription) -> Result<()> {
+        let plan = self
+            .app_state
+            .subscription_plan_repository
+            .find_by_id(&subscription.plan_id)
+            .await?
+            .ok_or_else(|| anyhow!("Plan {} not found", subscription.plan_id))?;
+
+        self.close_cycles(&mut subscription, &plan).await?;
+
+        if subscription.unpaid_cycles >= plan.max_unpaid_cycles {
+            let reason = format!("{} unpaid cycles in a row", subscription.unpaid_cycles);
+            if self.app_state.subscription_repository.cancel(&subscription.id, &reason).await? {
+                subscription.cancel(reason);
+                tracing::info!("Cancelled subscription {} after unpaid cycles", subscription.id);
+                self.notify(SubscriptionNoticeKind::SubscriptionCancelled, &subscription, None)
+                    .await;
+            }
+            return Ok(());
+        }
+
+        // One period per run, subscriptions behind after a downtime catch up over the next runs
+        if subscription.next_billing_at <= Utc::now() {
+            self.bill(&subscription, &plan).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Close the open cycles whose invoice got paid, expired or cancelled
+    async fn close_cycles(&self, subscription: &mut Subscription, plan: &SubscriptionPlan) -> Result<()> {
+        let open: Vec<BillingCycle> = subscription
+            .cycles
+            .iter()
+            .filter(|cycle| cycle.status == CycleStatus::Open)
+            .cloned()
+            .collect();
+
+        for cycle in open {
+            let Some(invoice) = self.app_state.invoice_repository.find_by_id(&cycle.invoice_id).await?
+            else {
+                // The period was claimed but its invoice could not be created, try again
+                if let Err(e) = self.create_invoice(subscription, plan, &cycle).await {
+                    tracing::warn!("Subscription {}: {}", subscription.id, e);
+                }
+                continue;
+            };
+            let invoice = expire_if_due(&self.app_state, invoice, None)
+                .await
+                .map_err(|(_, Json(error))| anyhow!(error.message))?;
+
+            let (outcome, kind) = match invoice.status {
+                InvoiceStatus::Paid | InvoiceStatus::Settled => {
+                    (CycleStatus::Paid, SubscriptionNoticeKind::CyclePaid)
+                }
+                InvoiceStatus::Expired | InvoiceStatus::Cancelled => {
+                    (CycleStatus::Overdue, SubscriptionNoticeKind::CycleOverdue)
+                }
+                InvoiceStatus::Created | InvoiceStatus::Pending => continue,
+            };
+
+            subscription.close_cycle(cycle.number, outcome);
+            if self.app_state.subscription_repository.close_cycle(subscription, cycle.number).await? {
+                let closed = BillingCycle { status: outcome, ..cycle };
+                self.notify(kind, subscription, Some(&closed)).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Claim the period starting at the subscription's next billing date and create its invoice
+    async fn bill(&self, subscription: &Subscription, plan: &SubscriptionPlan) -> Result<()> {
+        let period_start = subscription.next_billing_at;
+        let period_end = plan
+            .interval
+            .advance(period_start, plan.interval_count)
+            .ok_or_else(|| anyhow!("Period starting at {} has no end", period_start))?;
+
+        let cycle = BillingCycle {
+            number: subscription.cycles.last().map_or(1, |cycle| cycle.number + 1),
+            invoice_id: ObjectId::new(),
+            period_start,
+            period_end,
+            status: CycleStatus::Open,
+        };
+        // Another scheduler billed this period already
+        if !self.app_state.subscription_repository.claim_billing(&subscription.id, &cycle).await? {
+            return Ok(());
+        }
+
+        self.create_invoice(subscription, plan, &cycle).await
+    }
+
+    /// Create the invoice of a claimed cycle with the id recorded in the cycle
+    async fn create_invoice(
+        &self,
+        subscription: &Subscription,
+        plan: &SubscriptionPlan,
+        cycle: &BillingCycle,
+    ) -> Result<()> {
+        let number = cycle.number;
+        let request = CreateInvoiceRequest {
+            amount: format_money_amount(plan.amount),
+            settlement_asset: Some(plan.settlement_asset),
+            merchant_order_id: format!("{}-{}", subscription.id, number),
+            expires_in_secs: plan.payment_window_secs,
+            due_date: None,
+            description: plan.description.clone().or_else(|| Some(plan.name.clone())),
+            line_items: None,
+            metadata: None,
+            buyer_email: subscription.customer_email.clone(),
+            buyer_reference: subscription.customer_reference.clone(),
+        };
+        let context = AuditContext::new(Actor::System { worker: WORKER.to_string() }, None)
+            .with_reason(format!("Cycle {} of subscription {}", number, subscription.id));
+
+        let invoice = match new_invoice(&self.app_state, &subscription.wallet_address, request).await {
+            Ok(invoice) => {
+                let invoice = Invoice { id: cycle.invoice_id, subscription_id: Some(subscription.id), ..invoice };
+                save_invoice(&self.app_state, &invoice, &context).await.map(|_| invoice)
+            }
+            Err(e) => Err(e),
+        };
+        if let Err((_, Json(error))) = invoice {
+            // The cycle stays open without an invoice, the next run retries it
+            bail!("Failed to create the invoice of cycle {}: {}", number, error.message);
+        }
+
+        tracing::info!(
+            "Billed cycle {} of subscription {} with invoice {}",
+            number,
+            subscription.id,
+            cycle.invoice_id
+        );
+        self.notify(SubscriptionNoticeKind::InvoiceCreated, subscription, Some(cycle)).await;
+
+        Ok(())
+    }
+
+    /// Tell the merchant through their webhook and the customer by email, failures are only logged
+    async fn notify(
+        &self,
+        kind: SubscriptionNoticeKind,
+        subscription: &Subscription,
+        cycle: Option<&BillingCycle>,
+    ) {
+        let notice = SubscriptionNotice::new(kind, subscription, cycle);
+        let notifications = &self.app_state.notification_service;
+
+        match self
+            .app_state
+            .merchant_repository
+            .find_by_wallet_address(&subscription.wallet_address)
+            .await
+        {
+            Ok(Some(merchant)) => {
+                if let Some(url) = merchant.webhook_url
+                    && let Err(e) = notifications.send_webhook(&url, &notice).await
+                {
+                    tracing::warn!("Webhook for subscription {} failed: {}", subscription.id, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("Database error when retrieving merchant {}: {}", subscription.wallet_address, e)
+            }
+        }
+
+        let Some(email) = &subscription.customer_email else {
+            return;
+        };
+        let (subject, text) = match (kind, cycle) {
+            (SubscriptionNoticeKind::InvoiceCreated, Some(cycle)) => (
+                "Your new invoice is ready",
+                format!("Invoice {} for the period starting {} is awaiting payment.", cycle.invoice_id, cycle.period_start),
+            ),
+            (SubscriptionNoticeKind::CycleOverdue, Some(cycle)) => (
+                "Your invoice is overdue",
+                format!("Invoice {} for the period starting {} was not paid in time.", cycle.invoice_id, cycle.period_start),
+            ),
+            (SubscriptionNoticeKind::SubscriptionCancelled, _) => (
+                "Your subscription was cancelled",
+                format!("Subscription {} was cancelled after too many unpaid invoices.", subscription.id),
+            ),
+            _ => return,
+        };
+        if let Err(e) = notifications.send_email(email, subject, &text).await {
+            tracing::warn!("Email for subscription {} failed: {}", subscription.id, e);
+        }
+    }
+}
+
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::models::PaymentToken;
+
+/// sBTC token contract used when none is configured.
+pub const DEFAULT_SBTC_CONTRACT: &str = "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF.sbtc-token";
+
+/// Bolt protocol contract customers transfer through when none is configured.
+pub const DEFAULT_BOLT_PROTOCOL_CONTRACT: &str =
+    "ST3QZNX3CGT6V7PE1PBK17FCRK1TP1AT02W1N0YJF.boltproto-sbtc-rc-2-0-0";
+
+/// Fee in satoshis the Bolt protocol charges to sponsor a transfer.
+pub const DEFAULT_BOLT_TRANSFER_FEE: u128 = 2000;
+
+/// Function of the protocol contract moving tokens between Bolt wallets.
+pub const BOLT_TRANSFER_FUNCTION: &str = "transfer-bolt-to-bolt";
+
+/// On-chain details of a token customers can pay with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenContract {
+    /// `<address>.<name>` of the token contract.
+    pub contract_id: String,
+    pub decimals: u8,
+    /// `<address>.<name>` of the protocol contract the transfer goes through.
+    pub transfer_contract_id: String,
+    pub transfer_function: String,
+    /// Sponsoring fee in the token's base unit, on top of the amount due.
+    pub fee: u128,
+}
+
+/// Contracts of the payment tokens the gateway knows how to receive.
+#[derive(Debug, Clone)]
+pub struct TokenRegistry {
+    contracts: HashMap<PaymentToken, TokenContract>,
+}
+
+impl TokenRegistry {
+    pub fn new(sbtc_contract: String, bolt_protocol_contract: String, transfer_fee: u128) -> Self {
+        let mut contracts = HashMap::new();
+        contracts.insert(
+            PaymentToken::SBTC,
+            TokenContract {
+                contract_id: sbtc_contract,
+                decimals: 8,
+                transfer_contract_id: bolt_protocol_contract,
+                transfer_function: BOLT_TRANSFER_FUNCTION.to_string(),
+                fee: transfer_fee,
+            },
+        );
+        Self { contracts }
+    }
+
+    /// `None` for tokens with no contract configured
+    pub fn contract(&self, token: PaymentToken) -> Option<&TokenContract> {
+        self.contracts.get(&token)
+    }
+}
+
+impl Default for TokenRegistry {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_SBTC_CONTRACT.to_string(),
+            DEFAULT_BOLT_PROTOCOL_CONTRACT.to_string(),
+            DEFAULT_BOLT_TRANSFER_FEE,
+        )
+    }
+}
@@ -1,3 +1,7 @@
+pub mod rate_limit;
+pub mod request_id;
 pub mod util;
 
-pub use util::*;
\ No newline at end of file
+pub use rate_limit::*;
+pub use request_id::*;
+pub use util::*;
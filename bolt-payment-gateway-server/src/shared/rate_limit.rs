@@ -0,0 +1,407 @@
+// src/shared/rate_limit.rs
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use axum::{
+    extract::ConnectInfo,
+    http::{header, HeaderValue, Method, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::{ready, Either, Ready};
+use tower::{Layer, Service};
+
+use crate::models::ErrorResponse;
+
+/// Idle buckets are dropped every this many requests, so the table does not grow with every client seen
+const SWEEP_EVERY: u64 = 1024;
+
+/// What requests of a route group are counted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// The client's address, from `X-Forwarded-For` when proxies in front are trusted.
+    ClientIp,
+    /// The client IP per `{wallet_address}` of `/merchants/{wallet_address}/...` paths, the client IP elsewhere.
+    /// The routes are unauthenticated, so a wallet alone would let anyone use up a merchant's limit.
+    MerchantWallet,
+}
+
+impl std::str::FromStr for RateLimitKey {
+    type Err = anyhow::Error;
+
+    fn from_str(key: &str) -> Result<Self> {
+        match key {
+            "ip" => Ok(RateLimitKey::ClientIp),
+            "merchant" => Ok(RateLimitKey::MerchantWallet),
+            _ => bail!("unknown rate limit key {}, expected ip or merchant", key),
+        }
+    }
+}
+
+/// Routes sharing a limit, e.g. every payment submission.
+/// A client may send `burst` requests at once, after which requests are refilled at `burst` per `period`.
+#[derive(Debug, Clone)]
+pub struct RouteGroup {
+    pub name: String,
+    /// Method and path pattern of each route, `*` matches one path segment and a trailing `**` the rest.
+    /// `None` matches every method.
+    pub routes: Vec<(Option<Method>, String)>,
+    pub key: RateLimitKey,
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RouteGroup {
+    pub fn new(name: &str, key: RateLimitKey, burst: u32, period: Duration) -> Self {
+        Self { name: name.to_string(), routes: Vec::new(), key, burst, period }
+    }
+
+    pub fn route(mut self, method: Option<Method>, pattern: &str) -> Self {
+        self.routes.push((method, pattern.to_string()));
+        self
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        self.routes.iter().any(|(route_method, pattern)| {
+            route_method.as_ref().is_none_or(|route_method| route_method == method)
+                && path_matches(pattern, path)
+        })
+    }
+
+    /// Apply a `<requests>/<seconds>[,<key>]` setting
+    fn apply(&mut self, setting: &str) -> Result<()> {
+        let (limit, key) = match setting.split_once(',') {
+            Some((limit, key)) => (limit, Some(key.trim())),
+            None => (setting, None),
+        };
+        let (burst, seconds) = limit
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| anyhow!("expected <requests>/<seconds>, got {}", setting))?;
+        let burst: u32 = burst.trim().parse().map_err(|_| anyhow!("invalid request count {}", burst))?;
+        let seconds: u64 = seconds.trim().parse().map_err(|_| anyhow!("invalid period {}", seconds))?;
+        if burst == 0 || seconds == 0 {
+            bail!("request count and period must be positive, use off to disable the limit");
+        }
+
+        self.burst = burst;
+        self.period = Duration::from_secs(seconds);
+        if let Some(key) = key {
+            self.key = key.parse()?;
+        }
+        Ok(())
+    }
+
+    /// Tokens refilled per second
+    fn rate(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// Route groups of the gateway's public API, and where client addresses come from.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Prefix the API routes are nested under, stripped before matching patterns.
+    pub prefix: String,
+    /// Number of proxies in front of the gateway appending to `X-Forwarded-For`. The client IP is the
+    /// address the outermost of them received the request from, earlier entries are set by the client.
+    /// `0` uses the connecting address.
+    pub trusted_proxies: usize,
+    /// Checked in order, a request counts against the first group it matches.
+    pub groups: Vec<RouteGroup>,
+}
+
+impl RateLimitConfig {
+    /// Default limits for the unauthenticated routes that cost the most: payment submissions
+    /// broadcast transactions, quotes may hit the price API.
+    pub fn new(prefix: &str) -> Self {
+        let minute = Duration::from_secs(60);
+        Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            trusted_proxies: 0,
+            groups: vec![
+                RouteGroup::new("payments", RateLimitKey::ClientIp, 10, minute)
+                    .route(Some(Method::POST), "/invoices/*/payments/submit"),
+                RouteGroup::new("quotes", RateLimitKey::ClientIp, 30, minute)
+                    .route(Some(Method::GET), "/quotes")
+                    .route(Some(Method::GET), "/invoices/*/quote"),
+                RouteGroup::new("merchants", RateLimitKey::ClientIp, 120, minute)
+                    .route(None, "/merchants/**"),
+            ],
+        }
+    }
+
+    /// Defaults overridden from the environment. `RATE_LIMIT_<GROUP>` is `<requests>/<seconds>`,
+    /// optionally followed by `,ip` or `,merchant` to change the key, or `off`.
+    /// `RATE_LIMIT_TRUSTED_PROXIES=<n>` reads client IPs from `X-Forwarded-For` behind `n` proxies.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        let mut config = Self::new(prefix);
+        if let Ok(value) = std::env::var("RATE_LIMIT_TRUSTED_PROXIES") {
+            config.trusted_proxies = value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid RATE_LIMIT_TRUSTED_PROXIES: {}", value))?;
+        }
+
+        let mut groups = Vec::new();
+        for mut group in config.groups {
+            let variable = format!("RATE_LIMIT_{}", group.name.to_uppercase());
+            if let Ok(value) = std::env::var(&variable) {
+                if value.trim() == "off" {
+                    continue;
+                }
+                group.apply(&value).map_err(|e| anyhow!("Invalid {}: {}", variable, e))?;
+            }
+            groups.push(group);
+        }
+        config.groups = groups;
+        Ok(config)
+    }
+}
+
+/// The address `trusted_proxies` hops from the right of an `X-Forwarded-For` value, each proxy
+/// appending the address it received the request from. Shorter values only hold proxy entries.
+fn forwarded_client(forwarded_for: &str, trusted_proxies: usize) -> Option<String> {
+    let addresses: Vec<&str> = forwarded_for.split(',').map(str::trim).collect();
+    let client = addresses[addresses.len().saturating_sub(trusted_proxies)..].first()?;
+    (!client.is_empty()).then(|| client.to_string())
+}
+
+/// Whether `path` matches `pattern`, see `RouteGroup::routes`
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut segments = path.trim_matches('/').split('/');
+    for expected in pattern.trim_matches('/').split('/') {
+        if expected == "**" {
+            return true;
+        }
+        match segments.next() {
+            Some(segment) if expected == "*" || expected == segment => {}
+            _ => return false,
+        }
+    }
+    segments.next().is_none()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Take a token, or return how long until one is available
+    fn take(&mut self, group: &RouteGroup, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * group.rate()).min(group.burst as f64);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / group.rate()))
+        }
+    }
+
+    /// Refilled to the burst, the same as a bucket that does not exist
+    fn is_full(&self, group: &RouteGroup, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * group.rate() >= group.burst as f64
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    /// Keyed by group index and client key
+    buckets: HashMap<(usize, String), Bucket>,
+    requests: u64,
+}
+
+/// Token-bucket rate limiting per route group, answering `429 Too Many Requests` with `Retry-After`.
+/// Buckets live in memory, so every replica enforces the limits on its own.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    config: Arc<RateLimitConfig>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config: Arc::new(config), buckets: Arc::default() }
+    }
+
+    /// Count a request, returns how long the client has to wait when it is over the limit
+    fn check<B>(&self, request: &Request<B>, now: Instant) -> Option<(&RouteGroup, Duration)> {
+        let path = request.uri().path();
+        let path = path.strip_prefix(self.config.prefix.as_str()).unwrap_or(path);
+        let (index, group) = self
+            .config
+            .groups
+            .iter()
+            .enumerate()
+            .find(|(_, group)| group.matches(request.method(), path))?;
+        let key = self.key(group.key, request, path);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.requests += 1;
+        if buckets.requests.is_multiple_of(SWEEP_EVERY) {
+            let groups = &self.config.groups;
+            buckets.buckets.retain(|(index, _), bucket| !bucket.is_full(&groups[*index], now));
+        }
+
+        let bucket = buckets
+            .buckets
+            .entry((index, key))
+            .or_insert(Bucket { tokens: group.burst as f64, updated: now });
+        bucket.take(group, now).err().map(|wait| (group, wait))
+    }
+
+    fn key<B>(&self, key: RateLimitKey, request: &Request<B>, path: &str) -> String {
+        match key {
+            RateLimitKey::MerchantWallet => match path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..] {
+                ["merchants", wallet_address, ..] => {
+                    format!("merchant:{}:{}", wallet_address, self.client_ip(request))
+                }
+                _ => self.client_ip(request),
+            },
+            RateLimitKey::ClientIp => self.client_ip(request),
+        }
+    }
+
+    fn client_ip<B>(&self, request: &Request<B>) -> String {
+        let forwarded = (self.config.trusted_proxies > 0)
+            .then(|| request.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| forwarded_client(value, self.config.trusted_proxies));
+        let connected = || {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        };
+        format!("ip:{}", forwarded.or_else(connected).unwrap_or_else(|| "unknown".to_string()))
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit { inner, limiter: self.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: RateLimitLayer,
+}
+
+impl<S, B> Service<Request<B>> for RateLimit<S>
+where
+    S: Service<Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Response, S::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        match self.limiter.check(&request, Instant::now()) {
+            Some((group, wait)) => {
+                tracing::info!("Rate limited {} {} ({} group)", request.method(), request.uri().path(), group.name);
+                Either::Left(ready(Ok(too_many_requests(wait))))
+            }
+            None => Either::Right(self.inner.call(request)),
+        }
+    }
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse {
+            error: "rate_limited".to_string(),
+            message: format!("Too many requests, retry in {} seconds", retry_after),
+        }),
+    )
+        .into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_patterns() {
+        assert!(path_matches("/invoices/*/payments/submit", "/invoices/abc/payments/submit"));
+        assert!(!path_matches("/invoices/*/payments/submit", "/invoices/abc/payments"));
+        assert!(!path_matches("/invoices/*/quote", "/invoices/abc/quote/extra"));
+        assert!(path_matches("/merchants/**", "/merchants/ST1/invoices"));
+        assert!(path_matches("/quotes", "/quotes/"));
+        assert!(!path_matches("/quotes", "/invoices"));
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let group = RouteGroup::new("test", RateLimitKey::ClientIp, 2, Duration::from_secs(10));
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 2.0, updated: start };
+
+        assert!(bucket.take(&group, start).is_ok());
+        assert!(bucket.take(&group, start).is_ok());
+        assert_eq!(bucket.take(&group, start), Err(Duration::from_secs(5)));
+        assert!(bucket.take(&group, start + Duration::from_secs(5)).is_ok());
+        assert!(bucket.is_full(&group, start + Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn test_forwarded_client_skips_client_set_entries() {
+        assert_eq!(forwarded_client("6.6.6.6, 1.2.3.4", 1).as_deref(), Some("1.2.3.4"));
+        assert_eq!(forwarded_client("6.6.6.6, 1.2.3.4, 10.0.0.2", 2).as_deref(), Some("1.2.3.4"));
+        assert_eq!(forwarded_client("1.2.3.4", 2).as_deref(), Some("1.2.3.4"));
+        assert_eq!(forwarded_client("6.6.6.6, ", 1), None);
+    }
+
+    #[test]
+    fn test_merchant_routes_are_limited_per_client() {
+        let layer = RateLimitLayer::new(RateLimitConfig { trusted_proxies: 1, ..RateLimitConfig::new("") });
+        let request = |wallet: &str, ip: &str| {
+            Request::get(format!("/merchants/{}/invoices", wallet))
+                .header("x-forwarded-for", ip)
+                .body(())
+                .unwrap()
+        };
+        let group = &layer.config.groups[2];
+        let key = |wallet, ip| layer.key(group.key, &request(wallet, ip), &format!("/merchants/{}/invoices", wallet));
+
+        // Made-up wallets do not escape the limit, and no client can use up a merchant's
+        assert_eq!(key("ST1A", "1.2.3.4"), key("ST1B", "1.2.3.4"));
+        assert_ne!(key("ST1A", "1.2.3.4"), key("ST1A", "5.6.7.8"));
+
+        let path = "/merchants/ST1A/invoices";
+        assert_eq!(layer.key(RateLimitKey::MerchantWallet, &request("ST1A", "1.2.3.4"), path), "merchant:ST1A:ip:1.2.3.4");
+    }
+
+    #[test]
+    fn test_group_settings() {
+        let mut group = RouteGroup::new("test", RateLimitKey::ClientIp, 2, Duration::from_secs(10));
+        group.apply("5/60,merchant").unwrap();
+        assert_eq!((group.burst, group.period, group.key), (5, Duration::from_secs(60), RateLimitKey::MerchantWallet));
+
+        for invalid in ["5", "0/60", "5/x", "5/60,api_key"] {
+            assert!(group.apply(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
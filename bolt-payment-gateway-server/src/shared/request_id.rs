@@ -0,0 +1,25 @@
+// src/shared/request_id.rs
+use std::convert::Infallible;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+/// Header carrying the per-request ID set by `SetRequestIdLayer` in `main.rs`.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Extracts the request ID so it can be recorded alongside the changes a request makes.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let request_id = parts
+            .headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(RequestId(request_id))
+    }
+}
@@ -9,7 +9,9 @@
 ///
 /// # Example
 /// ```
-/// let satoshis = calculate_satoshis_for_usd(10000, 6000000000); // $100 USD at $60,000 BTC
+/// # use bolt_payment_gateway_server::shared::calculate_satoshis_for_usd;
+/// let satoshis = calculate_satoshis_for_usd(10000, 6000000); // $100 USD at $60,000 BTC
+/// assert_eq!(satoshis, 166_666);
 /// ```
 pub fn calculate_satoshis_for_usd(usd_cents: u128, btc_price_usd_cents: u128) -> u128 {
     // 1 BTC = 100,000,000 satoshis
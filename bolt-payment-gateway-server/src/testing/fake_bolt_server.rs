@@ -0,0 +1,247 @@
+// src/testing/fake_bolt_server.rs
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::services::bolt_protocol_service::{
+    BroadcastTransactionRequest, BroadcastTransactionResponse, TransactionStatus, TransactionStatusResponse,
+};
+
+/// Sender reported for accepted transactions unless a behavior sets one.
+pub const FAKE_SENDER_ADDRESS: &str = "ST1FAKESENDER000000000000000000000000000";
+
+/// What the fake Bolt server does with a broadcast request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "behavior", rename_all = "snake_case")]
+pub enum FakeBoltBehavior {
+    /// Accept the transaction, echoing the requested amount unless `amount` overrides it.
+    Accept {
+        #[serde(default)]
+        txid: Option<String>,
+        #[serde(default)]
+        sender: Option<String>,
+        #[serde(default)]
+        amount: Option<String>,
+    },
+    /// Answer with an error status and raw body.
+    Fail { status: u16, body: String },
+    /// Answer 200 with a body that is not a broadcast response.
+    Malformed { body: String },
+    /// Wait before accepting, to trigger client timeouts.
+    Hang { millis: u64 },
+}
+
+impl FakeBoltBehavior {
+    pub fn accept() -> Self {
+        FakeBoltBehavior::Accept { txid: None, sender: None, amount: None }
+    }
+}
+
+#[derive(Debug)]
+struct FakeBoltState {
+    /// Behaviors for the next requests, in order.
+    script: VecDeque<FakeBoltBehavior>,
+    /// Behavior once the script is exhausted.
+    default: FakeBoltBehavior,
+    requests: Vec<BroadcastTransactionRequest>,
+    /// Transactions looked up by txid, accepted broadcasts succeed unless set otherwise.
+    transactions: HashMap<String, TransactionStatusResponse>,
+}
+
+/// Local stand-in for the Bolt protocol API, implementing `/api/v1/transaction/bolt/broadcast`
+/// and the `/api/v1/transaction/{txid}` lookup.
+/// Behaviors are scripted through the methods below, or over HTTP under `/__fake` when run as a binary:
+/// `POST /__fake/script` queues behaviors, `PUT /__fake/default` sets the fallback,
+/// `PUT /__fake/transactions` sets what a txid lookup answers, `GET /__fake/requests` lists the broadcasts
+/// received and `DELETE /__fake` resets everything.
+pub struct FakeBoltServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<FakeBoltState>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl FakeBoltServer {
+    /// Start on a random local port, accepting every transaction
+    pub async fn start() -> std::io::Result<Self> {
+        Self::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+
+    pub async fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(FakeBoltState {
+            script: VecDeque::new(),
+            default: FakeBoltBehavior::accept(),
+            requests: Vec::new(),
+            transactions: HashMap::new(),
+        }));
+
+        let app = Router::new()
+            .route("/api/v1/transaction/bolt/broadcast", post(broadcast))
+            .route("/api/v1/transaction/{txid}", get(transaction))
+            .route("/__fake/script", post(push_script))
+            .route("/__fake/default", put(set_default))
+            .route("/__fake/transactions", put(put_transaction))
+            .route("/__fake/requests", get(list_requests))
+            .route("/__fake", delete(reset))
+            .with_state(state.clone());
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Fake Bolt server stopped: {}", e);
+            }
+        });
+
+        Ok(Self { addr, state, task: Some(task) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL to configure `BoltProtocolService` with
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Queue a behavior for the next unscripted request
+    pub fn push(&self, behavior: FakeBoltBehavior) {
+        self.state.lock().unwrap().script.push_back(behavior);
+    }
+
+    pub fn set_default(&self, behavior: FakeBoltBehavior) {
+        self.state.lock().unwrap().default = behavior;
+    }
+
+    /// Broadcast requests received so far, oldest first
+    pub fn requests(&self) -> Vec<BroadcastTransactionRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Answer `transaction` to lookups of its txid, e.g. for a broadcast whose answer was lost
+    pub fn set_transaction(&self, transaction: TransactionStatusResponse) {
+        self.state.lock().unwrap().transactions.insert(transaction.txid.clone(), transaction);
+    }
+
+    /// Serve until the process stops, for the standalone binary
+    pub async fn wait(mut self) {
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for FakeBoltServer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+async fn broadcast(
+    State(state): State<Arc<Mutex<FakeBoltState>>>,
+    Json(request): Json<BroadcastTransactionRequest>,
+) -> Response {
+    let behavior = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let default = state.default.clone();
+        state.script.pop_front().unwrap_or(default)
+    };
+
+    match behavior {
+        FakeBoltBehavior::Accept { txid, sender, amount } => accepted(&state, &request, txid, sender, amount),
+        FakeBoltBehavior::Fail { status, body } => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, body).into_response()
+        }
+        FakeBoltBehavior::Malformed { body } => {
+            ([("content-type", "application/json")], body).into_response()
+        }
+        FakeBoltBehavior::Hang { millis } => {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            accepted(&state, &request, None, None, None)
+        }
+    }
+}
+
+fn accepted(
+    state: &Mutex<FakeBoltState>,
+    request: &BroadcastTransactionRequest,
+    txid: Option<String>,
+    sender: Option<String>,
+    amount: Option<String>,
+) -> Response {
+    let response = BroadcastTransactionResponse {
+        txid: txid.unwrap_or_else(|| format!("0x{}", uuid::Uuid::new_v4().simple())),
+        fee: 0.0,
+        sender: sender.unwrap_or_else(|| FAKE_SENDER_ADDRESS.to_string()),
+        amount: amount.unwrap_or_else(|| request.amount.clone()),
+    };
+    let transaction = TransactionStatusResponse {
+        txid: response.txid.clone(),
+        status: TransactionStatus::Success,
+        sender: response.sender.clone(),
+        amount: response.amount.clone(),
+    };
+    state.lock().unwrap().transactions.entry(transaction.txid.clone()).or_insert(transaction);
+    Json(response).into_response()
+}
+
+async fn transaction(State(state): State<Arc<Mutex<FakeBoltState>>>, Path(txid): Path<String>) -> Response {
+    match state.lock().unwrap().transactions.get(&txid) {
+        Some(transaction) => Json(transaction.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn push_script(
+    State(state): State<Arc<Mutex<FakeBoltState>>>,
+    Json(behaviors): Json<Vec<FakeBoltBehavior>>,
+) -> StatusCode {
+    state.lock().unwrap().script.extend(behaviors);
+    StatusCode::NO_CONTENT
+}
+
+async fn set_default(
+    State(state): State<Arc<Mutex<FakeBoltState>>>,
+    Json(behavior): Json<FakeBoltBehavior>,
+) -> StatusCode {
+    state.lock().unwrap().default = behavior;
+    StatusCode::NO_CONTENT
+}
+
+async fn put_transaction(
+    State(state): State<Arc<Mutex<FakeBoltState>>>,
+    Json(transaction): Json<TransactionStatusResponse>,
+) -> StatusCode {
+    state.lock().unwrap().transactions.insert(transaction.txid.clone(), transaction);
+    StatusCode::NO_CONTENT
+}
+
+async fn list_requests(
+    State(state): State<Arc<Mutex<FakeBoltState>>>,
+) -> Json<Vec<BroadcastTransactionRequest>> {
+    Json(state.lock().unwrap().requests.clone())
+}
+
+async fn reset(State(state): State<Arc<Mutex<FakeBoltState>>>) -> StatusCode {
+    let mut state = state.lock().unwrap();
+    state.script.clear();
+    state.default = FakeBoltBehavior::accept();
+    state.requests.clear();
+    state.transactions.clear();
+    StatusCode::NO_CONTENT
+}
@@ -0,0 +1,102 @@
+// src/testing/fake_price_server.rs
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+#[derive(Debug)]
+struct FakePriceState {
+    /// BTC price in USD, as Binance formats it.
+    price: String,
+    /// Status answered instead of the price while set.
+    failure: Option<u16>,
+    requests: usize,
+}
+
+/// Local stand-in for the Binance `/avgPrice` endpoint `QuoteService` reads the BTC price from.
+pub struct FakePriceServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<FakePriceState>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl FakePriceServer {
+    /// Start on a random local port, answering `price` (in USD, e.g. `"100000.00"`)
+    pub async fn start(price: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(FakePriceState {
+            price: price.to_string(),
+            failure: None,
+            requests: 0,
+        }));
+
+        let app = Router::new()
+            .route("/avgPrice", get(avg_price))
+            .with_state(state.clone());
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Fake price server stopped: {}", e);
+            }
+        });
+
+        Ok(Self { addr, state, task: Some(task) })
+    }
+
+    /// Base URL to configure `QuoteService` with
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Answer `price` from now on, clearing any failure
+    pub fn set_price(&self, price: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.price = price.to_string();
+        state.failure = None;
+    }
+
+    /// Answer `status` until the next `set_price`
+    pub fn fail(&self, status: u16) {
+        self.state.lock().unwrap().failure = Some(status);
+    }
+
+    /// Number of price requests received so far
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+}
+
+impl Drop for FakePriceServer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+async fn avg_price(State(state): State<Arc<Mutex<FakePriceState>>>) -> Response {
+    let mut state = state.lock().unwrap();
+    state.requests += 1;
+
+    if let Some(status) = state.failure {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        return (status, "price feed unavailable").into_response();
+    }
+
+    Json(json!({
+        "mins": 5,
+        "price": state.price,
+        "closeTime": Utc::now().timestamp_millis(),
+    }))
+    .into_response()
+}
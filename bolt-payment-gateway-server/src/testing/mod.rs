@@ -0,0 +1,6 @@
+// src/testing/mod.rs
+pub mod fake_bolt_server;
+pub mod fake_price_server;
+
+pub use fake_bolt_server::*;
+pub use fake_price_server::*;
@@ -0,0 +1,123 @@
+// tests/admin.rs
+mod common;
+
+use axum::http::StatusCode;
+use bson::oid::ObjectId;
+use chrono::Utc;
+use serde_json::json;
+
+use bolt_payment_gateway_server::models::{
+    Actor, AuditContext, InvoiceStatus, Payment, PaymentStatus, PaymentToken,
+};
+use bolt_payment_gateway_server::services::admin_service::{AdminService, STUCK_PAYMENT_AGE};
+
+use common::{TestApp, MERCHANT};
+
+/// An invoice claimed by a payment whose broadcast never finished
+async fn stuck_payment(app: &TestApp, accepted_at: chrono::DateTime<Utc>) -> (ObjectId, ObjectId) {
+    let invoice_id = ObjectId::parse_str(app.create_invoice("10.00").await).unwrap();
+    let invoice = app.state.invoice_repository.find_by_id(&invoice_id).await.unwrap().unwrap();
+    let context = AuditContext::new(Actor::Customer { address: None }, None);
+    app.state.invoice_repository.claim_for_payment(&invoice, &context).await.unwrap();
+
+    let payment = Payment {
+        received_at: accepted_at,
+        ..Payment::new(invoice_id, PaymentToken::SBTC, 20_000)
+    };
+    app.state.payment_repository.create(&payment, &context).await.unwrap();
+    (invoice_id, payment.id)
+}
+
+#[tokio::test]
+async fn test_reject_stuck_payment_releases_invoice() {
+    let Some(app) = TestApp::start().await else { return };
+    let admin = AdminService::new(app.state.clone(), "ops");
+
+    let (invoice_id, payment_id) = stuck_payment(&app, Utc::now()).await;
+    assert!(admin.reject_payment(&payment_id, None, false).await.is_err());
+    assert!(admin.expire_invoice(&invoice_id, None).await.is_err());
+
+    let (invoice_id, payment_id) = stuck_payment(&app, Utc::now() - STUCK_PAYMENT_AGE).await;
+    let payment = admin.reject_payment(&payment_id, Some("node lost the tx"), false).await.unwrap();
+    assert_eq!(payment.status, PaymentStatus::Rejected);
+
+    let details = admin.invoice(&invoice_id).await.unwrap();
+    assert_eq!(details.invoice.status, InvoiceStatus::Created);
+    let last = details.timeline.last().unwrap();
+    assert_eq!(last.actor, Actor::Admin { operator: "ops".to_string() });
+    assert_eq!(last.reason.as_deref(), Some("node lost the tx"));
+
+    let invoice = admin.expire_invoice(&invoice_id, Some("customer left")).await.unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Expired);
+    assert!(admin.cancel_invoice(&invoice_id, None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_confirm_broadcast_payment_pays_invoice() {
+    let Some(app) = TestApp::start().await else { return };
+    let admin = AdminService::new(app.state.clone(), "ops");
+
+    let (invoice_id, payment_id) = stuck_payment(&app, Utc::now()).await;
+    let payment = admin
+        .confirm_payment(&payment_id, "0xfeed", "ST1SENDER", Some("broadcast found on chain"))
+        .await
+        .unwrap();
+    assert_eq!(payment.status, PaymentStatus::Confirmed);
+    assert_eq!(payment.tx_id.as_deref(), Some("0xfeed"));
+    assert_eq!(admin.invoice(&invoice_id).await.unwrap().invoice.status, InvoiceStatus::Paid);
+
+    // Settling it again is harmless, another transaction is refused
+    assert!(admin.confirm_payment(&payment_id, "0xfeed", "ST1SENDER", None).await.is_ok());
+    assert!(admin.confirm_payment(&payment_id, "0xbeef", "ST1SENDER", None).await.is_err());
+    assert!(admin.reject_payment(&payment_id, None, true).await.is_err());
+}
+
+#[tokio::test]
+async fn test_export_merchant() {
+    let Some(app) = TestApp::start().await else { return };
+    let admin = AdminService::new(app.state.clone(), "ops");
+    let paid = app.create_invoice("10.00").await;
+    app.create_invoice("5.00").await;
+    app.submit_payment(&paid, "20000").await;
+
+    let export = admin.export_merchant(MERCHANT).await.unwrap();
+    assert_eq!(export.invoices.len(), 2);
+    assert_eq!(export.payments.len(), 1);
+    assert!(export.merchant.is_none());
+
+    admin.record("export ST1MERCHANTTEST", Some(MERCHANT), None, None).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_verified_payout_address_receives_payments() {
+    let Some(app) = TestApp::start().await else { return };
+    let admin = AdminService::new(app.state.clone(), "ops");
+    let (status, body) = app
+        .post(
+            "/merchants",
+            json!({
+                "wallet_address": MERCHANT,
+                "display_name": "Self custody",
+                "payout_address": "ST1PAYOUT",
+                "custody_mode": "self_custody",
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = app
+        .post(&format!("/merchants/{}/invoices", MERCHANT), json!({ "amount": "10.00", "merchant_order_id": "ORD-1" }))
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    assert_eq!(body["error"], "payout_address_unverified");
+
+    // Only the address that was checked gets verified
+    assert!(admin.verify_payout_address(MERCHANT, "ST1OTHER").await.is_err());
+    let merchant = admin.verify_payout_address(MERCHANT, "ST1PAYOUT").await.unwrap();
+    assert!(merchant.payout_address_verified);
+
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.bolt.requests()[0].recipient_address, "ST1PAYOUT");
+}
@@ -0,0 +1,99 @@
+// tests/analytics.rs
+mod common;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use serde_json::json;
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_analytics_rejects_invalid_ranges() {
+    let app = TestApp::without_storage().await;
+    let uri = format!("/merchants/{}/analytics", MERCHANT);
+    for (query, error) in [
+        ("from=2026-02-01T00:00:00Z&to=2026-01-01T00:00:00Z", "invalid_range"),
+        ("from=2020-01-01T00:00:00Z&to=2026-01-01T00:00:00Z", "invalid_range"),
+        ("timezone=%20", "invalid_timezone"),
+    ] {
+        let (status, body) = app.get(&format!("{}?{}", uri, query)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{} {}", query, body);
+        assert_eq!(body["error"], error, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_analytics_totals_and_series() {
+    let Some(app) = TestApp::start().await else { return };
+    let paid = app.create_invoice("10.00").await;
+    let (status, body) = app.submit_payment(&paid, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let cancelled = app.create_invoice("5.00").await;
+    let (status, body) = app.post(&format!("/invoices/{}/cancel", cancelled), json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    app.create_invoice("2.50").await;
+
+    let uri = format!("/merchants/{}/analytics", MERCHANT);
+    let (status, body) = app.get(&uri).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["granularity"], "day");
+    assert_eq!(body["timezone"], "UTC");
+
+    let totals = body["totals"].as_array().unwrap();
+    assert_eq!(totals.len(), 1, "{}", body);
+    let usd = &totals[0];
+    assert_eq!(usd["settlement_asset"], "USD");
+    assert_eq!((usd["created"].as_i64(), usd["paid"].as_i64()), (Some(3), Some(1)));
+    assert_eq!((usd["expired"].as_i64(), usd["cancelled"].as_i64()), (Some(0), Some(1)));
+    assert!((usd["conversion_rate"].as_f64().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+    assert!(usd["average_time_to_pay_secs"].as_f64().unwrap() >= 0.0);
+    assert_eq!(usd["gross_volume"], "10.00");
+    assert_eq!(usd["sbtc_received"], "20000");
+    assert_eq!(usd["effective_exchange_rate"], "50000.00");
+
+    // Only paid invoices make up the series, bucketed by the day they were paid
+    let series = body["series"].as_array().unwrap();
+    assert_eq!(series.len(), 1, "{}", body);
+    let today = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+    assert_eq!(series[0]["period"].as_str().unwrap().parse::<chrono::DateTime<Utc>>().unwrap(), today);
+    assert_eq!((series[0]["paid"].as_i64(), series[0]["gross_volume"].as_str()), (Some(1), Some("10.00")));
+
+    // Invoices created outside the range are left out
+    let from = (Utc::now() + Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let to = (Utc::now() + Duration::hours(2)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let (status, body) = app.get(&format!("{}?from={}&to={}", uri, from, to)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["totals"].as_array().unwrap().is_empty(), "{}", body);
+    assert!(body["series"].as_array().unwrap().is_empty(), "{}", body);
+}
+
+#[tokio::test]
+async fn test_analytics_only_count_sbtc_payments_as_sats_received() {
+    let Some(app) = TestApp::start().await else { return };
+    let in_sbtc = app.create_invoice("10.00").await;
+    let (status, body) = app.submit_payment(&in_sbtc, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let in_usdt = app.create_invoice("4.00").await;
+    let (status, body) = app.submit_payment(&in_usdt, "1000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    app.database
+        .collection::<Document>("payments")
+        .update_one(
+            doc! { "invoice_id": ObjectId::parse_str(&in_usdt).unwrap() },
+            doc! { "$set": { "asset": "USDT" } },
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = app.get(&format!("/merchants/{}/analytics", MERCHANT)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let usd = &body["totals"][0];
+    assert_eq!(usd["paid"].as_i64(), Some(2), "{}", body);
+    assert_eq!(usd["gross_volume"], "14.00");
+    assert_eq!(usd["sbtc_received"], "20000");
+    assert_eq!(usd["effective_exchange_rate"], "50000.00");
+    let bucket = &body["series"][0];
+    assert_eq!((bucket["paid"].as_i64(), bucket["sbtc_received"].as_str()), (Some(2), Some("20000")));
+    assert_eq!(bucket["effective_exchange_rate"], "50000.00");
+}
@@ -0,0 +1,125 @@
+// tests/bolt_protocol_service.rs
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use bolt_payment_gateway_server::services::bolt_protocol_service::{
+    BoltProtocolService, BroadcastError, TransactionStatus, TransactionStatusResponse,
+};
+use bolt_payment_gateway_server::testing::{FakeBoltBehavior, FakeBoltServer, FAKE_SENDER_ADDRESS};
+
+async fn start() -> (FakeBoltServer, BoltProtocolService) {
+    let server = FakeBoltServer::start().await.expect("Failed to start the fake Bolt server");
+    let service = BoltProtocolService::with_timeout(server.base_url(), Duration::from_millis(300));
+    (server, service)
+}
+
+#[tokio::test]
+async fn test_broadcast_accepted() {
+    let (server, service) = start().await;
+    server.push(FakeBoltBehavior::Accept {
+        txid: Some("0xabc".to_string()),
+        sender: None,
+        amount: None,
+    });
+
+    let response = service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await
+        .unwrap_or_else(|e| panic!("broadcast failed: {}", e));
+
+    assert_eq!(response.txid, "0xabc");
+    assert_eq!(response.sender, FAKE_SENDER_ADDRESS);
+    assert_eq!(response.amount, 20000);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].serialized_tx, "0xdead");
+    assert_eq!(requests[0].amount, "20000");
+    assert_eq!(requests[0].recipient_address, "ST1RECIPIENT");
+}
+
+#[tokio::test]
+async fn test_transaction_lookup() {
+    let (server, service) = start().await;
+    server.push(FakeBoltBehavior::Accept { txid: Some("0xabc".to_string()), sender: None, amount: None });
+    service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await
+        .unwrap_or_else(|e| panic!("broadcast failed: {}", e));
+
+    let transaction = service.transaction_status("0xabc").await.unwrap().unwrap();
+    assert_eq!((transaction.status, transaction.sender.as_str()), (TransactionStatus::Success, FAKE_SENDER_ADDRESS));
+    assert!(service.transaction_status("0xunknown").await.unwrap().is_none());
+
+    server.set_transaction(TransactionStatusResponse {
+        txid: "0xabc".to_string(),
+        status: TransactionStatus::Failed,
+        sender: FAKE_SENDER_ADDRESS.to_string(),
+        amount: "20000".to_string(),
+    });
+    assert_eq!(service.transaction_status("0xabc").await.unwrap().unwrap().status, TransactionStatus::Failed);
+}
+
+#[tokio::test]
+async fn test_broadcast_error_statuses_are_passed_through() {
+    let (server, service) = start().await;
+    server.push(FakeBoltBehavior::Fail { status: 400, body: "bad transaction".to_string() });
+    server.push(FakeBoltBehavior::Fail { status: 503, body: "node unavailable".to_string() });
+
+    // A refusal is final, a server error may come after the transaction went out
+    let error = service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, BroadcastError::Rejected { .. }), "{}", error);
+    assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+
+    let error = service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, BroadcastError::Unknown { .. }), "{}", error);
+    assert_eq!(error.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_broadcast_timeout() {
+    let (server, service) = start().await;
+    server.push(FakeBoltBehavior::Hang { millis: 2000 });
+
+    let result = service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await;
+    let error = result.err().unwrap();
+    assert!(matches!(error, BroadcastError::Unknown { .. }), "{}", error);
+    assert_eq!(error.status(), StatusCode::GATEWAY_TIMEOUT);
+}
+
+#[tokio::test]
+async fn test_broadcast_malformed_responses() {
+    let (server, service) = start().await;
+    server.push(FakeBoltBehavior::Malformed { body: "{\"unexpected\": true}".to_string() });
+    server.push(FakeBoltBehavior::Accept { txid: None, sender: None, amount: Some("lots".to_string()) });
+
+    for _ in 0..2 {
+        let result = service
+            .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+            .await;
+        assert!(matches!(result, Err(BroadcastError::Unknown { .. })));
+    }
+}
+
+#[tokio::test]
+async fn test_broadcast_unreachable() {
+    let (server, service) = start().await;
+    drop(server);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let result = service
+        .broadcast_transaction("0xdead".to_string(), 20000, "ST1RECIPIENT".to_string())
+        .await;
+    assert!(matches!(result, Err(BroadcastError::Rejected { .. })));
+}
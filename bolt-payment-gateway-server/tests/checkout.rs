@@ -0,0 +1,98 @@
+// tests/checkout.rs
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use bolt_payment_gateway_server::models::{Merchant, PaymentToken};
+use bolt_payment_gateway_server::services::token_registry::{
+    BOLT_TRANSFER_FUNCTION, DEFAULT_BOLT_PROTOCOL_CONTRACT, DEFAULT_BOLT_TRANSFER_FEE, DEFAULT_SBTC_CONTRACT,
+};
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_checkout_offers_tokens_with_a_contract() {
+    let Some(app) = TestApp::start().await else { return };
+    let (status, body) = app
+        .post(
+            "/merchants",
+            json!({
+                "wallet_address": MERCHANT,
+                "display_name": "Coffee shop",
+                "allowed_payment_tokens": ["sBTC"],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let invoice_id = app.create_invoice("10.00").await;
+
+    let (status, checkout) = app.get(&format!("/invoices/{}/checkout", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+    assert_eq!(checkout["merchant_name"], "Coffee shop");
+    assert_eq!(checkout["amount"], "10.00");
+    assert!(checkout["memo"].as_str().unwrap().starts_with("BG-MID: "));
+
+    let options = checkout["payment_options"].as_array().unwrap();
+    assert_eq!(options.len(), 1, "{}", checkout);
+    let sbtc = &options[0];
+    assert_eq!(sbtc["token"], "sBTC");
+    assert_eq!(sbtc["amount_due"], checkout["quote"]["amount"]);
+    assert_eq!(sbtc["decimals"], 8);
+    assert_eq!(sbtc["token_contract_id"], DEFAULT_SBTC_CONTRACT);
+    assert_eq!(sbtc["transfer_contract_id"], DEFAULT_BOLT_PROTOCOL_CONTRACT);
+    assert_eq!(sbtc["transfer_function"], BOLT_TRANSFER_FUNCTION);
+    assert_eq!(sbtc["fee"], DEFAULT_BOLT_TRANSFER_FEE.to_string());
+
+    // The quote locked by the checkout is the one the payment URI is built from
+    let (status, uri) = app.get(&format!("/invoices/{}/payment-uri", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", uri);
+    assert_eq!(uri["token"], "sBTC");
+    assert_eq!(uri["amount"], sbtc["amount_due"]);
+    assert_eq!(uri["quote_expires_at"], checkout["quote"]["expires_at"]);
+
+    let (status, body) = app.get(&format!("/invoices/{}/payment-uri?token=USDT", invoice_id)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    assert_eq!(body["error"], "unsupported_payment_token");
+}
+
+#[tokio::test]
+async fn test_checkout_leaves_out_tokens_without_a_contract() {
+    let Some(app) = TestApp::start().await else { return };
+    // Stored before merchants were limited to priced tokens
+    let merchant = Merchant {
+        allowed_payment_tokens: vec![PaymentToken::USDT, PaymentToken::SBTC],
+        ..Merchant::with_defaults(MERCHANT)
+    };
+    app.state.merchant_repository.create(&merchant).await.unwrap();
+    let invoice_id = app.create_invoice("10.00").await;
+
+    let (status, checkout) = app.get(&format!("/invoices/{}/checkout", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+    let options = checkout["payment_options"].as_array().unwrap();
+    assert_eq!(options.len(), 1, "{}", checkout);
+    assert_eq!(options[0]["token"], "sBTC");
+
+    // Nor are payments in it accepted
+    let (status, body) = app
+        .post(
+            &format!("/invoices/{}/payments/submit", invoice_id),
+            json!({ "serialized_transaction": "0xdeadbeef", "asset": "USDT", "amount": "20000" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    assert_eq!(body["error"], "unsupported_payment_token");
+    assert!(app.bolt.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_checkout_of_paid_invoice_is_refused() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = app.get(&format!("/invoices/{}/checkout", invoice_id)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["error"], "invoice_not_payable");
+}
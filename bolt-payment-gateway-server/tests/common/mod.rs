@@ -0,0 +1,208 @@
+// tests/common/mod.rs
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use mongodb::{bson::doc, options::ClientOptions, Client, Database};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use bolt_payment_gateway_server::api::v1::routes::create_routes;
+use bolt_payment_gateway_server::database::{
+    AuditRepository, InvoiceRepository, MerchantRepository, PaymentLinkRepository,
+    PaymentRepository, PriceGuardRepository, PriceHistoryRepository, SubscriptionPlanRepository,
+    SubscriptionRepository,
+};
+use bolt_payment_gateway_server::models::Invoice;
+use bolt_payment_gateway_server::services::bolt_protocol_service::BoltProtocolService;
+use bolt_payment_gateway_server::services::event_bus::EventBus;
+use bolt_payment_gateway_server::services::notification_service::NotificationService;
+use bolt_payment_gateway_server::services::payout_service::PayoutService;
+use bolt_payment_gateway_server::services::price_guard::PriceGuard;
+use bolt_payment_gateway_server::services::quote_service::QuoteService;
+use bolt_payment_gateway_server::services::token_registry::TokenRegistry;
+use bolt_payment_gateway_server::testing::{FakeBoltServer, FakePriceServer};
+use bolt_payment_gateway_server::AppState;
+
+/// Merchant the invoices are created for, paid through the default treasury
+pub const MERCHANT: &str = "ST1MERCHANTTEST";
+
+/// Price the fake feed starts at
+pub const BTC_PRICE: &str = "100000.00";
+
+/// Satoshis a $10.00 invoice needs at `BTC_PRICE` with the default 0.50% payment spread
+pub const TEN_DOLLARS_MIN_SATS: u128 = 10_050;
+
+/// How long broadcasts wait for the fake Bolt server
+pub const BROADCAST_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// A fresh database on the test MongoDB, `None` when no MongoDB is reachable so tests can skip.
+/// With `MONGODB_TEST_URI` or `CI` set a MongoDB is expected, and an unreachable one fails the test.
+pub async fn test_database() -> Option<Database> {
+    let configured = std::env::var("MONGODB_TEST_URI").ok();
+    let required = configured.is_some() || std::env::var_os("CI").is_some();
+    let uri = configured.unwrap_or_else(|| "mongodb://localhost:27017".to_string());
+
+    match connect(&uri).await {
+        Ok(database) => Some(database),
+        Err(e) if required => panic!("MongoDB is not reachable at {}: {}", uri, e),
+        Err(e) => {
+            eprintln!("Skipping test, MongoDB is not reachable at {}: {}", uri, e);
+            None
+        }
+    }
+}
+
+async fn connect(uri: &str) -> mongodb::error::Result<Database> {
+    let mut options = ClientOptions::parse(uri).await?;
+    options.server_selection_timeout = Some(Duration::from_secs(2));
+    let client = Client::with_options(options)?;
+    client.database("admin").run_command(doc! { "ping": 1 }).await?;
+    Ok(client.database(&format!("test_db_{}", ObjectId::new())))
+}
+
+/// The gateway's router in-process, with the Bolt protocol API and the price feed faked
+pub struct TestApp {
+    pub state: AppState,
+    pub router: Router,
+    pub database: Database,
+    pub bolt: FakeBoltServer,
+    pub prices: FakePriceServer,
+}
+
+impl TestApp {
+    /// Gateway backed by a fresh test database, `None` when MongoDB is unreachable
+    pub async fn start() -> Option<Self> {
+        let database = test_database().await?;
+        let app = Self::with_database(database).await;
+        app.state
+            .payment_repository
+            .create_indexes()
+            .await
+            .expect("Failed to create payment indexes");
+        Some(app)
+    }
+
+    /// Gateway whose storage cannot be reached, for routes that never touch it
+    pub async fn without_storage() -> Self {
+        let mut options = ClientOptions::parse("mongodb://127.0.0.1:9").await.unwrap();
+        options.server_selection_timeout = Some(Duration::from_millis(200));
+        let client = Client::with_options(options).unwrap();
+        Self::with_database(client.database("unreachable")).await
+    }
+
+    async fn with_database(database: Database) -> Self {
+        let bolt = FakeBoltServer::start().await.expect("Failed to start the fake Bolt server");
+        let prices = FakePriceServer::start(BTC_PRICE)
+            .await
+            .expect("Failed to start the fake price server");
+
+        let event_bus = EventBus::new();
+        let price_history_repository = PriceHistoryRepository::new(&database);
+        let state = AppState {
+            invoice_repository: InvoiceRepository::new(&database, event_bus.clone()),
+            payment_repository: PaymentRepository::new(&database, event_bus.clone()),
+            audit_repository: AuditRepository::new(&database),
+            merchant_repository: MerchantRepository::new(&database),
+            payment_link_repository: PaymentLinkRepository::new(&database),
+            subscription_plan_repository: SubscriptionPlanRepository::new(&database),
+            subscription_repository: SubscriptionRepository::new(&database),
+            price_history_repository: price_history_repository.clone(),
+            price_guard_repository: PriceGuardRepository::new(&database),
+            // Prices are not cached so tests see every change of the fake feed
+            quote_service: QuoteService::with_cache_expiry(prices.base_url(), Duration::ZERO)
+                .with_history(price_history_repository),
+            bolt_protocol_service: BoltProtocolService::with_timeout(bolt.base_url(), BROADCAST_TIMEOUT),
+            payout_service: PayoutService::default(),
+            event_bus,
+            notification_service: NotificationService::new(None),
+            token_registry: TokenRegistry::default(),
+        };
+        let router = create_routes().with_state(state.clone());
+
+        Self { state, router, database, bolt, prices }
+    }
+
+    /// Check every price with `guard`, tests moving the fake price freely run without one
+    pub fn with_price_guard(mut self, guard: PriceGuard) -> Self {
+        self.state.quote_service = self.state.quote_service.clone().with_guard(guard);
+        self.router = create_routes().with_state(self.state.clone());
+        self
+    }
+
+    /// Send a request with an optional JSON body, returns the status and the JSON answer
+    pub async fn send(&self, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        let body = match body {
+            Some(body) => {
+                request = request.header("content-type", "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, json)
+    }
+
+    pub async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        self.send(Method::GET, uri, None).await
+    }
+
+    pub async fn post(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.send(Method::POST, uri, Some(body)).await
+    }
+
+    /// Create an invoice for `MERCHANT` and return its id
+    pub async fn create_invoice(&self, amount: &str) -> String {
+        let (status, body) = self
+            .post(
+                &format!("/merchants/{}/invoices", MERCHANT),
+                json!({ "amount": amount, "merchant_order_id": ObjectId::new().to_hex() }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["id"].as_str().unwrap().to_string()
+    }
+
+    pub async fn submit_payment(&self, invoice_id: &str, amount: &str) -> (StatusCode, Value) {
+        self.post(
+            &format!("/invoices/{}/payments/submit", invoice_id),
+            json!({ "serialized_transaction": "0xdeadbeef", "asset": "sBTC", "amount": amount }),
+        )
+        .await
+    }
+
+    pub async fn invoice(&self, invoice_id: &str) -> Invoice {
+        let invoice_id = ObjectId::parse_str(invoice_id).unwrap();
+        self.state.invoice_repository.find_by_id(&invoice_id).await.unwrap().unwrap()
+    }
+
+    /// Move an invoice's deadline, bypassing the bounds the API enforces
+    pub async fn set_deadline(&self, invoice_id: &str, expires_at: DateTime<Utc>) {
+        let invoice_id = ObjectId::parse_str(invoice_id).unwrap();
+        self.database
+            .collection::<Invoice>("invoices")
+            .update_one(
+                doc! { "_id": invoice_id },
+                doc! { "$set": { "expires_at": bson::to_bson(&expires_at).unwrap() } },
+            )
+            .await
+            .unwrap();
+    }
+}
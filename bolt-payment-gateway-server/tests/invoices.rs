@@ -0,0 +1,200 @@
+// tests/invoices.rs
+mod common;
+
+use std::collections::HashSet;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use bolt_payment_gateway_server::database::StatusUpdateError;
+use bolt_payment_gateway_server::models::{Actor, AuditContext, InvoiceAmendment, InvoiceStatus};
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_create_and_get_invoice() {
+    let Some(app) = TestApp::start().await else { return };
+
+    let (status, created) = app
+        .post(
+            &format!("/merchants/{}/invoices", MERCHANT),
+            json!({ "amount": "12.34", "merchant_order_id": "order-1", "description": "Coffee beans" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    assert_eq!(created["status"], "created");
+    assert_eq!(created["amount"], "12.34");
+    assert_eq!(created["merchant_order_id"], "order-1");
+
+    let (status, fetched) = app.get(&format!("/invoices/{}", created["id"].as_str().unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched["id"], created["id"]);
+    assert_eq!(fetched["description"], "Coffee beans");
+}
+
+#[tokio::test]
+async fn test_create_invoice_rejects_invalid_amounts() {
+    let Some(app) = TestApp::start().await else { return };
+
+    for amount in ["0", "-1.00", "ten"] {
+        let (status, body) = app
+            .post(
+                &format!("/merchants/{}/invoices", MERCHANT),
+                json!({ "amount": amount, "merchant_order_id": "order-1" }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", amount, body);
+    }
+
+    let (status, body) = app.get(&format!("/merchants/{}/invoices", MERCHANT)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_list_invoices_pagination() {
+    let Some(app) = TestApp::start().await else { return };
+    let mut created = HashSet::new();
+    for _ in 0..5 {
+        created.insert(app.create_invoice("1.00").await);
+    }
+    let list = |query: &str| {
+        let app = &app;
+        let uri = format!("/merchants/{}/invoices?{}", MERCHANT, query);
+        async move { app.get(&uri).await }
+    };
+    let ids = |body: &serde_json::Value| -> Vec<String> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|invoice| invoice["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Pages are disjoint and cover every invoice, the last one is partial
+    let mut seen = HashSet::new();
+    for (offset, expected) in [(0, 2), (2, 2), (4, 1)] {
+        let (status, body) = list(&format!("limit=2&offset={}", offset)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["total"], 5);
+        assert_eq!(body["limit"], 2);
+        assert_eq!(body["offset"], offset);
+        let page = ids(&body);
+        assert_eq!(page.len(), expected);
+        for id in page {
+            assert!(seen.insert(id));
+        }
+    }
+    assert_eq!(seen, created);
+
+    // Offsets at or past the end give an empty page with the total
+    for offset in [5, 50] {
+        let (status, body) = list(&format!("offset={}", offset)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 5);
+        assert!(ids(&body).is_empty());
+    }
+
+    let (status, body) = list("limit=0").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 5);
+    assert!(ids(&body).is_empty());
+
+    let (_, body) = list("").await;
+    assert_eq!(body["limit"], 20);
+    assert_eq!(ids(&body).len(), 5);
+
+    let (status, body) = list("limit=100").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ids(&body).len(), 5);
+
+    let (status, body) = list("limit=101").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_limit");
+
+    for query in ["limit=-1", "offset=abc"] {
+        let (status, _) = list(query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_list_invoices_filters_before_paginating() {
+    let Some(app) = TestApp::start().await else { return };
+    let paid = app.create_invoice("10.00").await;
+    for _ in 0..3 {
+        app.create_invoice("10.00").await;
+    }
+    let (status, _) = app.submit_payment(&paid, "20000").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = app
+        .get(&format!("/merchants/{}/invoices?status=paid&limit=1", MERCHANT))
+        .await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["id"], paid.as_str());
+
+    let (_, body) = app
+        .get(&format!("/merchants/{}/invoices?status=created&offset=2", MERCHANT))
+        .await;
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+
+    // Other merchants' invoices are never listed
+    let (_, body) = app.get("/merchants/ST1SOMEONEELSE/invoices").await;
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_stale_invoice_update_is_a_version_conflict() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let stale = app.invoice(&invoice_id).await;
+    let context = AuditContext::new(Actor::System { worker: "test".to_string() }, None);
+
+    let amendment = InvoiceAmendment { amount: Some(2000), merchant_order_id: None, expires_at: None };
+    let amended = app.state.invoice_repository.amend(&stale, &amendment, &context).await.unwrap();
+    assert_eq!(amended.version, stale.version + 1);
+
+    // Cancelling the copy read before the amendment must not go through
+    let result = app
+        .state
+        .invoice_repository
+        .update_status(&stale, InvoiceStatus::Cancelled, &context)
+        .await;
+    assert!(matches!(result, Err(StatusUpdateError::VersionConflict { .. })), "{:?}", result);
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Created);
+
+    let cancelled = app
+        .state
+        .invoice_repository
+        .update_status(&amended, InvoiceStatus::Cancelled, &context)
+        .await
+        .unwrap();
+    assert_eq!(cancelled.status, InvoiceStatus::Cancelled);
+    assert_eq!(cancelled.version, amended.version + 1);
+}
+
+#[tokio::test]
+async fn test_earlier_deadline_cuts_the_locked_quote_short() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, body) = app.get(&format!("/invoices/{}/quote", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let invoice = app.invoice(&invoice_id).await;
+    let quote = invoice.quote.clone().unwrap();
+    let context = AuditContext::new(Actor::System { worker: "test".to_string() }, None);
+
+    let deadline = quote.locked_at + chrono::Duration::seconds(30);
+    assert!(deadline < quote.expires_at);
+    let amendment = InvoiceAmendment { amount: None, merchant_order_id: None, expires_at: Some(deadline) };
+    let amended = app.state.invoice_repository.amend(&invoice, &amendment, &context).await.unwrap();
+    let clamped = amended.quote.clone().unwrap();
+    assert_eq!((clamped.expires_at, clamped.amount), (deadline, quote.amount));
+
+    // A later deadline does not extend the quote
+    let amendment = InvoiceAmendment { expires_at: Some(quote.expires_at + chrono::Duration::hours(1)), ..amendment };
+    let amended = app.state.invoice_repository.amend(&amended, &amendment, &context).await.unwrap();
+    assert_eq!(amended.quote.unwrap().expires_at, deadline);
+}
@@ -0,0 +1,37 @@
+// tests/leases.rs
+mod common;
+
+use std::time::Duration;
+
+use bolt_payment_gateway_server::database::LeaseRepository;
+use bolt_payment_gateway_server::services::leader_lease::LeaderLease;
+
+use common::test_database;
+
+#[tokio::test]
+async fn test_one_replica_leads_until_its_lease_expires() {
+    let Some(database) = test_database().await else { return };
+    let repository = LeaseRepository::new(&database);
+    let ttl = Duration::from_millis(500);
+    let first = LeaderLease::new(repository.clone(), "job", "replica-1", ttl);
+    let second = LeaderLease::new(repository.clone(), "job", "replica-2", ttl);
+
+    let (a, b) = tokio::join!(first.hold(), second.hold());
+    assert!(a ^ b, "exactly one replica leads, got {} and {}", a, b);
+    let (leader, follower) = if a { (&first, &second) } else { (&second, &first) };
+
+    // The leader keeps its lease by renewing it
+    assert!(leader.hold().await);
+    assert!(!follower.hold().await);
+
+    // A leader that stops renewing is replaced once the lease expires
+    tokio::time::sleep(ttl + Duration::from_millis(100)).await;
+    assert!(follower.hold().await);
+    assert!(!leader.hold().await);
+
+    // Releasing hands over right away
+    follower.release().await;
+    assert!(leader.hold().await);
+    let lease = repository.find("job").await.unwrap().unwrap();
+    assert!(lease.holder == "replica-1" || lease.holder == "replica-2");
+}
@@ -0,0 +1,82 @@
+// tests/merchants.rs
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_create_merchant_validates_settings() {
+    // Validation answers before anything is stored
+    let app = TestApp::without_storage().await;
+    for (settings, error) in [
+        (json!({ "wallet_address": " " }), "invalid_wallet_address"),
+        (json!({ "display_name": "" }), "invalid_display_name"),
+        (json!({ "min_invoice_expiry_secs": 0 }), "invalid_invoice_expiry"),
+        (json!({ "min_invoice_expiry_secs": 7200, "max_invoice_expiry_secs": 3600 }), "invalid_invoice_expiry"),
+        (json!({ "max_invoice_expiry_secs": 400 * 24 * 3600 }), "invalid_invoice_expiry"),
+        (json!({ "default_invoice_expiry_secs": 10 }), "invalid_invoice_expiry"),
+        (json!({ "quote_window_secs": 5 }), "invalid_quote_window"),
+        (json!({ "quote_window_secs": 7200 }), "invalid_quote_window"),
+        (json!({ "allowed_payment_tokens": [] }), "invalid_payment_tokens"),
+        (json!({ "allowed_payment_tokens": ["sBTC", "USDT"] }), "invalid_payment_tokens"),
+        (json!({ "fee_schedule": { "quote_spread_bps": 10001, "payment_spread_bps": 50 } }), "invalid_fee_schedule"),
+        (json!({ "webhook_url": "ftp://example.com" }), "invalid_webhook_url"),
+    ] {
+        let mut request = json!({ "wallet_address": MERCHANT, "display_name": "Coffee shop" });
+        for (key, value) in settings.as_object().unwrap() {
+            request[key] = value.clone();
+        }
+
+        let (status, body) = app.post("/merchants", request.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{} {}", request, body);
+        assert_eq!(body["error"], error, "{}", request);
+    }
+}
+
+#[tokio::test]
+async fn test_create_get_and_update_merchant() {
+    let Some(app) = TestApp::start().await else { return };
+    app.state.merchant_repository.create_indexes().await.unwrap();
+    let uri = format!("/merchants/{}", MERCHANT);
+
+    let (status, _) = app.get(&uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let request = json!({ "wallet_address": MERCHANT, "display_name": "Coffee shop", "payout_address": MERCHANT });
+    let (status, body) = app.post("/merchants", request.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["payout_address_verified"], true);
+    assert_eq!(body["allowed_payment_tokens"], json!(["sBTC"]));
+    let (status, body) = app.post("/merchants", request).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["error"], "merchant_already_exists");
+
+    let (status, body) = app
+        .send(
+            Method::PATCH,
+            &uri,
+            Some(json!({ "display_name": "Tea shop", "payout_address": "ST1PAYOUT", "quote_window_secs": 60 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["display_name"], "Tea shop");
+    // Another payout address has to be verified by an operator
+    assert_eq!(body["payout_address_verified"], false);
+
+    // Updates are validated against the stored settings, and not applied when invalid
+    let (status, body) = app
+        .send(Method::PATCH, &uri, Some(json!({ "min_invoice_expiry_secs": 400 * 24 * 3600 })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error"], "invalid_invoice_expiry");
+
+    let (status, body) = app.get(&uri).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!((body["display_name"].as_str(), body["quote_window_secs"].as_i64()), (Some("Tea shop"), Some(60)));
+    assert_eq!(body["payout_address"], "ST1PAYOUT");
+
+    let (status, _) = app.send(Method::PATCH, "/merchants/ST1UNKNOWN", Some(json!({ "display_name": "x" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,130 @@
+// tests/migrations.rs
+mod common;
+
+use axum::http::StatusCode;
+use mongodb::bson::{doc, Document};
+
+use bolt_payment_gateway_server::database::{MigrationRunner, MIGRATIONS};
+use bolt_payment_gateway_server::models::Merchant;
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_migrations_backfill_versions_once() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let invoices = app.database.collection::<Document>("invoices");
+    let invoice_id = bson::oid::ObjectId::parse_str(&invoice_id).unwrap();
+    // Stored the way releases before document versions did
+    invoices
+        .update_one(doc! { "_id": invoice_id }, doc! { "$unset": { "version": "" } })
+        .await
+        .unwrap();
+
+    let runner = MigrationRunner::new(&app.database, "test");
+    let dry_run = runner.dry_run().await.unwrap();
+    assert_eq!(dry_run.len(), MIGRATIONS.len());
+    assert_eq!(dry_run[0].documents, 1);
+    let stored = invoices.find_one(doc! { "_id": invoice_id }).await.unwrap().unwrap();
+    assert!(!stored.contains_key("version"));
+
+    // Replicas starting together migrate once
+    let other = MigrationRunner::new(&app.database, "other");
+    let (first, second) = tokio::join!(runner.run(), other.run());
+    let applied = first.unwrap().len() + second.unwrap().len();
+    assert_eq!(applied, MIGRATIONS.len());
+
+    let stored = invoices.find_one(doc! { "_id": invoice_id }).await.unwrap().unwrap();
+    assert_eq!(stored.get_i64("version").unwrap(), 0);
+    assert_eq!(runner.applied().await.unwrap().len(), MIGRATIONS.len());
+    assert!(runner.run().await.unwrap().is_empty());
+    assert!(runner.dry_run().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_migrations_copy_the_merchant_onto_payments() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let payments = app.database.collection::<Document>("payments");
+    assert_eq!(payments.count_documents(doc! { "wallet_address": MERCHANT }).await.unwrap(), 1);
+
+    // Stored the way releases before merchant listings were paged in the database did
+    payments.update_many(doc! {}, doc! { "$unset": { "wallet_address": "" } }).await.unwrap();
+    let orphan = doc! { "_id": bson::oid::ObjectId::new(), "invoice_id": bson::oid::ObjectId::new() };
+    payments.insert_one(orphan).await.unwrap();
+
+    let runner = MigrationRunner::new(&app.database, "test");
+    let reports = runner.run().await.unwrap();
+    let backfill = reports.iter().find(|report| report.name == "backfill_payment_wallet_addresses").unwrap();
+    assert_eq!(backfill.documents, 1);
+    assert_eq!(payments.count_documents(doc! { "wallet_address": MERCHANT }).await.unwrap(), 1);
+}
+
+/// Record `version` as applied, the way another release would
+async fn record_applied(app: &TestApp, version: i32, name: &str) {
+    app.database
+        .collection::<Document>("schema_migrations")
+        .insert_one(doc! {
+            "_id": version,
+            "name": name,
+            "modified": 0_i64,
+            "duration_ms": 0_i64,
+            "applied_by": "newer",
+            "applied_at": "2030-01-01T00:00:00Z",
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_database_migrated_by_newer_release_is_served() {
+    let Some(app) = TestApp::start().await else { return };
+    for migration in MIGRATIONS {
+        record_applied(&app, migration.version, migration.name).await;
+    }
+    record_applied(&app, 9999, "from_the_future").await;
+
+    // Rolled back behind a newer release, or still running next to it during a deploy
+    let runner = MigrationRunner::new(&app.database, "test");
+    assert!(runner.run().await.unwrap().is_empty());
+    assert!(runner.dry_run().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_database_missing_a_migration_is_refused() {
+    let Some(app) = TestApp::start().await else { return };
+    record_applied(&app, 9999, "from_the_future").await;
+
+    let runner = MigrationRunner::new(&app.database, "test");
+    let error = runner.run().await.unwrap_err();
+    assert!(error.to_string().contains("never applied"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_documents_written_by_newer_release_are_read() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let invoice_id = bson::oid::ObjectId::parse_str(&invoice_id).unwrap();
+    app.database
+        .collection::<Document>("invoices")
+        .update_one(doc! { "_id": invoice_id }, doc! { "$set": { "added_later": true } })
+        .await
+        .unwrap();
+
+    let invoice = app.state.invoice_repository.find_by_id(&invoice_id).await.unwrap();
+    assert!(invoice.is_some());
+
+    app.state.merchant_repository.create(&Merchant::with_defaults(MERCHANT)).await.unwrap();
+    app.database
+        .collection::<Document>("merchants")
+        .update_one(
+            doc! { "wallet_address": MERCHANT },
+            doc! { "$set": { "added_later": true, "fee_schedule.added_later": 1 } },
+        )
+        .await
+        .unwrap();
+    let merchant = app.state.merchant_repository.find_by_wallet_address(MERCHANT).await.unwrap();
+    assert!(merchant.is_some());
+}
@@ -0,0 +1,40 @@
+// tests/payment_links.rs
+mod common;
+
+use axum::http::{Method, StatusCode};
+use bson::oid::ObjectId;
+use serde_json::json;
+
+use common::{TestApp, MERCHANT};
+
+#[tokio::test]
+async fn test_update_keeps_uses_claimed_concurrently() {
+    let Some(app) = TestApp::start().await else { return };
+    let (status, body) = app
+        .post(
+            &format!("/merchants/{}/payment-links", MERCHANT),
+            json!({
+                "title": "Coffee",
+                "amount": { "type": "fixed", "amount": "4.50" },
+                "usage_limit": 10,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let link_id = ObjectId::parse_str(body["id"].as_str().unwrap()).unwrap();
+
+    // The merchant edits a copy read before a customer opened the link
+    let repository = &app.state.payment_link_repository;
+    let mut edited = repository.find_by_id(&link_id).await.unwrap().unwrap();
+    repository.claim_use(&link_id).await.unwrap().unwrap();
+    edited.title = "Espresso".to_string();
+    let updated = repository.update(&edited).await.unwrap().unwrap();
+    assert_eq!((updated.title.as_str(), updated.usage_count), ("Espresso", 1));
+
+    let (status, body) = app
+        .send(Method::PATCH, &format!("/payment-links/{}", link_id), Some(json!({ "usage_limit": 5 })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["usage_limit"], 5);
+    assert_eq!(body["usage_count"], 1);
+}
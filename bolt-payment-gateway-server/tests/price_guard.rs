@@ -0,0 +1,128 @@
+// tests/price_guard.rs
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use tower::ServiceExt;
+
+use bolt_payment_gateway_server::database::PriceGuardRepository;
+use bolt_payment_gateway_server::handlers::metrics_handler;
+use bolt_payment_gateway_server::models::{PriceCheck, PriceHalt};
+use bolt_payment_gateway_server::services::admin_service::AdminService;
+use bolt_payment_gateway_server::services::price_guard::{PriceGuard, PriceGuardConfig, RECOVERY_TICKS};
+use bolt_payment_gateway_server::services::quote_service::QuoteService;
+
+use common::{TestApp, TEN_DOLLARS_MIN_SATS};
+
+const QUOTE: &str = "/quotes?from=BTC&to=USD&to_amount=10.00";
+
+async fn scrape_metrics(app: &TestApp) -> String {
+    let router = Router::new()
+        .route("/metrics", get(metrics_handler::get_metrics))
+        .with_state(app.state.clone());
+    let response = router
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_price_spike_halts_quotes_until_the_feed_recovers() {
+    let app = TestApp::without_storage()
+        .await
+        .with_price_guard(PriceGuard::new(PriceGuardConfig::default()));
+    let (status, body) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    app.prices.set_price("50000.00");
+    let (status, body) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+    assert_eq!(body["error"], "price_feed_halted");
+    let metrics = scrape_metrics(&app).await;
+    assert!(metrics.contains("price_guard_halted{pair=\"BTCUSD\"} 1"), "{}", metrics);
+    assert!(metrics.contains("check=\"tick_deviation\"} 1"), "{}", metrics);
+
+    // Back to the last good price, for long enough
+    app.prices.set_price("100000.00");
+    for _ in 1..RECOVERY_TICKS {
+        let (status, _) = app.get(QUOTE).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let (status, body) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let metrics = scrape_metrics(&app).await;
+    assert!(metrics.contains("price_guard_halted{pair=\"BTCUSD\"} 0"), "{}", metrics);
+    assert!(metrics.contains("price_guard_last_price_usd{pair=\"BTCUSD\"} 100000.00"), "{}", metrics);
+}
+
+#[tokio::test]
+async fn test_malformed_price_halts_quotes_like_a_bad_tick() {
+    let app = TestApp::without_storage()
+        .await
+        .with_price_guard(PriceGuard::new(PriceGuardConfig::default()));
+    let (status, body) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    for price in ["not-a-number", "1.\u{e9}"] {
+        app.prices.set_price(price);
+        let (status, body) = app.get(QUOTE).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{} {}", price, body);
+        assert_eq!(body["error"], "price_feed_halted");
+    }
+    let metrics = scrape_metrics(&app).await;
+    assert!(metrics.contains("price_guard_halted{pair=\"BTCUSD\"} 1"), "{}", metrics);
+    assert!(metrics.contains("check=\"malformed\"} 1"), "{}", metrics);
+
+    app.prices.set_price("100000.00");
+    for _ in 1..RECOVERY_TICKS {
+        let (status, _) = app.get(QUOTE).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let (status, body) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn test_halt_stops_payments_on_every_replica_until_resumed() {
+    let Some(app) = TestApp::start().await else { return };
+    let repository = PriceGuardRepository::new(&app.database);
+    let app = app.with_price_guard(PriceGuard::new(PriceGuardConfig::default()).with_repository(repository.clone()));
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, quote) = app.get(&format!("/invoices/{}/quote", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+
+    app.prices.set_price("50000.00");
+    let (status, _) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // The locked quote does not let payments through
+    let (status, body) = app.submit_payment(&invoice_id, &TEN_DOLLARS_MIN_SATS.to_string()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+    assert_eq!(body["error"], "price_feed_halted");
+    assert!(app.bolt.requests().is_empty());
+
+    // A replica without a previous tick would accept the price, but follows the halt
+    let replica = QuoteService::with_cache_expiry(app.prices.base_url(), Duration::ZERO)
+        .with_guard(PriceGuard::new(PriceGuardConfig::default()).with_repository(repository));
+    let error = replica.get_bitcoin_price().await.unwrap_err();
+    assert_eq!(error.downcast_ref::<PriceHalt>().unwrap().check, PriceCheck::TickDeviation);
+
+    let admin = AdminService::new(app.state.clone(), "ops");
+    let halt = admin.resume_payments(Some("price moved")).await.unwrap();
+    assert_eq!(halt.price, 5_000_000);
+    assert!(admin.resume_payments(None).await.is_err());
+    let state = admin.price_guard().await.unwrap().unwrap();
+    assert_eq!(state.resumed_by.as_deref(), Some("ops"));
+
+    let (status, body) = app.submit_payment(&invoice_id, &TEN_DOLLARS_MIN_SATS.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(replica.get_bitcoin_price().await.unwrap(), 5_000_000);
+}
@@ -0,0 +1,100 @@
+// tests/prices.rs
+mod common;
+
+use axum::http::StatusCode;
+
+use common::TestApp;
+
+const QUOTE: &str = "/quotes?from=BTC&to=USD&to_amount=10.00";
+
+#[tokio::test]
+async fn test_price_history_candles() {
+    let Some(app) = TestApp::start().await else { return };
+    for price in ["100000.00", "120000.00", "90000.00", "110000.00"] {
+        app.prices.set_price(price);
+        let (status, body) = app.get(QUOTE).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+    }
+
+    let (status, body) = app.get("/prices/history?pair=btcusd&interval=1d").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["pair"], "BTCUSD");
+    assert_eq!(body["interval"], "1d");
+    let candles = body["candles"].as_array().unwrap();
+    assert_eq!(candles.len(), 1, "{}", body);
+    assert_eq!(candles[0]["open"], "100000.00");
+    assert_eq!(candles[0]["high"], "120000.00");
+    assert_eq!(candles[0]["low"], "90000.00");
+    assert_eq!(candles[0]["close"], "110000.00");
+    assert_eq!(candles[0]["ticks"], 4);
+
+    // Nothing was recorded before the test started
+    let (status, body) = app
+        .get("/prices/history?from=2020-01-01T00:00:00Z&to=2020-01-02T00:00:00Z")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["candles"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_price_history_rejects_invalid_requests() {
+    let app = TestApp::without_storage().await;
+
+    let cases = [
+        ("/prices/history?pair=ETHUSD", "unsupported_pair"),
+        ("/prices/history?from=2025-01-02T00:00:00Z&to=2025-01-01T00:00:00Z", "invalid_range"),
+        // 1440 one-minute candles
+        ("/prices/history?interval=1m", "invalid_range"),
+    ];
+    for (uri, expected_error) in cases {
+        let (status, body) = app.get(uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+        assert_eq!(body["error"], expected_error, "{}", uri);
+    }
+
+    let (status, _) = app.get("/prices/history?interval=2m").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_payment_price_at_current_price() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, payment) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", payment);
+    app.prices.set_price("50000.00");
+
+    let (status, body) = app.get(&format!("/payments/{}/price", payment["id"].as_str().unwrap())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["basis"], "current_price");
+    assert_eq!(body["btc_price"], "100000.00");
+    assert!(body["quote"].is_null());
+    assert_eq!(body["tick"]["price"], "100000.00");
+    assert_eq!(body["tick"]["source"], "binance");
+}
+
+#[tokio::test]
+async fn test_payment_price_from_locked_quote() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let (status, _) = app.get(&format!("/invoices/{}/quote", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The payment is validated against the locked price, not the current one
+    app.prices.set_price("50000.00");
+    let (status, _) = app.get(QUOTE).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, payment) = app.submit_payment(&invoice_id, "10050").await;
+    assert_eq!(status, StatusCode::OK, "{}", payment);
+
+    let (status, body) = app.get(&format!("/payments/{}/price", payment["id"].as_str().unwrap())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["basis"], "locked_quote");
+    assert_eq!(body["btc_price"], "100000.00");
+    assert_eq!(body["quote"]["unit_price"], "100000.00");
+    assert_eq!(body["tick"]["price"], "100000.00");
+
+    let (status, body) = app.get(&format!("/payments/{}/price", bson::oid::ObjectId::new())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "payment_not_found");
+}
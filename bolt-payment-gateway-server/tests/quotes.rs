@@ -0,0 +1,81 @@
+// tests/quotes.rs
+mod common;
+
+use axum::http::StatusCode;
+
+use bolt_payment_gateway_server::database::PriceSnapshotRepository;
+use bolt_payment_gateway_server::models::BTC_USD_PAIR;
+use bolt_payment_gateway_server::services::quote_service::QuoteService;
+use bolt_payment_gateway_server::testing::FakePriceServer;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn test_quote_from_current_price() {
+    let app = TestApp::without_storage().await;
+
+    let (status, body) = app.get("/quotes?from=BTC&to=USD&to_amount=100.00").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["from_asset"], "BTC");
+    assert_eq!(body["to_asset"], "USD");
+    // 100,000 satoshis for $100 at $100,000, plus the default 1.00% spread
+    assert_eq!(body["from_amount"], "101000");
+    assert_eq!(body["to_amount"], "100.00");
+    assert_eq!(body["unit_price"], "100000.00");
+    assert_eq!(body["spread"], "1.00%");
+
+    app.prices.set_price("40000.00");
+    let (status, body) = app.get("/quotes?from=btc&to=usd&to_amount=100.00").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["from_amount"], "252500");
+    assert_eq!(app.prices.requests(), 2);
+}
+
+#[tokio::test]
+async fn test_quote_rejects_invalid_requests() {
+    let app = TestApp::without_storage().await;
+
+    let cases = [
+        ("/quotes?from=ETH&to=USD&to_amount=1.00", "unsupported_from_asset"),
+        ("/quotes?from=BTC&to=EUR&to_amount=1.00", "unsupported_to_asset"),
+        ("/quotes?from=BTC&to=USD&to_amount=abc", "invalid_amount"),
+        ("/quotes?from=BTC&to=USD&to_amount=0", "invalid_amount"),
+    ];
+    for (uri, expected_error) in cases {
+        let (status, body) = app.get(uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+        assert_eq!(body["error"], expected_error);
+    }
+
+    // Invalid requests are answered without fetching a price
+    assert_eq!(app.prices.requests(), 0);
+}
+
+#[tokio::test]
+async fn test_quote_when_price_feed_fails() {
+    let app = TestApp::without_storage().await;
+    app.prices.fail(503);
+
+    let (status, body) = app.get("/quotes?from=BTC&to=USD&to_amount=100.00").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["error"], "price_fetch_error");
+}
+
+#[tokio::test]
+async fn test_replicas_quote_from_the_shared_snapshot() {
+    let Some(database) = common::test_database().await else { return };
+    let first_feed = FakePriceServer::start("100000.00").await.unwrap();
+    let second_feed = FakePriceServer::start("90000.00").await.unwrap();
+    let snapshots = PriceSnapshotRepository::new(&database);
+    let first = QuoteService::with_base_url(first_feed.base_url()).with_snapshots(snapshots.clone());
+    let second = QuoteService::with_base_url(second_feed.base_url()).with_snapshots(snapshots.clone());
+
+    assert_eq!(first.get_bitcoin_price().await.unwrap(), 10_000_000);
+    // The second replica quotes the first one's tick instead of fetching its own
+    assert_eq!(second.get_bitcoin_price().await.unwrap(), 10_000_000);
+    assert_eq!(second_feed.requests(), 0);
+
+    let snapshot = snapshots.find(BTC_USD_PAIR).await.unwrap().unwrap();
+    assert_eq!(snapshot.price, 10_000_000);
+    assert_eq!(snapshot.source, "binance");
+}
@@ -0,0 +1,66 @@
+// tests/rate_limit.rs
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use bolt_payment_gateway_server::shared::{RateLimitConfig, RateLimitKey, RateLimitLayer, RouteGroup};
+
+use common::TestApp;
+
+async fn get(router: &Router, uri: &str, forwarded_for: &str) -> (StatusCode, Option<String>, Value) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .header("x-forwarded-for", forwarded_for);
+    let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|value| value.to_str().unwrap().to_string());
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, retry_after, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_quotes_are_limited_per_client_ip() {
+    let app = TestApp::without_storage().await;
+    let config = RateLimitConfig {
+        prefix: String::new(),
+        trusted_proxies: 1,
+        groups: vec![
+            RouteGroup::new("quotes", RateLimitKey::ClientIp, 2, Duration::from_secs(60))
+                .route(Some(Method::GET), "/quotes"),
+        ],
+    };
+    let router = app.router.clone().layer(RateLimitLayer::new(config));
+    let uri = "/quotes?from=BTC&to=USD&to_amount=100.00";
+
+    for _ in 0..2 {
+        let (status, retry_after, body) = get(&router, uri, "1.2.3.4").await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(retry_after.is_none());
+    }
+
+    // Addresses the client puts in front of the proxy's entry do not make it a new client
+    let (status, retry_after, body) = get(&router, uri, "6.6.6.6, 1.2.3.4").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(retry_after.as_deref(), Some("30"));
+    assert_eq!(body["error"], "rate_limited");
+    // Rejected requests never reach the price feed
+    assert_eq!(app.prices.requests(), 2);
+
+    // Other clients and routes outside the group are not affected
+    let (status, _, _) = get(&router, uri, "1.2.3.4, 5.6.7.8").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = get(&router, "/invoices/not-an-id/quote", "1.2.3.4").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
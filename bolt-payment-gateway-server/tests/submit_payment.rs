@@ -0,0 +1,343 @@
+// tests/submit_payment.rs
+mod common;
+
+use axum::http::StatusCode;
+use bson::oid::ObjectId;
+use chrono::Utc;
+use serde_json::json;
+
+use bolt_payment_gateway_server::models::{Actor, AuditContext, InvoiceStatus, Payment, PaymentStatus, PaymentToken};
+use bolt_payment_gateway_server::services::bolt_protocol_service::{TransactionStatus, TransactionStatusResponse};
+use bolt_payment_gateway_server::services::payment_reconciler::PaymentReconciler;
+use bolt_payment_gateway_server::testing::{FakeBoltBehavior, FAKE_SENDER_ADDRESS};
+
+use common::{TestApp, MERCHANT, TEN_DOLLARS_MIN_SATS};
+
+#[tokio::test]
+async fn test_submit_payment_confirms_and_pays_invoice() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    app.bolt.push(FakeBoltBehavior::Accept {
+        txid: Some("0xfeed".to_string()),
+        sender: None,
+        amount: None,
+    });
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["status"], "confirmed");
+    assert_eq!(body["tx_id"], "0xfeed");
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Paid);
+
+    let requests = app.bolt.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].amount, "20000");
+}
+
+#[tokio::test]
+async fn test_underpayment_is_checked_against_current_price() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+
+    let short = (TEN_DOLLARS_MIN_SATS - 1).to_string();
+    let (status, body) = app.submit_payment(&invoice_id, &short).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED, "{}", body);
+    assert_eq!(body["error"], "underpayment_detected");
+    assert!(app.bolt.requests().is_empty());
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Created);
+
+    // BTC halving in price doubles what the invoice costs
+    app.prices.set_price("50000.00");
+    let (status, _) = app.submit_payment(&invoice_id, &TEN_DOLLARS_MIN_SATS.to_string()).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    app.prices.set_price("100000.00");
+    let (status, body) = app.submit_payment(&invoice_id, &TEN_DOLLARS_MIN_SATS.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn test_locked_quote_is_honored_over_current_price() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+
+    let (status, quote) = app.get(&format!("/invoices/{}/quote", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["amount"], "10100");
+
+    app.prices.set_price("50000.00");
+    let (status, body) = app.submit_payment(&invoice_id, &TEN_DOLLARS_MIN_SATS.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.prices.requests(), 1);
+}
+
+#[tokio::test]
+async fn test_price_feed_failure() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    app.prices.fail(503);
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["error"], "price_fetch_error");
+    assert!(app.bolt.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_expired_invoice_cannot_be_paid() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    app.set_deadline(&invoice_id, Utc::now() - chrono::Duration::seconds(1)).await;
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["error"], "invoice_expired");
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Expired);
+    assert!(app.bolt.requests().is_empty());
+
+    let (status, body) = app.get(&format!("/invoices/{}", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "expired");
+}
+
+#[tokio::test]
+async fn test_double_payment_is_refused() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+
+    let (status, _) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "invoice_already_paid");
+    assert_eq!(app.bolt.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_payments_broadcast_once() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    // Keep the first broadcast in flight while the second request arrives
+    app.bolt.push(FakeBoltBehavior::Hang { millis: 100 });
+
+    let (first, second) = tokio::join!(
+        app.submit_payment(&invoice_id, "20000"),
+        app.submit_payment(&invoice_id, "20000"),
+    );
+    let mut statuses = [first.0, second.0];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT], "{} / {}", first.1, second.1);
+
+    assert_eq!(app.bolt.requests().len(), 1);
+    let invoice_id = ObjectId::parse_str(&invoice_id).unwrap();
+    let payments = app.state.payment_repository.find_by_invoice_id(&invoice_id).await.unwrap();
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].status, PaymentStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn test_broadcast_refusal_rejects_payment_and_releases_invoice() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    app.bolt.push(FakeBoltBehavior::Fail { status: 400, body: "bad transaction".to_string() });
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR, "{}", body);
+    assert_eq!(body["error"], "transaction_broadcast_error");
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Created);
+
+    let object_id = ObjectId::parse_str(&invoice_id).unwrap();
+    let payments = app.state.payment_repository.find_by_invoice_id(&object_id).await.unwrap();
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].status, PaymentStatus::Rejected);
+}
+
+#[tokio::test]
+async fn test_unknown_broadcast_outcome_keeps_invoice_pending() {
+    let Some(app) = TestApp::start().await else { return };
+    // The transaction may have been broadcast in each case
+    let outcomes = [
+        FakeBoltBehavior::Fail { status: 502, body: "node unavailable".to_string() },
+        FakeBoltBehavior::Hang { millis: 2000 },
+        FakeBoltBehavior::Malformed { body: "not json".to_string() },
+        FakeBoltBehavior::Accept { txid: None, sender: None, amount: Some("lots".to_string()) },
+    ];
+
+    for outcome in outcomes {
+        let invoice_id = app.create_invoice("10.00").await;
+        app.bolt.push(outcome.clone());
+
+        let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+        assert_eq!(status, StatusCode::ACCEPTED, "{:?}: {}", outcome, body);
+        assert_eq!(body["status"], "accepted");
+        assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Pending);
+
+        let object_id = ObjectId::parse_str(&invoice_id).unwrap();
+        let payments = app.state.payment_repository.find_by_invoice_id(&object_id).await.unwrap();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].status, PaymentStatus::Accepted);
+
+        // Paying again could charge the customer twice
+        let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "payment_already_exists");
+    }
+}
+
+#[tokio::test]
+async fn test_reconciler_settles_payments_by_their_transaction() {
+    let Some(app) = TestApp::start().await else { return };
+    let context = AuditContext::new(Actor::System { worker: "test".to_string() }, None);
+    let transaction = |txid: &str, status| TransactionStatusResponse {
+        txid: txid.to_string(),
+        status,
+        sender: FAKE_SENDER_ADDRESS.to_string(),
+        amount: "20000".to_string(),
+    };
+    app.bolt.set_transaction(transaction("0xmined", TransactionStatus::Success));
+    app.bolt.set_transaction(transaction("0xdropped", TransactionStatus::Failed));
+    app.bolt.set_transaction(transaction("0xmempool", TransactionStatus::Pending));
+
+    // Payments left accepted by broadcasts whose outcome was lost
+    let mut cases = Vec::new();
+    for tx_id in [Some("0xmined"), Some("0xdropped"), Some("0xmempool"), None] {
+        let invoice = app.invoice(&app.create_invoice("10.00").await).await;
+        app.state.invoice_repository.claim_for_payment(&invoice, &context).await.unwrap();
+        let mut payment = Payment::new(invoice.id, PaymentToken::SBTC, 20_000);
+        payment.tx_id = tx_id.map(str::to_string);
+        payment.received_at = Utc::now() - chrono::Duration::minutes(5);
+        app.state.payment_repository.create(&payment, &context).await.unwrap();
+        cases.push((invoice.id, payment.id));
+    }
+    // Too recent, the request may still be confirming it
+    let recent = app.invoice(&app.create_invoice("10.00").await).await;
+    app.state.invoice_repository.claim_for_payment(&recent, &context).await.unwrap();
+    let mut payment = Payment::new(recent.id, PaymentToken::SBTC, 20_000);
+    payment.tx_id = Some("0xmined".to_string());
+    app.state.payment_repository.create(&payment, &context).await.unwrap();
+
+    let settled = PaymentReconciler::new(app.state.clone()).reconcile().await.unwrap();
+    assert_eq!(settled, 2);
+
+    let expected = [
+        (PaymentStatus::Confirmed, InvoiceStatus::Paid),
+        (PaymentStatus::Rejected, InvoiceStatus::Created),
+        (PaymentStatus::Accepted, InvoiceStatus::Pending),
+        (PaymentStatus::Accepted, InvoiceStatus::Pending),
+    ];
+    for ((invoice_id, payment_id), (payment_status, invoice_status)) in cases.into_iter().zip(expected) {
+        let payment = app.state.payment_repository.find_by_id(&payment_id).await.unwrap().unwrap();
+        assert_eq!(payment.status, payment_status, "{:?}", payment.tx_id);
+        if payment_status == PaymentStatus::Confirmed {
+            assert_eq!(payment.sender_address.as_deref(), Some(FAKE_SENDER_ADDRESS));
+        }
+        assert_eq!(app.invoice(&invoice_id.to_hex()).await.status, invoice_status, "{:?}", payment.tx_id);
+    }
+    assert_eq!(app.invoice(&recent.id.to_hex()).await.status, InvoiceStatus::Pending);
+}
+
+#[tokio::test]
+async fn test_payment_retried_after_broadcast_failure() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    app.bolt.push(FakeBoltBehavior::Fail { status: 400, body: "bad nonce".to_string() });
+
+    let (status, _) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.invoice(&invoice_id).await.status, InvoiceStatus::Paid);
+
+    let (status, body) = app.get(&format!("/invoices/{}/payments", invoice_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let statuses: Vec<&str> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|payment| payment["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses.len(), 2);
+    assert!(statuses.contains(&"rejected") && statuses.contains(&"confirmed"));
+}
+
+#[tokio::test]
+async fn test_rejected_before_broadcast() {
+    let Some(app) = TestApp::start().await else { return };
+    let invoice_id = app.create_invoice("10.00").await;
+    let submit = |invoice_id: String, body| {
+        let app = &app;
+        async move { app.post(&format!("/invoices/{}/payments/submit", invoice_id), body).await }
+    };
+    let payment = |amount: &str| {
+        json!({ "serialized_transaction": "0xdeadbeef", "asset": "sBTC", "amount": amount })
+    };
+
+    let cases = [
+        (invoice_id.clone(), payment("-5"), StatusCode::UNPROCESSABLE_ENTITY, "invalid_amount"),
+        (
+            invoice_id.clone(),
+            json!({ "serialized_transaction": "", "asset": "sBTC", "amount": "20000" }),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_transaction",
+        ),
+        (invoice_id.clone(), payment("20000.5"), StatusCode::BAD_REQUEST, "invalid_amount_format"),
+        ("not-an-id".to_string(), payment("20000"), StatusCode::BAD_REQUEST, "invalid_object_id"),
+        (ObjectId::new().to_hex(), payment("20000"), StatusCode::NOT_FOUND, "invoice_not_found"),
+    ];
+
+    for (invoice_id, body, expected_status, expected_error) in cases {
+        let (status, answer) = submit(invoice_id, body).await;
+        assert_eq!(status, expected_status, "{}", answer);
+        assert_eq!(answer["error"], expected_error);
+    }
+
+    assert!(app.bolt.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_merchant_payments_are_paged_newest_first() {
+    let Some(app) = TestApp::start().await else { return };
+    app.state.payment_repository.create_indexes().await.unwrap();
+    let mut tx_ids = Vec::new();
+    for i in 0..3 {
+        let invoice_id = app.create_invoice("10.00").await;
+        let tx_id = format!("0xpage{}", i);
+        app.bolt.push(FakeBoltBehavior::Accept { txid: Some(tx_id.clone()), sender: None, amount: None });
+        let (status, body) = app.submit_payment(&invoice_id, "20000").await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        tx_ids.push(tx_id);
+    }
+    let rejected = app.create_invoice("10.00").await;
+    app.bolt.push(FakeBoltBehavior::Fail { status: 400, body: "bad nonce".to_string() });
+    app.submit_payment(&rejected, "20000").await;
+
+    let uri = format!("/merchants/{}/payments", MERCHANT);
+    let (status, body) = app.get(&format!("{}?status=confirmed&limit=2&offset=1", uri)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["total"], 3);
+    let page: Vec<&str> = body["items"].as_array().unwrap().iter().map(|p| p["tx_id"].as_str().unwrap()).collect();
+    assert_eq!(page, [tx_ids[1].as_str(), tx_ids[0].as_str()]);
+
+    let (status, body) = app.get(&format!("{}?tx_id={}", uri, tx_ids[2])).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!((body["total"].as_i64(), body["items"][0]["tx_id"].as_str()), (Some(1), Some(tx_ids[2].as_str())));
+
+    let later = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let (status, body) = app.get(&format!("{}?from_date={}", uri, later)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["total"], 0);
+
+    let (status, body) = app.get("/merchants/ST1OTHER/payments").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_payments_across_merchants_need_a_tx_id() {
+    let app = TestApp::without_storage().await;
+    let (status, body) = app.get("/payments?status=confirmed").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error"], "missing_filter");
+}
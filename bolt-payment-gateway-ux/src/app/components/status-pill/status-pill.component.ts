@@ -1,5 +1,6 @@
 import { Component, Input } from '@angular/core';
 import { CommonModule } from '@angular/common';
+import { InvoiceStatus } from '../../services/gateway.service';
 
 @Component({
   selector: 'app-status-pill',
@@ -41,17 +42,29 @@ import { CommonModule } from '@angular/common';
       background: #e0e7ff;
       color: #4338ca;
     }
+
+    .status-pill.pending {
+      background: #fef3c7;
+      color: #b45309;
+    }
+
+    .status-pill.cancelled {
+      background: #f3f4f6;
+      color: #4b5563;
+    }
   `]
 })
 export class StatusPillComponent {
-  @Input() status: 'created' | 'paid' | 'expired' | 'settled' = 'created';
+  @Input() status: InvoiceStatus = 'created';
 
   get statusText(): string {
     switch (this.status) {
       case 'created': return 'Created';
+      case 'pending': return 'Pending';
       case 'paid': return 'Paid';
       case 'expired': return 'Expired';
       case 'settled': return 'Settled';
+      case 'cancelled': return 'Cancelled';
       default: return 'Unknown';
     }
   }
@@ -1,7 +1,7 @@
 import { Component, inject, OnInit, OnDestroy, signal } from '@angular/core';
 import { CommonModule } from '@angular/common';
 import { ActivatedRoute, Router } from '@angular/router';
-import { Subject, takeUntil, firstValueFrom } from 'rxjs';
+import { Subject, takeUntil, firstValueFrom, first, timeout } from 'rxjs';
 import { GatewayService, Invoice, Quote, SubmitPaymentRequest } from '../../services/gateway.service';
 import { WalletService } from '../../services/wallet.service';
 import { ToastService } from '../../services/toast.service';
@@ -1883,29 +1883,23 @@ export class CheckoutComponent implements OnInit, OnDestroy {
   }
 
   private pollPaymentStatus() {
-    // Poll every 3 seconds for payment confirmation
-    const pollInterval = setInterval(() => {
-      this.gatewayService.getInvoice(this.invoiceId)
-        .pipe(takeUntil(this.destroy$))
-        .subscribe({
-          next: (invoice) => {
-            if (invoice.status === 'paid' || invoice.status === 'settled') {
-              clearInterval(pollInterval);
-              this.paymentStatus.set('completed');
-              this.invoice.set(invoice);
-              this.toastService.success('Payment Confirmed!', 'Your payment has been confirmed');
-            }
-          },
-          error: () => {
-            // Continue polling on error
-          }
-        });
-    }, 3000);
-
-    // Stop polling after 5 minutes
-    setTimeout(() => {
-      clearInterval(pollInterval);
-    }, 300000);
+    // Wait for the payment confirmation pushed by the invoice event stream
+    this.gatewayService.watchInvoice(this.invoiceId)
+      .pipe(
+        first(invoice => invoice.status === 'paid' || invoice.status === 'settled'),
+        timeout(300000),
+        takeUntil(this.destroy$)
+      )
+      .subscribe({
+        next: (invoice) => {
+          this.paymentStatus.set('completed');
+          this.invoice.set(invoice);
+          this.toastService.success('Payment Confirmed!', 'Your payment has been confirmed');
+        },
+        error: () => {
+          // Stream ended without a confirmation, the invoice page shows the final state on reload
+        }
+      });
   }
 
   private async simulatePaymentProcess() {
@@ -21,14 +21,49 @@ export interface Quote {
   refreshed_at: string;
 }
 
+export type InvoiceStatus = 'created' | 'pending' | 'paid' | 'expired' | 'settled' | 'cancelled';
+
 export interface Invoice {
   invoice_id: string;
-  status: 'created' | 'paid' | 'expired' | 'settled';
+  status: InvoiceStatus;
   amount: string;
   settlement_asset: 'USD' | 'BRL';
   merchant_order_id: string;
   created_at: string;
   checkout_url: string;
+  recipient_address?: string;
+}
+
+export interface PaymentOption {
+  token: 'sBTC' | 'USDT';
+  amount_due: string;
+  decimals: number;
+  token_contract_id: string;
+  transfer_contract_id: string;
+  transfer_function: string;
+  fee: string;
+}
+
+export interface Checkout {
+  invoice_id: string;
+  status: InvoiceStatus;
+  merchant_name: string;
+  amount: string;
+  settlement_asset: 'USD' | 'BRL';
+  merchant_order_id: string;
+  description?: string;
+  expires_at?: string;
+  recipient_address: string;
+  memo: string;
+  quote: {
+    asset: string;
+    amount: string;
+    unit_price: string;
+    spread: string;
+    locked_at: string;
+    expires_at: string;
+  };
+  payment_options: PaymentOption[];
 }
 
 export interface PaymentResult {
@@ -62,7 +97,7 @@ export interface ListInvoicesResponse {
 }
 
 export interface ListInvoicesParams {
-  status?: 'created' | 'paid' | 'expired' | 'settled';
+  status?: InvoiceStatus;
   merchant_order_id?: string;
   from_date?: string;
   to_date?: string;
@@ -209,6 +244,29 @@ export class GatewayService {
     );
   }
 
+  /**
+   * Everything needed to build the transfer paying an invoice
+   * GET /invoices/{invoice_id}/checkout
+   */
+  getCheckout(invoiceId: string): Observable<Checkout> {
+    const url = `${this.baseUrl}/invoices/${invoiceId}/checkout`;
+    return this.http.get<Checkout>(url).pipe(
+      catchError(this.handleError)
+    );
+  }
+
+  /**
+   * URL of the QR code encoding the invoice's payment request.
+   * Pass the locked quote's `locked_at` so the image is fetched again when the quote refreshes.
+   * GET /invoices/{invoice_id}/qr
+   */
+  getPaymentQrCodeUrl(invoiceId: string, quoteLockedAt: string, format: 'png' | 'svg' = 'svg'): string {
+    const params = new HttpParams()
+      .set('format', format)
+      .set('v', quoteLockedAt);
+    return `${this.baseUrl}/invoices/${invoiceId}/qr?${params.toString()}`;
+  }
+
   /**
    * Submit a payment transaction for an invoice
    * POST /invoices/{invoice_id}/payments/submit
@@ -219,7 +277,7 @@ export class GatewayService {
     // get the serialized transaction and then submit payment
     return this.boltContractSBTCService.transferBoltToBolt(
       parseInt(amount),
-      environment.gatewayAddress,
+      invoice.recipient_address ?? environment.gatewayAddress,
       `BG-MID: ${invoice.merchant_order_id}`
     ).pipe(
       map((serialized_transaction: string) => {
@@ -281,10 +339,43 @@ export class GatewayService {
     });
   }
 
+  /**
+   * Follow an invoice through its Server-Sent Events stream
+   * GET /invoices/{invoice_id}/events
+   * Emits the invoice on subscribe and after every change, completes once it can no longer change.
+   */
+  watchInvoice(invoiceId: string): Observable<Invoice> {
+    return new Observable<Invoice>(observer => {
+      const source = new EventSource(`${this.baseUrl}/invoices/${invoiceId}/events`);
+
+      source.addEventListener('invoice', (event: MessageEvent) => {
+        const invoice = JSON.parse(event.data).data;
+        observer.next({
+          ...invoice,
+          invoice_id: invoice.id,
+          checkout_url: `${this.frontendUrl}/pay/${invoice.id}`
+        });
+        if (invoice.status === 'expired' || invoice.status === 'cancelled' || invoice.status === 'settled') {
+          source.close();
+          observer.complete();
+        }
+      });
+
+      source.onerror = () => {
+        // EventSource reconnects on its own unless the server refused the stream
+        if (source.readyState === EventSource.CLOSED) {
+          observer.error(new GatewayError('stream_error', 'Invoice event stream closed'));
+        }
+      };
+
+      return () => source.close();
+    });
+  }
+
   /**
    * Helper method to check invoice status
    */
-  checkInvoiceStatus(invoiceId: string): Observable<'created' | 'paid' | 'expired' | 'settled'> {
+  checkInvoiceStatus(invoiceId: string): Observable<InvoiceStatus> {
     return this.getInvoice(invoiceId).pipe(
       map(invoice => invoice.status)
     );
@@ -309,7 +400,7 @@ export class GatewayService {
 
         this.getInvoice(invoiceId).subscribe({
           next: (invoice) => {
-            if (invoice.status === 'paid' || invoice.status === 'expired' || invoice.status === 'settled') {
+            if (invoice.status === 'paid' || invoice.status === 'expired' || invoice.status === 'settled' || invoice.status === 'cancelled') {
               observer.next(invoice);
               observer.complete();
             } else {